@@ -0,0 +1,112 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use software_economics::computation::{self, Input};
+use software_economics::mrgame::MRGame;
+use software_economics::relation::{Alternative, Material, Product};
+use software_economics::{Constant, RRGame, Relation};
+
+const SUPPLIERS: usize = 40;
+const MATERIALS: usize = 200;
+const RETAILERS: usize = 10;
+const PRODUCTS: usize = 40;
+const ALTERNATIVES: usize = 120;
+const MODULES: usize = 30;
+
+/// A network much larger than the example scenario: every material has two
+/// suppliers, every retailer sells every product, and every product is built
+/// from three alternatives of three different modules.
+fn enlarged_relation() -> Relation {
+    let mut relation = Relation::new();
+
+    let mut pairs = Vec::new();
+    for l in 0..MATERIALS {
+        pairs.push((l % SUPPLIERS, l));
+        pairs.push(((l + 1) % SUPPLIERS, l));
+    }
+    relation.supplier_material_pairs(&pairs).unwrap();
+
+    let mut pairs = Vec::new();
+    for m in 0..RETAILERS {
+        for g in 0..PRODUCTS {
+            pairs.push((m, g));
+        }
+    }
+    relation.retailer_product_pairs(&pairs).unwrap();
+
+    let pairs: Vec<(usize, usize)> = (0..ALTERNATIVES).map(|k| (k, k % MODULES)).collect();
+    relation.alternative_module_pairs(&pairs).unwrap();
+
+    let pairs: Vec<(usize, usize)> = (0..MATERIALS).map(|l| (l, l % ALTERNATIVES)).collect();
+    relation.material_alternative_pairs(&pairs);
+
+    let mut pairs = Vec::new();
+    for g in 0..PRODUCTS {
+        for i in 0..3 {
+            pairs.push(((g + i * PRODUCTS) % ALTERNATIVES, g));
+        }
+    }
+    relation.alternative_product_pairs(&pairs);
+
+    relation
+}
+
+/// The linear scan the adjacency indexes replace.
+fn scan_suppliers_for_material(relation: &Relation, l: Material) -> usize {
+    relation
+        .supplier_materials()
+        .iter()
+        .filter(|p| p.1.id == l.id)
+        .count()
+}
+
+fn bench_queries(c: &mut Criterion) {
+    let relation = enlarged_relation();
+
+    c.bench_function("suppliers_for_material (scan)", |b| {
+        b.iter(|| {
+            let mut n = 0;
+            for l in relation.all_materials() {
+                n += scan_suppliers_for_material(&relation, black_box(l));
+            }
+            n
+        })
+    });
+    c.bench_function("suppliers_for_material (index)", |b| {
+        b.iter(|| {
+            let mut n = 0;
+            for l in relation.all_materials() {
+                n += relation.suppliers_for_material(black_box(l)).len();
+            }
+            n
+        })
+    });
+    c.bench_function("retailers (index)", |b| {
+        b.iter(|| {
+            let mut n = 0;
+            for g in relation.all_products() {
+                n += relation.retailers(black_box(g)).len();
+            }
+            n
+        })
+    });
+}
+
+fn bench_computation(c: &mut Criterion) {
+    let relation = enlarged_relation();
+    let constant = Constant::new(&relation);
+    let mrgame = MRGame::new(&relation);
+    let rrgame = RRGame::new(&relation);
+    let input = Input {
+        relation: &relation,
+        constant: &constant,
+        mrgame: &mrgame,
+        rrgame: &rrgame,
+    };
+
+    c.bench_function("NP0", |b| b.iter(|| computation::NP0(black_box(&input))));
+    c.bench_function("dA_DA", |b| {
+        b.iter(|| computation::dA_DA(black_box(&input), Alternative { id: 0 }, Product { id: 0 }))
+    });
+}
+
+criterion_group!(benches, bench_queries, bench_computation);
+criterion_main!(benches);
@@ -0,0 +1,185 @@
+//! Lower and upper bounds on the decision variables of both games. The
+//! solvers keep every variable within its bounds and report the bounds that
+//! are active at the solution.
+
+use crate::computation::comp_generic::Variable;
+use crate::relation::{MaterialMap, ProductMap, Relation, RetailerMap, SupplierMap};
+
+/// Smallest cycle time `c_m` and `crm_s` may take by default: the ordering
+/// costs divide by them.
+pub const MIN_CYCLE_TIME: f64 = 0.000001;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bound {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Which bound of its `Bound` a variable is held at.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BoundSide {
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ActiveBound {
+    pub variable: Variable,
+    pub side: BoundSide,
+    /// Non-negative at a solution: how fast the objective would grow if the
+    /// bound were relaxed.
+    pub multiplier: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounds {
+    pub p_mg: RetailerMap<ProductMap<Bound>>,
+    pub a_mg: RetailerMap<ProductMap<Bound>>,
+    pub A_g: ProductMap<Bound>,
+    pub rho_g: ProductMap<Bound>,
+    pub c_m: RetailerMap<Bound>,
+    pub crm_s: SupplierMap<Bound>,
+    pub drm_sl: SupplierMap<MaterialMap<Bound>>,
+}
+
+impl Bound {
+    pub fn new(lower: f64, upper: f64) -> Self {
+        Bound { lower, upper }
+    }
+
+    pub fn unbounded() -> Self {
+        Bound::new(f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub fn non_negative() -> Self {
+        Bound::new(0.0, f64::INFINITY)
+    }
+
+    pub fn clamp(&self, x: f64) -> f64 {
+        x.max(self.lower).min(self.upper)
+    }
+
+    pub fn value(&self, side: BoundSide) -> f64 {
+        match side {
+            BoundSide::Lower => self.lower,
+            BoundSide::Upper => self.upper,
+        }
+    }
+
+    /// The bound `x` lies beyond by more than `epsilon`, if any.
+    pub fn violated(&self, x: f64, epsilon: f64) -> Option<BoundSide> {
+        if x < self.lower - epsilon {
+            Some(BoundSide::Lower)
+        } else if x > self.upper + epsilon {
+            Some(BoundSide::Upper)
+        } else {
+            None
+        }
+    }
+
+    /// The bound `x` lies at, within `epsilon`, when the derivative
+    /// `gradient` of a maximised objective points out of the box there.
+    pub fn blocking(&self, x: f64, gradient: f64, epsilon: f64) -> Option<BoundSide> {
+        if x <= self.lower + epsilon && gradient < -epsilon {
+            Some(BoundSide::Lower)
+        } else if x >= self.upper - epsilon && gradient > epsilon {
+            Some(BoundSide::Upper)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the box is empty or a bound is NaN.
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_nan() || self.upper.is_nan() || self.lower > self.upper
+    }
+}
+
+impl ActiveBound {
+    /// The bounds `held` holds `variables` at, with the multipliers read off
+    /// the stationarity conditions `gradient`, i.e. `∂L/∂x_i`, of a
+    /// maximisation.
+    pub fn collect(
+        variables: &[Variable],
+        held: &[Option<BoundSide>],
+        gradient: &[f64],
+    ) -> Vec<ActiveBound> {
+        let mut result = Vec::new();
+        for (i, side) in held.iter().enumerate() {
+            if let Some(side) = *side {
+                let multiplier = match side {
+                    BoundSide::Lower => -gradient[i],
+                    BoundSide::Upper => gradient[i],
+                };
+                result.push(ActiveBound {
+                    variable: variables[i],
+                    side,
+                    multiplier,
+                });
+            }
+        }
+        result
+    }
+
+    pub fn label(&self, relation: &Relation) -> String {
+        let side = match self.side {
+            BoundSide::Lower => "lower",
+            BoundSide::Upper => "upper",
+        };
+        format!("{} at {} bound", self.variable.label(relation), side)
+    }
+}
+
+impl Bounds {
+    /// Prices, advertising, orders and `A_g` are non-negative, cycle times at
+    /// least `MIN_CYCLE_TIME`, and `rho_g` is free.
+    pub fn new(relation: &Relation) -> Self {
+        let cycle_time = Bound::new(MIN_CYCLE_TIME, f64::INFINITY);
+        Bounds {
+            p_mg: RetailerMap::new(relation, ProductMap::new(relation, Bound::non_negative())),
+            a_mg: RetailerMap::new(relation, ProductMap::new(relation, Bound::non_negative())),
+            A_g: ProductMap::new(relation, Bound::non_negative()),
+            rho_g: ProductMap::new(relation, Bound::unbounded()),
+            c_m: RetailerMap::new(relation, cycle_time),
+            crm_s: SupplierMap::new(relation, cycle_time),
+            drm_sl: SupplierMap::new(relation, MaterialMap::new(relation, Bound::non_negative())),
+        }
+    }
+
+    pub fn bound(&self, variable: Variable) -> Bound {
+        match variable {
+            Variable::p_mg(m, g) => self.p_mg[m][g],
+            Variable::a_mg(m, g) => self.a_mg[m][g],
+            Variable::A_g(g) => self.A_g[g],
+            Variable::rho_g(g) => self.rho_g[g],
+            Variable::c_m(m) => self.c_m[m],
+            Variable::crm_s(s) => self.crm_s[s],
+            Variable::drm_sl(s, l) => self.drm_sl[s][l],
+        }
+    }
+
+    /// Every variable of both games with its bound.
+    pub fn all(&self, relation: &Relation) -> Vec<(Variable, Bound)> {
+        let mut result = Vec::new();
+        for m in relation.initial_retailers() {
+            for &g in relation.initial_products(m) {
+                result.push((Variable::p_mg(m, g), self.p_mg[m][g]));
+                result.push((Variable::a_mg(m, g), self.a_mg[m][g]));
+            }
+        }
+        for g in relation.all_products() {
+            result.push((Variable::A_g(g), self.A_g[g]));
+            result.push((Variable::rho_g(g), self.rho_g[g]));
+        }
+        for m in relation.initial_retailers() {
+            result.push((Variable::c_m(m), self.c_m[m]));
+        }
+        for s in relation.all_suppliers() {
+            result.push((Variable::crm_s(s), self.crm_s[s]));
+            for &l in relation.materials(s) {
+                result.push((Variable::drm_sl(s, l), self.drm_sl[s][l]));
+            }
+        }
+        result
+    }
+}
@@ -1,14 +1,24 @@
+pub mod comp_generic;
 pub mod comp_mrgame;
 
+use crate::dual::{Dual, HyperDual};
 use crate::mrgame::MRGame;
 use crate::relation::Alternative;
 use crate::relation::Constant;
+use crate::relation::CrossMap;
+use crate::relation::Material;
 use crate::relation::Product;
+use crate::relation::ProductMap;
 use crate::relation::Relation;
 use crate::relation::Retailer;
+use crate::relation::RetailerMap;
 use crate::rrgame::RRGame;
+use comp_generic::{Demand, DualInput, Seed, Variable};
 pub use comp_mrgame::*;
+use ndarray::{Array2, Array4, Axis, Zip};
 
+/// Everything the model functions read: `DP`, `NP`, `NP0` and their
+/// derivatives are evaluated at the parameters of `mrgame` and `rrgame`.
 pub struct Input<'a, 'b, 'c, 'd> {
     pub relation: &'a Relation,
     pub constant: &'b Constant,
@@ -16,6 +26,99 @@ pub struct Input<'a, 'b, 'c, 'd> {
     pub rrgame: &'d RRGame,
 }
 
+/// The model at one `Input`, with every `DP` and `pw` computed once and read
+/// by `NP`, `NP0`, the constraints and the derivatives. The hand-written
+/// derivatives that read `DP` or `pw` take an `Evaluation` for the same
+/// reason. The results agree with those of the functions taking an `Input`
+/// up to rounding.
+pub struct Evaluation<'i, 'a, 'b, 'c, 'd> {
+    input: &'i Input<'a, 'b, 'c, 'd>,
+    demand: Demand,
+}
+
+impl<'i, 'a, 'b, 'c, 'd> Evaluation<'i, 'a, 'b, 'c, 'd> {
+    pub fn new(input: &'i Input<'a, 'b, 'c, 'd>) -> Self {
+        Evaluation {
+            input,
+            demand: Demand::new(input),
+        }
+    }
+
+    pub fn input(&self) -> &'i Input<'a, 'b, 'c, 'd> {
+        self.input
+    }
+
+    pub fn dual(&self) -> DualInput<'_, 'a, 'b, 'c, 'd> {
+        DualInput::with_demand(self.input, &self.demand)
+    }
+
+    pub fn DP(&self, m: Retailer, g: Product) -> f64 {
+        self.demand.DP_mg[m][g]
+    }
+
+    pub fn pw(&self, m: Retailer, g: Product) -> f64 {
+        self.demand.pw_mg[m][g]
+    }
+
+    pub fn DA(&self, k: Alternative) -> f64 {
+        comp_generic::DA(&self.dual(), k)
+    }
+
+    pub fn NP(&self, m: Retailer) -> f64 {
+        comp_generic::NP(&self.dual(), m)
+    }
+
+    pub fn TVR_constraint(&self, m: Retailer) -> f64 {
+        comp_generic::TVR_constraint(&self.dual(), m)
+    }
+
+    pub fn Ta_constraint(&self, m: Retailer) -> f64 {
+        comp_generic::Ta_constraint(&self.dual(), m)
+    }
+
+    pub fn NP0(&self) -> f64 {
+        comp_generic::NP0(&self.dual())
+    }
+
+    pub fn NP0_bom_constraint(&self, l: Material) -> f64 {
+        comp_generic::NP0_bom_constraint(&self.dual(), l)
+    }
+
+    pub fn NP0_TVP_constraint(&self) -> f64 {
+        comp_generic::NP0_TVP_constraint(&self.dual())
+    }
+
+    /// `comp_generic::derivative`, reading the cached demand unless `x`
+    /// moves it.
+    pub fn derivative(&self, x: Variable, f: impl Fn(&DualInput) -> Dual) -> f64 {
+        f(&self.dual().seed(Some(x), None)).eps
+    }
+
+    pub fn second_derivative(
+        &self,
+        x: Variable,
+        y: Variable,
+        f: impl Fn(&DualInput) -> HyperDual,
+    ) -> f64 {
+        f(&self.dual().seed(Some(x), Some(y))).e12
+    }
+
+    /// `derivative` along the direction `x`.
+    pub fn derivative_along(&self, x: Seed, f: impl Fn(&DualInput) -> Dual) -> f64 {
+        f(&self.dual().seed_along(Some(x), None)).eps
+    }
+
+    /// `second_derivative` along the directions `x` and `y`.
+    pub fn second_derivative_along(
+        &self,
+        x: Seed,
+        y: Seed,
+        f: impl Fn(&DualInput) -> HyperDual,
+    ) -> f64 {
+        f(&self.dual().seed_along(Some(x), Some(y))).e12
+    }
+}
+
 fn safe_pow(a: f64, n: f64) -> f64 {
     if n == 0.0 {
         1.0
@@ -27,61 +130,89 @@ fn safe_pow(a: f64, n: f64) -> f64 {
 }
 
 pub fn DP(input: &Input, m: Retailer, g: Product) -> f64 {
-    let K_mg = &input.constant.K_mg;
-    let u_mgy = &input.constant.u_mgy;
-    let eA_mgy = &input.constant.eA_mgy;
-    let A_g = &input.mrgame.parameter.A_g;
+    comp_generic::DP(&DualInput::new(input), m, g)
+}
+
+/// `[x, y]` is whether retailer `x` sells product `y` under the decision.
+fn sold_xy(input: &Input) -> Array2<bool> {
     let relation = input.relation;
     let decision = &input.mrgame.decision;
 
-    let beta_mgxy = &input.constant.beta_mgxy;
-    let ep_mgxy = &input.constant.ep_mgxy;
-
-    let v_mgxy = &input.constant.v_mgxy;
-    let ea_mgxy = &input.constant.ea_mgxy;
-
-    let p_mg = &input.rrgame.parameter.p_mg;
-    let a_mg = &input.rrgame.parameter.a_mg;
-
-    let mut sum = K_mg[m][g];
-    for y in relation.products(m, decision) {
-        sum += u_mgy[m][g][y] * safe_pow(A_g[y], eA_mgy[m][g][y]);
-    }
-
+    let shape = (
+        relation.initial_retailers().count(),
+        relation.all_products().count(),
+    );
+    let mut sold = Array2::from_elem(shape, false);
     for x in relation.initial_retailers() {
         for y in relation.products(x, decision) {
-            sum += beta_mgxy[m][g][x][y] * safe_pow(p_mg[x][y], ep_mgxy[m][g][x][y]);
-            sum += v_mgxy[m][g][x][y] * safe_pow(a_mg[x][y], ea_mgxy[m][g][x][y]);
+            sold[(x.id, y.id)] = true;
         }
     }
-
-    sum
+    sold
 }
 
-pub fn pw(input: &Input, m: Retailer, g: Product) -> f64 {
-    let pw_g0 = &input.constant.pw_g0;
-    let rho_g = &input.mrgame.parameter.rho_g;
-
-    pw_g0[g] - rho_g[g] * DP(input, m, g)
+/// `[m, g]` is the sum of `coefficient[(m, g, x, y)] * value[x, y]^exponent[(m, g, x, y)]`
+/// over the sold pairs `(x, y)`.
+fn cross_sum(
+    coefficient: &CrossMap,
+    exponent: &CrossMap,
+    value: &Array2<f64>,
+    sold: &Array2<bool>,
+) -> Array2<f64> {
+    let shape = coefficient.array().dim();
+    let value = value.broadcast(shape).unwrap();
+    let sold = sold.broadcast(shape).unwrap();
+
+    let mut terms = Array4::zeros(shape);
+    Zip::from(&mut terms)
+        .and(coefficient.array())
+        .and(exponent.array())
+        .and(&value)
+        .and(&sold)
+        .apply(|term, c, e, v, sold| {
+            if *sold {
+                *term = c * safe_pow(*v, *e);
+            }
+        });
+    terms.sum_axis(Axis(3)).sum_axis(Axis(2))
 }
 
-pub fn NP(input: &Input, m: Retailer) -> f64 {
-    let p_mg = &input.rrgame.parameter.p_mg;
-    let a_mg = &input.rrgame.parameter.a_mg;
-    let zeta_mg = &input.constant.zeta_mg;
+/// `DP` of every retailer and product at once. The cross terms are
+/// contractions of the `CrossMap` constants, which pays off over calling `DP`
+/// pair by pair when there are many retailers.
+pub fn DP_mg(input: &Input) -> RetailerMap<ProductMap<f64>> {
+    let K_mg = &input.constant.K_mg;
+    let u_mgy = &input.constant.u_mgy;
+    let eA_mgy = &input.constant.eA_mgy;
+    let A_g = &input.mrgame.parameter.A_g;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
+    let constant = input.constant;
+
+    let p_xy = input.rrgame.parameter.p_mg.to_array2();
+    let a_xy = input.rrgame.parameter.a_mg.to_array2();
+    let sold = sold_xy(input);
+    let cross = cross_sum(&constant.beta_mgxy, &constant.ep_mgxy, &p_xy, &sold)
+        + cross_sum(&constant.v_mgxy, &constant.ea_mgxy, &a_xy, &sold);
+
+    RetailerMap::from_fn(relation, |m| {
+        let products: Vec<_> = relation.products(m, decision).collect();
+        ProductMap::from_fn(relation, |g| {
+            let mut sum = K_mg[m][g] + cross[(m.id, g.id)];
+            for y in products.iter() {
+                sum += u_mgy[m][g][*y] * safe_pow(A_g[*y], eA_mgy[m][g][*y]);
+            }
+            sum
+        })
+    })
+}
 
-    let mut sum = 0.0;
-
-    for g in relation.products(m, decision) {
-        sum += DP(input, m, g) * p_mg[m][g];
-        sum -= DP(input, m, g) * pw(input, m, g);
-        sum -= zeta_mg[m][g] * DP(input, m, g);
-        sum -= a_mg[m][g];
-    }
+pub fn pw(input: &Input, m: Retailer, g: Product) -> f64 {
+    comp_generic::pw(&DualInput::new(input), m, g)
+}
 
-    sum
+pub fn NP(input: &Input, m: Retailer) -> f64 {
+    comp_generic::NP(&DualInput::new(input), m)
 }
 
 pub fn dp_DP(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
@@ -89,10 +220,11 @@ pub fn dp_DP(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     let beta_mgxy = &input.constant.beta_mgxy;
     let ep_mgxy = &input.constant.ep_mgxy;
 
-    beta_mgxy[m][g][m][j] * ep_mgxy[m][g][m][j] * safe_pow(p_mg[m][j], ep_mgxy[m][g][m][j] - 1.0)
+    beta_mgxy[(m, g, m, j)]
+        * ep_mgxy[(m, g, m, j)]
+        * safe_pow(p_mg[m][j], ep_mgxy[(m, g, m, j)] - 1.0)
 }
 
-#[allow(dead_code)]
 pub fn dp_DP_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     rrgame.parameter.p_mg[m][j] += 0.01;
@@ -110,10 +242,9 @@ pub fn da_DP(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     let v_mgxy = &input.constant.v_mgxy;
     let ea_mgxy = &input.constant.ea_mgxy;
 
-    v_mgxy[m][g][m][j] * ea_mgxy[m][g][m][j] * safe_pow(a_mg[m][j], ea_mgxy[m][g][m][j] - 1.0)
+    v_mgxy[(m, g, m, j)] * ea_mgxy[(m, g, m, j)] * safe_pow(a_mg[m][j], ea_mgxy[(m, g, m, j)] - 1.0)
 }
 
-#[allow(dead_code)]
 pub fn da_DP_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     rrgame.parameter.a_mg[m][j] += 0.01;
@@ -131,18 +262,19 @@ pub fn dp_pw(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     -rho_g[g] * dp_DP(input, m, g, j)
 }
 
-pub fn dp_NP(input: &Input, m: Retailer, j: Product) -> f64 {
+pub fn dp_NP(evaluation: &Evaluation, m: Retailer, j: Product) -> f64 {
+    let input = evaluation.input;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
     let p_mg = &input.rrgame.parameter.p_mg;
     let zeta_mg = &input.constant.zeta_mg;
 
-    let mut sum = DP(input, m, j);
+    let mut sum = evaluation.DP(m, j);
     for g in relation.products(m, decision) {
         sum += dp_DP(input, m, g, j) * p_mg[m][g];
 
-        sum -= dp_DP(input, m, g, j) * pw(input, m, g);
-        sum -= DP(input, m, g) * dp_pw(input, m, g, j);
+        sum -= dp_DP(input, m, g, j) * evaluation.pw(m, g);
+        sum -= evaluation.DP(m, g) * dp_pw(input, m, g, j);
 
         sum -= zeta_mg[m][g] * dp_DP(input, m, g, j);
     }
@@ -150,7 +282,6 @@ pub fn dp_NP(input: &Input, m: Retailer, j: Product) -> f64 {
     sum
 }
 
-#[allow(dead_code)]
 pub fn dp_NP_approx(input: &Input, m: Retailer, j: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     rrgame.parameter.p_mg[m][j] += 0.01;
@@ -168,7 +299,6 @@ pub fn da_pw(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     -rho_g[g] * da_DP(input, m, g, j)
 }
 
-#[allow(dead_code)]
 pub fn da_pw_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     rrgame.parameter.a_mg[m][j] += 0.01;
@@ -181,7 +311,8 @@ pub fn da_pw_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     (pw(&new_input, m, g) - pw(input, m, g)) / 0.01
 }
 
-pub fn da_NP(input: &Input, m: Retailer, j: Product) -> f64 {
+pub fn da_NP(evaluation: &Evaluation, m: Retailer, j: Product) -> f64 {
+    let input = evaluation.input;
     let p_mg = &input.rrgame.parameter.p_mg;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
@@ -191,8 +322,8 @@ pub fn da_NP(input: &Input, m: Retailer, j: Product) -> f64 {
 
     for g in relation.products(m, decision) {
         sum += da_DP(input, m, g, j) * p_mg[m][g];
-        sum -= da_DP(input, m, g, j) * pw(input, m, g);
-        sum -= DP(input, m, g) * da_pw(input, m, g, j);
+        sum -= da_DP(input, m, g, j) * evaluation.pw(m, g);
+        sum -= evaluation.DP(m, g) * da_pw(input, m, g, j);
         sum -= zeta_mg[m][g] * da_DP(input, m, g, j);
     }
 
@@ -201,7 +332,6 @@ pub fn da_NP(input: &Input, m: Retailer, j: Product) -> f64 {
     sum
 }
 
-#[allow(dead_code)]
 pub fn da_NP_approx(input: &Input, m: Retailer, j: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     let delta = 0.001;
@@ -215,7 +345,184 @@ pub fn da_NP_approx(input: &Input, m: Retailer, j: Product) -> f64 {
     (NP(&new_input, m) - NP(input, m)) / delta
 }
 
-pub fn dpdp_NP(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+pub fn drho_pw(evaluation: &Evaluation, m: Retailer, g: Product, j: Product) -> f64 {
+    if g.id == j.id {
+        -evaluation.DP(m, g)
+    } else {
+        0.0
+    }
+}
+
+pub fn drho_pw_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.rho_g[j] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (pw(&new_input, m, g) - pw(input, m, g)) / 0.0001
+}
+
+pub fn drho_NP(evaluation: &Evaluation, m: Retailer, j: Product) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut sum = 0.0;
+    for g in relation.products(m, decision) {
+        sum -= evaluation.DP(m, g) * drho_pw(evaluation, m, g, j);
+    }
+
+    sum
+}
+
+pub fn drho_NP_approx(input: &Input, m: Retailer, j: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.rho_g[j] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (NP(&new_input, m) - NP(input, m)) / 0.0001
+}
+
+/// Derivative of `dp_NP(m, j)` with respect to `rho_k`. Only `pw(m, k)` and
+/// `dp_pw(m, k, j)` depend on `rho_k`.
+pub fn drhodp_NP(evaluation: &Evaluation, m: Retailer, j: Product, k: Product) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut sum = 0.0;
+    for g in relation.products(m, decision) {
+        if g.id == k.id {
+            sum += 2.0 * evaluation.DP(m, g) * dp_DP(input, m, g, j);
+        }
+    }
+
+    sum
+}
+
+pub fn drhodp_NP_approx(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.rho_g[k] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dp_NP(&Evaluation::new(&new_input), m, j) - dp_NP(&Evaluation::new(input), m, j)) / 0.0001
+}
+
+/// Derivative of `da_NP(m, j)` with respect to `rho_k`.
+pub fn drhoda_NP(evaluation: &Evaluation, m: Retailer, j: Product, k: Product) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut sum = 0.0;
+    for g in relation.products(m, decision) {
+        if g.id == k.id {
+            sum += 2.0 * evaluation.DP(m, g) * da_DP(input, m, g, j);
+        }
+    }
+
+    sum
+}
+
+pub fn drhoda_NP_approx(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.rho_g[k] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (da_NP(&Evaluation::new(&new_input), m, j) - da_NP(&Evaluation::new(input), m, j)) / 0.0001
+}
+
+pub fn dpdp_DP(input: &Input, m: Retailer, g: Product, j: Product, k: Product) -> f64 {
+    let p_mg = &input.rrgame.parameter.p_mg;
+    let beta_mgxy = &input.constant.beta_mgxy;
+    let ep_mgxy = &input.constant.ep_mgxy;
+
+    if j.id != k.id {
+        return 0.0;
+    }
+
+    let ep = ep_mgxy[(m, g, m, j)];
+
+    beta_mgxy[(m, g, m, j)] * ep * (ep - 1.0) * safe_pow(p_mg[m][j], ep - 2.0)
+}
+
+pub fn dpdp_DP_approx(input: &Input, m: Retailer, g: Product, j: Product, k: Product) -> f64 {
+    let mut rrgame = input.rrgame.clone();
+    rrgame.parameter.p_mg[m][k] += 0.0001;
+
+    let new_input = Input {
+        rrgame: &rrgame,
+        ..(*input)
+    };
+
+    (dp_DP(&new_input, m, g, j) - dp_DP(input, m, g, j)) / 0.0001
+}
+
+pub fn dada_DP(input: &Input, m: Retailer, g: Product, j: Product, k: Product) -> f64 {
+    let a_mg = &input.rrgame.parameter.a_mg;
+    let v_mgxy = &input.constant.v_mgxy;
+    let ea_mgxy = &input.constant.ea_mgxy;
+
+    if j.id != k.id {
+        return 0.0;
+    }
+
+    let ea = ea_mgxy[(m, g, m, j)];
+
+    v_mgxy[(m, g, m, j)] * ea * (ea - 1.0) * safe_pow(a_mg[m][j], ea - 2.0)
+}
+
+pub fn dada_DP_approx(input: &Input, m: Retailer, g: Product, j: Product, k: Product) -> f64 {
+    let mut rrgame = input.rrgame.clone();
+    rrgame.parameter.a_mg[m][k] += 0.0001;
+
+    let new_input = Input {
+        rrgame: &rrgame,
+        ..(*input)
+    };
+
+    (da_DP(&new_input, m, g, j) - da_DP(input, m, g, j)) / 0.0001
+}
+
+/// Derivative of `dp_NP(m, j)` with respect to `p_mk`.
+pub fn dpdp_NP(evaluation: &Evaluation, m: Retailer, j: Product, k: Product) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+    let p_mg = &input.rrgame.parameter.p_mg;
+    let rho_g = &input.mrgame.parameter.rho_g;
+    let zeta_mg = &input.constant.zeta_mg;
+
+    let mut sum = dp_DP(input, m, j, k) + dp_DP(input, m, k, j);
+    for g in relation.products(m, decision) {
+        sum += dpdp_DP(input, m, g, j, k) * p_mg[m][g];
+
+        sum -= dpdp_DP(input, m, g, j, k) * evaluation.pw(m, g);
+        sum += dpdp_DP(input, m, g, j, k) * rho_g[g] * evaluation.DP(m, g);
+        sum += 2.0 * rho_g[g] * dp_DP(input, m, g, j) * dp_DP(input, m, g, k);
+
+        sum -= zeta_mg[m][g] * dpdp_DP(input, m, g, j, k);
+    }
+
+    sum
+}
+
+pub fn dpdp_NP_approx(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     let delta = 0.0001;
     rrgame.parameter.p_mg[m][k] += delta;
@@ -225,39 +532,92 @@ pub fn dpdp_NP(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
         ..(*input)
     };
 
-    (dp_NP(&new_input, m, j) - dp_NP(input, m, j)) / delta
+    (dp_NP(&Evaluation::new(&new_input), m, j) - dp_NP(&Evaluation::new(input), m, j)) / delta
 }
 
-#[allow(dead_code)]
-pub fn compute_TVR_m(input: &Input, m: Retailer) -> f64 {
-    let w_m = &input.constant.w_m;
-    let c_m = &input.mrgame.parameter.c_m;
-    let V_g = &input.constant.V_g;
+/// Derivative of `dp_NP(m, j)` with respect to `a_mk`, which is also the
+/// derivative of `da_NP(m, k)` with respect to `p_mj`. DP has no term mixing
+/// prices and advertising.
+pub fn dpda_NP(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+    let rho_g = &input.mrgame.parameter.rho_g;
+
+    let mut sum = da_DP(input, m, j, k);
+    for g in relation.products(m, decision) {
+        sum += 2.0 * rho_g[g] * dp_DP(input, m, g, j) * da_DP(input, m, g, k);
+    }
+
+    sum
+}
+
+pub fn dpda_NP_approx(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let mut rrgame = input.rrgame.clone();
+    let delta = 0.0001;
+    rrgame.parameter.a_mg[m][k] += delta;
+
+    let new_input = Input {
+        rrgame: &rrgame,
+        ..(*input)
+    };
+
+    (dp_NP(&Evaluation::new(&new_input), m, j) - dp_NP(&Evaluation::new(input), m, j)) / delta
+}
+
+/// Derivative of `da_NP(m, j)` with respect to `a_mk`.
+pub fn dada_NP(evaluation: &Evaluation, m: Retailer, j: Product, k: Product) -> f64 {
+    let input = evaluation.input;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
+    let p_mg = &input.rrgame.parameter.p_mg;
+    let rho_g = &input.mrgame.parameter.rho_g;
+    let zeta_mg = &input.constant.zeta_mg;
 
     let mut sum = 0.0;
     for g in relation.products(m, decision) {
-        sum += c_m[m] * DP(input, m, g) * V_g[g] / 2.0;
+        sum += dada_DP(input, m, g, j, k) * p_mg[m][g];
+
+        sum -= dada_DP(input, m, g, j, k) * evaluation.pw(m, g);
+        sum += dada_DP(input, m, g, j, k) * rho_g[g] * evaluation.DP(m, g);
+        sum += 2.0 * rho_g[g] * da_DP(input, m, g, j) * da_DP(input, m, g, k);
+
+        sum -= zeta_mg[m][g] * dada_DP(input, m, g, j, k);
     }
 
-    w_m[m] * sum
+    sum
 }
 
-pub fn TVR_constraint(input: &Input, m: Retailer) -> f64 {
+pub fn dada_NP_approx(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let mut rrgame = input.rrgame.clone();
+    let delta = 0.0001;
+    rrgame.parameter.a_mg[m][k] += delta;
+
+    let new_input = Input {
+        rrgame: &rrgame,
+        ..(*input)
+    };
+
+    (da_NP(&Evaluation::new(&new_input), m, j) - da_NP(&Evaluation::new(input), m, j)) / delta
+}
+
+pub fn compute_TVR_m(evaluation: &Evaluation, m: Retailer) -> f64 {
+    let input = evaluation.input;
     let w_m = &input.constant.w_m;
     let c_m = &input.mrgame.parameter.c_m;
     let V_g = &input.constant.V_g;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
-    let TVR_m = &input.constant.TVR_m;
 
     let mut sum = 0.0;
     for g in relation.products(m, decision) {
-        sum += c_m[m] * DP(input, m, g) * V_g[g] / 2.0;
+        sum += c_m[m] * evaluation.DP(m, g) * V_g[g] / 2.0;
     }
 
-    w_m[m] * sum - TVR_m[m]
+    w_m[m] * sum
+}
+
+pub fn TVR_constraint(input: &Input, m: Retailer) -> f64 {
+    comp_generic::TVR_constraint(&DualInput::new(input), m)
 }
 
 pub fn dp_TVR_constraint(input: &Input, m: Retailer, j: Product) -> f64 {
@@ -276,7 +636,6 @@ pub fn dp_TVR_constraint(input: &Input, m: Retailer, j: Product) -> f64 {
     w_m[m] * sum
 }
 
-#[allow(dead_code)]
 pub fn dp_TVR_constraint_approx(input: &Input, m: Retailer, j: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     let delta = 0.001;
@@ -306,7 +665,6 @@ pub fn da_TVR_constraint(input: &Input, m: Retailer, j: Product) -> f64 {
     w_m[m] * sum
 }
 
-#[allow(dead_code)]
 pub fn da_TVR_constraint_approx(input: &Input, m: Retailer, j: Product) -> f64 {
     let mut rrgame = input.rrgame.clone();
     let delta = 0.001;
@@ -320,20 +678,70 @@ pub fn da_TVR_constraint_approx(input: &Input, m: Retailer, j: Product) -> f64 {
     (TVR_constraint(&new_input, m) - TVR_constraint(input, m)) / delta
 }
 
-pub fn Ta_constraint(input: &Input, m: Retailer) -> f64 {
+/// Derivative of `dp_TVR_constraint(m, j)` with respect to `p_mk`. The
+/// constraint has no term mixing prices and advertising.
+pub fn dpdp_TVR_constraint(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
     let relation = input.relation;
     let decision = &input.mrgame.decision;
-    let a_mg = &input.rrgame.parameter.a_mg;
-    let Ta_m = &input.constant.Ta_m;
+    let w_m = &input.constant.w_m;
+    let c_m = &input.mrgame.parameter.c_m;
+    let V_g = &input.constant.V_g;
+
+    let mut sum = 0.0;
+
+    for g in relation.products(m, decision) {
+        sum += c_m[m] * dpdp_DP(input, m, g, j, k) * V_g[g] / 2.0;
+    }
+
+    w_m[m] * sum
+}
+
+pub fn dpdp_TVR_constraint_approx(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let mut rrgame = input.rrgame.clone();
+    let delta = 0.0001;
+    rrgame.parameter.p_mg[m][k] += delta;
+
+    let new_input = Input {
+        rrgame: &rrgame,
+        ..(*input)
+    };
+
+    (dp_TVR_constraint(&new_input, m, j) - dp_TVR_constraint(input, m, j)) / delta
+}
+
+pub fn dada_TVR_constraint(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+    let w_m = &input.constant.w_m;
+    let c_m = &input.mrgame.parameter.c_m;
+    let V_g = &input.constant.V_g;
 
     let mut sum = 0.0;
+
     for g in relation.products(m, decision) {
-        sum += a_mg[m][g];
+        sum += c_m[m] * dada_DP(input, m, g, j, k) * V_g[g] / 2.0;
     }
 
-    sum - Ta_m[m]
+    w_m[m] * sum
+}
+
+pub fn dada_TVR_constraint_approx(input: &Input, m: Retailer, j: Product, k: Product) -> f64 {
+    let mut rrgame = input.rrgame.clone();
+    let delta = 0.0001;
+    rrgame.parameter.a_mg[m][k] += delta;
+
+    let new_input = Input {
+        rrgame: &rrgame,
+        ..(*input)
+    };
+
+    (da_TVR_constraint(&new_input, m, j) - da_TVR_constraint(input, m, j)) / delta
+}
+
+pub fn Ta_constraint(input: &Input, m: Retailer) -> f64 {
+    comp_generic::Ta_constraint(&DualInput::new(input), m)
 }
 
-pub fn da_Ta_constraint(input: &Input, _m: Retailer, _j: Product) -> f64 {
+pub fn da_Ta_constraint(_input: &Input, _m: Retailer, _j: Product) -> f64 {
     1.0
 }
@@ -0,0 +1,757 @@
+use super::Input;
+use crate::dual::{Dual, HyperDual, Scalar};
+use crate::relation::{
+    Alternative, Material, Product, ProductMap, Relation, Retailer, RetailerMap, Supplier,
+};
+use ndarray::{Array1, Array2};
+use std::ops::Deref;
+
+// The profit model written once for any `Scalar`. With `f64` these are the
+// functions of `computation`; with `Dual` and `HyperDual` they give first and
+// second derivatives along the variables seeded in `DualInput`. The
+// hand-written derivatives of `computation` are kept to cross-check them.
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Variable {
+    p_mg(Retailer, Product),
+    a_mg(Retailer, Product),
+    A_g(Product),
+    rho_g(Product),
+    c_m(Retailer),
+    crm_s(Supplier),
+    drm_sl(Supplier, Material),
+}
+
+impl Variable {
+    /// The variable's name with the labels of its entities, e.g.
+    /// `p_mg[Web store, Suite]`.
+    pub fn label(self, relation: &Relation) -> String {
+        match self {
+            Variable::p_mg(m, g) => format!(
+                "p_mg[{}, {}]",
+                relation.retailer_label(m),
+                relation.product_label(g)
+            ),
+            Variable::a_mg(m, g) => format!(
+                "a_mg[{}, {}]",
+                relation.retailer_label(m),
+                relation.product_label(g)
+            ),
+            Variable::A_g(g) => format!("A_g[{}]", relation.product_label(g)),
+            Variable::rho_g(g) => format!("rho_g[{}]", relation.product_label(g)),
+            Variable::c_m(m) => format!("c_m[{}]", relation.retailer_label(m)),
+            Variable::crm_s(s) => format!("crm_s[{}]", relation.supplier_label(s)),
+            Variable::drm_sl(s, l) => format!(
+                "drm_sl[{}, {}]",
+                relation.supplier_label(s),
+                relation.material_label(l)
+            ),
+        }
+    }
+
+    /// Whether `DP` depends on the variable.
+    fn moves_demand(self) -> bool {
+        matches!(
+            self,
+            Variable::p_mg(..) | Variable::a_mg(..) | Variable::A_g(..)
+        )
+    }
+}
+
+/// What a `DualInput` differentiates along: a single variable, or a direction
+/// given by the component of each variable it moves.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Seed<'s> {
+    Variable(Variable),
+    Direction(&'s [(Variable, f64)]),
+}
+
+impl<'s> Seed<'s> {
+    fn component(self, variable: Variable) -> f64 {
+        match self {
+            Seed::Variable(x) if x == variable => 1.0,
+            Seed::Variable(_) => 0.0,
+            Seed::Direction(direction) => direction
+                .iter()
+                .filter(|(x, _)| *x == variable)
+                .map(|(_, value)| value)
+                .sum(),
+        }
+    }
+
+    fn moves(self, moves: &impl Fn(Variable) -> bool) -> bool {
+        match self {
+            Seed::Variable(x) => moves(x),
+            Seed::Direction(direction) => direction
+                .iter()
+                .any(|(x, value)| *value != 0.0 && moves(*x)),
+        }
+    }
+}
+
+/// `DP` and `pw` of every retailer and product at one `Input`, `DP` from the
+/// contractions of `computation::DP_mg`.
+pub struct Demand {
+    pub DP_mg: RetailerMap<ProductMap<f64>>,
+    pub pw_mg: RetailerMap<ProductMap<f64>>,
+}
+
+impl Demand {
+    pub fn new(input: &Input) -> Self {
+        let pw_g0 = &input.constant.pw_g0;
+        let rho_g = &input.mrgame.parameter.rho_g;
+
+        let DP_mg = super::DP_mg(input);
+        let pw_mg = DP_mg.map(|_, DP_g| DP_g.map(|g, DP| pw_g0[g] - rho_g[g] * DP));
+
+        Demand { DP_mg, pw_mg }
+    }
+}
+
+pub struct DualInput<'i, 'a, 'b, 'c, 'd> {
+    pub input: &'i Input<'a, 'b, 'c, 'd>,
+    pub first: Option<Seed<'i>>,
+    pub second: Option<Seed<'i>>,
+    /// Read by `DP` and `pw` instead of evaluating them, unless a seeded
+    /// variable moves them. It must be built from `input`.
+    pub demand: Option<&'i Demand>,
+}
+
+impl<'i, 'a, 'b, 'c, 'd> DualInput<'i, 'a, 'b, 'c, 'd> {
+    pub fn new(input: &'i Input<'a, 'b, 'c, 'd>) -> Self {
+        Self {
+            input,
+            first: None,
+            second: None,
+            demand: None,
+        }
+    }
+
+    pub fn with_demand(input: &'i Input<'a, 'b, 'c, 'd>, demand: &'i Demand) -> Self {
+        Self {
+            demand: Some(demand),
+            ..Self::new(input)
+        }
+    }
+
+    /// The same input and demand with `first` and `second` seeded.
+    pub fn seed(&self, first: Option<Variable>, second: Option<Variable>) -> Self {
+        self.seed_along(first.map(Seed::Variable), second.map(Seed::Variable))
+    }
+
+    /// The same input and demand with the directions `first` and `second`
+    /// seeded.
+    pub fn seed_along<'s>(
+        &self,
+        first: Option<Seed<'s>>,
+        second: Option<Seed<'s>>,
+    ) -> DualInput<'s, 'a, 'b, 'c, 'd>
+    where
+        'i: 's,
+    {
+        DualInput {
+            input: self.input,
+            first,
+            second,
+            demand: self.demand,
+        }
+    }
+
+    fn is_seeded(&self, moves: impl Fn(Variable) -> bool) -> bool {
+        self.first.is_some_and(|x| x.moves(&moves)) || self.second.is_some_and(|x| x.moves(&moves))
+    }
+
+    /// `demand`, when no seeded variable moves `DP`.
+    fn cached_demand(&self) -> Option<&Demand> {
+        if self.is_seeded(Variable::moves_demand) {
+            return None;
+        }
+        self.demand
+    }
+
+    fn value<T: Scalar>(&self, variable: Variable, x: f64) -> T {
+        let component = |seed: Option<Seed>| seed.map_or(0.0, |x| x.component(variable));
+        T::seeded(x, component(self.first), component(self.second))
+    }
+}
+
+impl<'i, 'a, 'b, 'c, 'd> Deref for DualInput<'i, 'a, 'b, 'c, 'd> {
+    type Target = Input<'a, 'b, 'c, 'd>;
+
+    fn deref(&self) -> &Self::Target {
+        self.input
+    }
+}
+
+fn safe_pow<T: Scalar>(a: T, n: f64) -> T {
+    if n == 0.0 {
+        T::constant(1.0)
+    } else if a.value() <= 0.0 {
+        T::constant(0.0)
+    } else {
+        a.powf(n)
+    }
+}
+
+pub fn DP<T: Scalar>(input: &DualInput, m: Retailer, g: Product) -> T {
+    let K_mg = &input.constant.K_mg;
+    let u_mgy = &input.constant.u_mgy;
+    let eA_mgy = &input.constant.eA_mgy;
+    let A_g = &input.mrgame.parameter.A_g;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let beta_mgxy = &input.constant.beta_mgxy;
+    let ep_mgxy = &input.constant.ep_mgxy;
+
+    let v_mgxy = &input.constant.v_mgxy;
+    let ea_mgxy = &input.constant.ea_mgxy;
+
+    let p_mg = &input.rrgame.parameter.p_mg;
+    let a_mg = &input.rrgame.parameter.a_mg;
+
+    if let Some(demand) = input.cached_demand() {
+        return T::constant(demand.DP_mg[m][g]);
+    }
+
+    let mut sum = T::constant(K_mg[m][g]);
+    for y in relation.products(m, decision) {
+        let A: T = input.value(Variable::A_g(y), A_g[y]);
+        sum += safe_pow(A, eA_mgy[m][g][y]) * u_mgy[m][g][y];
+    }
+
+    for x in relation.initial_retailers() {
+        for y in relation.products(x, decision) {
+            let p: T = input.value(Variable::p_mg(x, y), p_mg[x][y]);
+            let a: T = input.value(Variable::a_mg(x, y), a_mg[x][y]);
+            sum += safe_pow(p, ep_mgxy[(m, g, x, y)]) * beta_mgxy[(m, g, x, y)];
+            sum += safe_pow(a, ea_mgxy[(m, g, x, y)]) * v_mgxy[(m, g, x, y)];
+        }
+    }
+
+    sum
+}
+
+pub fn pw<T: Scalar>(input: &DualInput, m: Retailer, g: Product) -> T {
+    let pw_g0 = &input.constant.pw_g0;
+    let rho_g = &input.mrgame.parameter.rho_g;
+
+    if let Some(demand) = input.cached_demand() {
+        if !input.is_seeded(|x| x == Variable::rho_g(g)) {
+            return T::constant(demand.pw_mg[m][g]);
+        }
+    }
+
+    let rho: T = input.value(Variable::rho_g(g), rho_g[g]);
+
+    T::constant(pw_g0[g]) - rho * DP::<T>(input, m, g)
+}
+
+pub fn NP<T: Scalar>(input: &DualInput, m: Retailer) -> T {
+    let p_mg = &input.rrgame.parameter.p_mg;
+    let a_mg = &input.rrgame.parameter.a_mg;
+    let zeta_mg = &input.constant.zeta_mg;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut sum = T::constant(0.0);
+
+    for g in relation.products(m, decision) {
+        let p: T = input.value(Variable::p_mg(m, g), p_mg[m][g]);
+        let a: T = input.value(Variable::a_mg(m, g), a_mg[m][g]);
+        let DP: T = DP(input, m, g);
+
+        sum += DP * p;
+        sum -= DP * pw::<T>(input, m, g);
+        sum -= DP * zeta_mg[m][g];
+        sum -= a;
+    }
+
+    sum
+}
+
+pub fn TVR_constraint<T: Scalar>(input: &DualInput, m: Retailer) -> T {
+    let w_m = &input.constant.w_m;
+    let c_m = &input.mrgame.parameter.c_m;
+    let V_g = &input.constant.V_g;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+    let TVR_m = &input.constant.TVR_m;
+
+    let c: T = input.value(Variable::c_m(m), c_m[m]);
+
+    let mut sum = T::constant(0.0);
+    for g in relation.products(m, decision) {
+        sum += c * DP::<T>(input, m, g) * V_g[g] / 2.0;
+    }
+
+    sum * w_m[m] - TVR_m[m]
+}
+
+pub fn Ta_constraint<T: Scalar>(input: &DualInput, m: Retailer) -> T {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+    let a_mg = &input.rrgame.parameter.a_mg;
+    let Ta_m = &input.constant.Ta_m;
+
+    let mut sum = T::constant(0.0);
+    for g in relation.products(m, decision) {
+        sum += input.value(Variable::a_mg(m, g), a_mg[m][g]);
+    }
+
+    sum - Ta_m[m]
+}
+
+pub fn DA<T: Scalar>(input: &DualInput, k: Alternative) -> T {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let delta_gk = &input.constant.delta_gk;
+
+    let mut sum = T::constant(0.0);
+    for g in relation.products_for_alternative(k, decision) {
+        for &m in relation.retailers(g) {
+            sum += DP::<T>(input, m, g) * (delta_gk[g][k] as f64);
+        }
+    }
+
+    sum
+}
+
+pub fn NP0<T: Scalar>(input: &DualInput) -> T {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let c_m = &input.mrgame.parameter.c_m;
+    let HR_mg = &input.constant.HR_mg;
+    let zeta_mg = &input.constant.zeta_mg;
+    let TP_mg = &input.constant.TP_mg;
+    let PCP_g = &input.constant.PCP_g;
+    let ORM_s = &input.constant.ORM_s;
+    let HRM_l = &input.constant.HRM_l;
+    let PCA_k = &input.constant.PCA_k;
+    let PCR_sl = &input.constant.PCR_sl;
+    let FCM_j = &input.constant.FCM_j;
+    let FCA_k = &input.constant.FCA_k;
+    let HP_g = &input.constant.HP_g;
+    let OP_m = &input.constant.OP_m;
+
+    let crm_s = &input.mrgame.parameter.crm_s;
+    let drm_sl = &input.mrgame.parameter.drm_sl;
+    let A_g = &input.mrgame.parameter.A_g;
+
+    let mut sum = T::constant(0.0);
+
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            sum += DP::<T>(input, m, g) * pw::<T>(input, m, g);
+        }
+    }
+
+    sum -= {
+        let mut inner_sum = T::constant(0.0);
+
+        for m in relation.initial_retailers() {
+            let c: T = input.value(Variable::c_m(m), c_m[m]);
+            for g in relation.products(m, decision) {
+                let DP: T = DP(input, m, g);
+                inner_sum += c * DP * HR_mg[m][g];
+                inner_sum -= DP * zeta_mg[m][g];
+                inner_sum += c.recip() * OP_m[m];
+                inner_sum += c * DP * HP_g[g] / 2.0;
+            }
+        }
+
+        inner_sum
+    };
+
+    sum -= {
+        let mut inner_sum = T::constant(0.0);
+        for s in relation.all_suppliers() {
+            let crm: T = input.value(Variable::crm_s(s), crm_s[s]);
+            inner_sum += crm.recip() * ORM_s[s];
+
+            for &l in relation.materials(s) {
+                let drm: T = input.value(Variable::drm_sl(s, l), drm_sl[s][l]);
+                inner_sum += crm * drm * HRM_l[l] / 2.0;
+            }
+        }
+        inner_sum
+    };
+
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            let DP: T = DP(input, m, g);
+            sum -= DP * TP_mg[m][g];
+            sum -= DP * PCP_g[g];
+        }
+    }
+
+    for k in relation.all_alternatives() {
+        sum -= DA::<T>(input, k) * PCA_k[k];
+    }
+
+    for s in relation.all_suppliers() {
+        for &l in relation.materials(s) {
+            let drm: T = input.value(Variable::drm_sl(s, l), drm_sl[s][l]);
+            sum -= drm * PCR_sl[s][l];
+        }
+    }
+
+    for g in relation.all_products() {
+        sum -= T::constant(decision.fpp(g) * PCP_g[g]);
+    }
+
+    for j in relation.all_modules() {
+        sum -= T::constant(decision.fpm(relation, j) * FCM_j[j]);
+    }
+
+    for k in relation.all_alternatives() {
+        sum -= T::constant(decision.fpa(k) * FCA_k[k]);
+    }
+
+    for g in relation.all_products() {
+        let A: T = input.value(Variable::A_g(g), A_g[g]);
+        sum -= A * decision.fpp(g);
+    }
+
+    sum
+}
+
+pub fn NP0_bom_constraint<T: Scalar>(input: &DualInput, l: Material) -> T {
+    let relation = input.relation;
+    let drm_sl = &input.mrgame.parameter.drm_sl;
+    let sigma_kl = &input.constant.sigma_kl;
+
+    let mut sum = T::constant(0.0);
+    for &s in relation.suppliers_for_material(l) {
+        sum += input.value(Variable::drm_sl(s, l), drm_sl[s][l]);
+    }
+
+    for k in relation.all_alternatives() {
+        sum -= DA::<T>(input, k) * (sigma_kl[k][l] as f64);
+    }
+
+    sum
+}
+
+pub fn NP0_TVP_constraint<T: Scalar>(input: &DualInput) -> T {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let crm_s = &input.mrgame.parameter.crm_s;
+    let drm_sl = &input.mrgame.parameter.drm_sl;
+    let c_m = &input.mrgame.parameter.c_m;
+
+    let VRM_l = &input.constant.VRM_l;
+    let V_g = &input.constant.V_g;
+    let TVP = input.constant.TVP;
+
+    let mut sum = T::constant(0.0);
+    for s in relation.all_suppliers() {
+        let crm: T = input.value(Variable::crm_s(s), crm_s[s]);
+        for &l in relation.materials(s) {
+            let drm: T = input.value(Variable::drm_sl(s, l), drm_sl[s][l]);
+            sum += crm * drm * VRM_l[l] / 2.0;
+        }
+    }
+
+    for m in relation.initial_retailers() {
+        let c: T = input.value(Variable::c_m(m), c_m[m]);
+        for g in relation.products(m, decision) {
+            sum += c * DP::<T>(input, m, g) * V_g[g] / 2.0
+        }
+    }
+
+    sum - TVP
+}
+
+/// Derivative of `f` with respect to `x`, e.g.
+/// `derivative(input, Variable::A_g(j), NP0)` for `dA_NP0(input, j)`.
+pub fn derivative(input: &Input, x: Variable, f: impl Fn(&DualInput) -> Dual) -> f64 {
+    f(&DualInput::new(input).seed(Some(x), None)).eps
+}
+
+/// Second derivative of `f` with respect to `x` and `y`.
+pub fn second_derivative(
+    input: &Input,
+    x: Variable,
+    y: Variable,
+    f: impl Fn(&DualInput) -> HyperDual,
+) -> f64 {
+    f(&DualInput::new(input).seed(Some(x), Some(y))).e12
+}
+
+pub fn gradient(
+    input: &Input,
+    variables: &[Variable],
+    f: impl Fn(&DualInput) -> Dual,
+) -> Array1<f64> {
+    let mut result = Array1::zeros(variables.len());
+    for (i, x) in variables.iter().enumerate() {
+        result[i] = derivative(input, *x, &f);
+    }
+    result
+}
+
+pub fn hessian(
+    input: &Input,
+    variables: &[Variable],
+    f: impl Fn(&DualInput) -> HyperDual,
+) -> Array2<f64> {
+    let len = variables.len();
+    let mut result = Array2::zeros((len, len));
+    for i in 0..len {
+        for j in i..len {
+            let value = second_derivative(input, variables[i], variables[j], &f);
+            result[(i, j)] = value;
+            result[(j, i)] = value;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computation;
+    use crate::scenario::Scenario;
+
+    fn assert_close(exact: f64, dual: f64) {
+        assert!(
+            (exact - dual).abs() < 1e-6 * exact.abs().max(1.0),
+            "{} != {}",
+            exact,
+            dual
+        );
+    }
+
+    #[test]
+    fn test_dual_derivatives() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mut mrgame, mut rrgame) = scenario.build().unwrap();
+
+        // DP has a kink where a price, an advertising budget or A is zero.
+        for m in relation.initial_retailers() {
+            for g in relation.products(m, &mrgame.decision) {
+                rrgame.parameter.p_mg[m][g] = rrgame.parameter.p_mg[m][g].max(50.0);
+                rrgame.parameter.a_mg[m][g] = rrgame.parameter.a_mg[m][g].max(500.0);
+            }
+        }
+        for g in relation.all_products() {
+            mrgame.parameter.A_g[g] = mrgame.parameter.A_g[g].max(100.0);
+        }
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let evaluation = computation::Evaluation::new(&input);
+        let decision = &mrgame.decision;
+
+        let DP_mg = computation::DP_mg(&input);
+        for m in relation.initial_retailers() {
+            for g in relation.all_products() {
+                assert_close(computation::DP(&input, m, g), DP_mg[m][g]);
+            }
+        }
+
+        for m in relation.initial_retailers() {
+            let NP_m = |x: &DualInput| NP(x, m);
+            let TVR_m = |x: &DualInput| TVR_constraint(x, m);
+            for j in relation.products(m, decision) {
+                let p_j = Variable::p_mg(m, j);
+                let a_j = Variable::a_mg(m, j);
+                let rho_j = Variable::rho_g(j);
+                assert_close(
+                    computation::dp_NP(&evaluation, m, j),
+                    derivative(&input, p_j, NP_m),
+                );
+                assert_close(
+                    computation::da_NP(&evaluation, m, j),
+                    derivative(&input, a_j, NP_m),
+                );
+                assert_close(
+                    computation::drho_NP(&evaluation, m, j),
+                    derivative(&input, rho_j, NP_m),
+                );
+                assert_close(
+                    computation::dp_TVR_constraint(&input, m, j),
+                    derivative(&input, p_j, TVR_m),
+                );
+                for k in relation.products(m, decision) {
+                    let p_k = Variable::p_mg(m, k);
+                    let a_k = Variable::a_mg(m, k);
+                    assert_close(
+                        computation::dpdp_NP(&evaluation, m, j, k),
+                        second_derivative(&input, p_j, p_k, |x: &DualInput| NP(x, m)),
+                    );
+                    assert_close(
+                        computation::dpda_NP(&input, m, j, k),
+                        second_derivative(&input, p_j, a_k, |x: &DualInput| NP(x, m)),
+                    );
+                    assert_close(
+                        computation::dada_NP(&evaluation, m, j, k),
+                        second_derivative(&input, a_j, a_k, |x: &DualInput| NP(x, m)),
+                    );
+                }
+            }
+
+            let c_m = Variable::c_m(m);
+            assert_close(
+                computation::dc_NP0(&evaluation, m),
+                derivative(&input, c_m, NP0),
+            );
+            assert_close(
+                computation::dcdc_NP0(&input, m),
+                second_derivative(&input, c_m, c_m, NP0),
+            );
+            assert_close(
+                computation::dc_NP0_TVP_constraint(&evaluation, m),
+                derivative(&input, c_m, NP0_TVP_constraint),
+            );
+        }
+
+        for j in relation.all_products() {
+            let A_j = Variable::A_g(j);
+            assert_close(
+                computation::dA_NP0(&evaluation, j),
+                derivative(&input, A_j, NP0),
+            );
+            assert_close(
+                computation::drho_NP0(&evaluation, j),
+                derivative(&input, Variable::rho_g(j), NP0),
+            );
+            assert_close(
+                computation::dA_NP0_TVP_constraint(&input, j),
+                derivative(&input, A_j, NP0_TVP_constraint),
+            );
+            for k in relation.all_products() {
+                let A_k = Variable::A_g(k);
+                assert_close(
+                    computation::dAdA_NP0(&evaluation, j, k),
+                    second_derivative(&input, A_j, A_k, NP0),
+                );
+                assert_close(
+                    computation::dAdA_NP0_TVP_constraint(&input, j, k),
+                    second_derivative(&input, A_j, A_k, NP0_TVP_constraint),
+                );
+            }
+            for l in relation.all_materials() {
+                let bom_l = |x: &DualInput| NP0_bom_constraint(x, l);
+                assert_close(
+                    computation::dA_NP0_bom_constraint(&input, l, j),
+                    derivative(&input, A_j, bom_l),
+                );
+            }
+        }
+
+        for s in relation.all_suppliers() {
+            let crm_s = Variable::crm_s(s);
+            assert_close(
+                computation::dcrm_NP0(&input, s),
+                derivative(&input, crm_s, NP0),
+            );
+            assert_close(
+                computation::dcrmdcrm_NP0(&input, s),
+                second_derivative(&input, crm_s, crm_s, NP0),
+            );
+            for &l in relation.materials(s) {
+                let drm_sl = Variable::drm_sl(s, l);
+                assert_close(
+                    computation::ddrm_NP0(&input, s, l),
+                    derivative(&input, drm_sl, NP0),
+                );
+                assert_close(
+                    computation::dcrmddrm_NP0_TVP_constraint(&input, s, l),
+                    second_derivative(&input, crm_s, drm_sl, NP0_TVP_constraint),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_gradient_and_hessian() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let evaluation = computation::Evaluation::new(&input);
+
+        let variables: Vec<Variable> = relation.all_products().map(Variable::A_g).collect();
+        let gradient = gradient(&input, &variables, NP0);
+        let hessian = hessian(&input, &variables, NP0);
+        for (i, j) in relation.all_products().enumerate() {
+            assert_close(computation::dA_NP0(&evaluation, j), gradient[i]);
+            for (k_index, k) in relation.all_products().enumerate() {
+                assert_close(
+                    computation::dAdA_NP0(&evaluation, j, k),
+                    hessian[(i, k_index)],
+                );
+                assert_eq!(hessian[(i, k_index)], hessian[(k_index, i)]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_demand_cache() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let evaluation = computation::Evaluation::new(&input);
+        let plain = DualInput::new(&input);
+
+        // The cache sums the demand in another order, so its values only
+        // agree up to rounding.
+        assert_close(evaluation.NP0(), NP0::<f64>(&plain));
+        assert_close(
+            evaluation.NP0_TVP_constraint(),
+            computation::NP0_TVP_constraint(&input),
+        );
+        for l in relation.all_materials() {
+            assert_close(
+                evaluation.NP0_bom_constraint(l),
+                NP0_bom_constraint::<f64>(&plain, l),
+            );
+        }
+
+        for m in relation.initial_retailers() {
+            assert_close(evaluation.NP(m), computation::NP(&input, m));
+            assert_close(
+                evaluation.TVR_constraint(m),
+                computation::TVR_constraint(&input, m),
+            );
+            for g in relation.all_products() {
+                assert_close(evaluation.DP(m, g), computation::DP(&input, m, g));
+                assert_close(evaluation.pw(m, g), computation::pw(&input, m, g));
+
+                // The cache is bypassed for variables that move the demand.
+                let NP_m = |x: &DualInput| NP(x, m);
+                for x in [Variable::rho_g(g), Variable::p_mg(m, g)].iter() {
+                    assert_close(
+                        evaluation.derivative(*x, NP_m),
+                        derivative(&input, *x, NP_m),
+                    );
+                }
+            }
+        }
+
+        let rho = Variable::rho_g(Product { id: 0 });
+        let A = Variable::A_g(Product { id: 1 });
+        assert_close(
+            evaluation.second_derivative(rho, A, NP0),
+            second_derivative(&input, rho, A, NP0),
+        );
+    }
+}
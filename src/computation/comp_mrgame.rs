@@ -2,152 +2,63 @@ use super::*;
 use crate::relation::{Material, Supplier};
 
 fn DA(input: &Input, k: Alternative) -> f64 {
-    let relation = input.relation;
-    let decision = &input.mrgame.decision;
-
-    let delta_gk = &input.constant.delta_gk;
-
-    let mut sum = 0.0;
-    for g in relation.products_for_alternative(k, &decision) {
-        for m in relation.retailers(g) {
-            sum += (delta_gk[g][k] as f64) * DP(input, m, g);
-        }
-    }
-
-    sum
+    comp_generic::DA(&DualInput::new(input), k)
 }
 
 pub fn NP0(input: &Input) -> f64 {
-    let relation = input.relation;
-    let decision = &input.mrgame.decision;
+    Evaluation::new(input).NP0()
+}
 
-    let c_m = &input.mrgame.parameter.c_m;
-    let HR_mg = &input.constant.HR_mg;
-    let zeta_mg = &input.constant.zeta_mg;
-    let TP_mg = &input.constant.TP_mg;
-    let PCP_g = &input.constant.PCP_g;
-    let ORM_s = &input.constant.ORM_s;
-    let HRM_l = &input.constant.HRM_l;
-    let PCA_k = &input.constant.PCA_k;
-    let PCR_sl = &input.constant.PCR_sl;
-    let FCM_j = &input.constant.FCM_j;
-    let FCA_k = &input.constant.FCA_k;
-    let HP_g = &input.constant.HP_g;
-    let OP_m = &input.constant.OP_m;
+pub fn NP0_bom_constraint(input: &Input, l: Material) -> f64 {
+    Evaluation::new(input).NP0_bom_constraint(l)
+}
 
-    let crm_s = &input.mrgame.parameter.crm_s;
-    let drm_sl = &input.mrgame.parameter.drm_sl;
+pub fn dA_DP(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
+    let u_mgy = &input.constant.u_mgy;
+    let eA_mgy = &input.constant.eA_mgy;
     let A_g = &input.mrgame.parameter.A_g;
 
-    let mut sum = 0.0;
-
-    for m in relation.initial_retailers() {
-        for g in relation.products(m, decision) {
-            sum += DP(input, m, g) * pw(input, m, g);
-        }
-    }
-
-    sum -= {
-        let mut inner_sum = 0.0;
-
-        for m in relation.initial_retailers() {
-            for g in relation.products(m, decision) {
-                inner_sum += c_m[m] * DP(input, m, g) * HR_mg[m][g];
-                inner_sum -= zeta_mg[m][g] * DP(input, m, g);
-                inner_sum += OP_m[m] / c_m[m];
-                inner_sum += c_m[m] * DP(input, m, g) * HP_g[g] / 2.0;
-            }
-        }
-
-        inner_sum
-    };
-
-    sum -= {
-        let mut inner_sum = 0.0;
-        for s in relation.all_suppliers() {
-            inner_sum += ORM_s[s] / crm_s[s];
-
-            for l in relation.materials(s) {
-                inner_sum += crm_s[s] * drm_sl[s][l] * HRM_l[l] / 2.0;
-            }
-        }
-        inner_sum
-    };
-
-    for m in relation.initial_retailers() {
-        for g in relation.products(m, decision) {
-            sum -= DP(input, m, g) * TP_mg[m][g];
-            sum -= DP(input, m, g) * PCP_g[g];
-        }
-    }
-
-    for k in relation.all_alternatives() {
-        sum -= DA(input, k) * PCA_k[k];
-    }
-
-    for s in relation.all_suppliers() {
-        for l in relation.materials(s) {
-            sum -= drm_sl[s][l] * PCR_sl[s][l];
-        }
-    }
-
-    for g in relation.all_products() {
-        sum -= decision.fpp(g) * PCP_g[g];
-    }
-
-    for j in relation.all_modules() {
-        sum -= decision.fpm(relation, j) * FCM_j[j];
-    }
-
-    for k in relation.all_alternatives() {
-        sum -= decision.fpa(k) * FCA_k[k];
-    }
-
-    for g in relation.all_products() {
-        sum -= A_g[g];
-    }
+    let eA = eA_mgy[m][g][j];
 
-    sum
+    u_mgy[m][g][j] * eA * safe_pow(A_g[j], eA - 1.0)
 }
 
-pub fn NP0_bom_constraint(input: &Input, l: Material) -> f64 {
-    let relation = input.relation;
-    let drm_sl = &input.mrgame.parameter.drm_sl;
-    let sigma_kl = &input.constant.sigma_kl;
-
-    let mut sum = 0.0;
-    for s in relation.suppliers_for_material(l) {
-        sum += drm_sl[s][l];
-    }
+pub fn dA_DP_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.A_g[j] += 0.01;
 
-    for k in relation.all_alternatives() {
-        sum -= sigma_kl[k][l] as f64 * DA(input, k);
-    }
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
 
-    sum
+    (DP(&new_input, m, g) - DP(input, m, g)) / 0.01
 }
 
-pub fn dA_DP(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
+pub fn dAdA_DP(input: &Input, m: Retailer, g: Product, j: Product, k: Product) -> f64 {
     let u_mgy = &input.constant.u_mgy;
     let eA_mgy = &input.constant.eA_mgy;
     let A_g = &input.mrgame.parameter.A_g;
 
+    if j.id != k.id {
+        return 0.0;
+    }
+
     let eA = eA_mgy[m][g][j];
 
-    u_mgy[m][g][j] * eA * safe_pow(A_g[j], eA - 1.0)
+    u_mgy[m][g][j] * eA * (eA - 1.0) * safe_pow(A_g[j], eA - 2.0)
 }
 
-#[allow(dead_code)]
-pub fn dA_DP_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
+pub fn dAdA_DP_approx(input: &Input, m: Retailer, g: Product, j: Product, k: Product) -> f64 {
     let mut mrgame = input.mrgame.clone();
-    mrgame.parameter.A_g[j] += 0.01;
+    mrgame.parameter.A_g[k] += 0.0001;
 
     let new_input = Input {
         mrgame: &mrgame,
         ..(*input)
     };
 
-    (DP(&new_input, m, g) - DP(input, m, g)) / 0.01
+    (dA_DP(&new_input, m, g, j) - dA_DP(input, m, g, j)) / 0.0001
 }
 
 pub fn dA_pw(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
@@ -156,7 +67,6 @@ pub fn dA_pw(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     -rho_g[g] * dA_DP(input, m, g, j)
 }
 
-#[allow(dead_code)]
 pub fn dA_pw_approx(input: &Input, m: Retailer, g: Product, j: Product) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.A_g[j] += 0.01;
@@ -176,8 +86,8 @@ pub fn dA_DA(input: &Input, k: Alternative, j: Product) -> f64 {
     let delta_gk = &input.constant.delta_gk;
 
     let mut sum = 0.0;
-    for g in relation.products_for_alternative(k, &decision) {
-        for m in relation.retailers(g) {
+    for g in relation.products_for_alternative(k, decision) {
+        for &m in relation.retailers(g) {
             sum += (delta_gk[g][k] as f64) * dA_DP(input, m, g, j);
         }
     }
@@ -185,7 +95,6 @@ pub fn dA_DA(input: &Input, k: Alternative, j: Product) -> f64 {
     sum
 }
 
-#[allow(dead_code)]
 pub fn dA_DA_approx(input: &Input, k: Alternative, j: Product) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.A_g[j] += 0.01;
@@ -198,7 +107,36 @@ pub fn dA_DA_approx(input: &Input, k: Alternative, j: Product) -> f64 {
     (DA(&new_input, k) - DA(input, k)) / 0.01
 }
 
-pub fn dA_NP0(input: &Input, j: Product) -> f64 {
+pub fn dAdA_DA(input: &Input, k: Alternative, j: Product, i: Product) -> f64 {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let delta_gk = &input.constant.delta_gk;
+
+    let mut sum = 0.0;
+    for g in relation.products_for_alternative(k, decision) {
+        for &m in relation.retailers(g) {
+            sum += (delta_gk[g][k] as f64) * dAdA_DP(input, m, g, j, i);
+        }
+    }
+
+    sum
+}
+
+pub fn dAdA_DA_approx(input: &Input, k: Alternative, j: Product, i: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.A_g[i] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dA_DA(&new_input, k, j) - dA_DA(input, k, j)) / 0.0001
+}
+
+pub fn dA_NP0(evaluation: &Evaluation, j: Product) -> f64 {
+    let input = evaluation.input;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
 
@@ -207,24 +145,15 @@ pub fn dA_NP0(input: &Input, j: Product) -> f64 {
     let zeta_mg = &input.constant.zeta_mg;
     let TP_mg = &input.constant.TP_mg;
     let PCP_g = &input.constant.PCP_g;
-    let ORM_s = &input.constant.ORM_s;
-    let HRM_l = &input.constant.HRM_l;
     let PCA_k = &input.constant.PCA_k;
-    let PCR_sl = &input.constant.PCR_sl;
-    let FCM_j = &input.constant.FCM_j;
-    let FCA_k = &input.constant.FCA_k;
     let HP_g = &input.constant.HP_g;
 
-    let crm_s = &input.mrgame.parameter.crm_s;
-    let drm_sl = &input.mrgame.parameter.drm_sl;
-    let A_g = &input.mrgame.parameter.A_g;
-
     let mut sum = 0.0;
 
     for m in relation.initial_retailers() {
         for g in relation.products(m, decision) {
-            sum += dA_DP(input, m, g, j) * pw(input, m, g);
-            sum += DP(input, m, g) * dA_pw(input, m, g, j);
+            sum += dA_DP(input, m, g, j) * evaluation.pw(m, g);
+            sum += evaluation.DP(m, g) * dA_pw(input, m, g, j);
         }
     }
 
@@ -253,10 +182,9 @@ pub fn dA_NP0(input: &Input, j: Product) -> f64 {
         sum -= dA_DA(input, k, j) * PCA_k[k];
     }
 
-    sum - 1.0
+    sum - decision.fpp(j)
 }
 
-#[allow(dead_code)]
 pub fn dA_NP0_approx(input: &Input, j: Product) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.A_g[j] += 0.01;
@@ -269,7 +197,163 @@ pub fn dA_NP0_approx(input: &Input, j: Product) -> f64 {
     (NP0(&new_input) - NP0(input)) / 0.01
 }
 
-pub fn dc_NP0(input: &Input, m: Retailer) -> f64 {
+/// Derivative of `dA_NP0(j)` with respect to `A_k`.
+pub fn dAdA_NP0(evaluation: &Evaluation, j: Product, k: Product) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let c_m = &input.mrgame.parameter.c_m;
+    let rho_g = &input.mrgame.parameter.rho_g;
+    let HR_mg = &input.constant.HR_mg;
+    let zeta_mg = &input.constant.zeta_mg;
+    let TP_mg = &input.constant.TP_mg;
+    let PCP_g = &input.constant.PCP_g;
+    let PCA_k = &input.constant.PCA_k;
+    let HP_g = &input.constant.HP_g;
+
+    let mut sum = 0.0;
+
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            sum += dAdA_DP(input, m, g, j, k) * evaluation.pw(m, g);
+            sum -= dAdA_DP(input, m, g, j, k) * rho_g[g] * evaluation.DP(m, g);
+            sum -= 2.0 * rho_g[g] * dA_DP(input, m, g, j) * dA_DP(input, m, g, k);
+        }
+    }
+
+    sum -= {
+        let mut inner_sum = 0.0;
+
+        for m in relation.initial_retailers() {
+            for g in relation.products(m, decision) {
+                inner_sum += c_m[m] * dAdA_DP(input, m, g, j, k) * HR_mg[m][g];
+                inner_sum -= zeta_mg[m][g] * dAdA_DP(input, m, g, j, k);
+                inner_sum += c_m[m] * dAdA_DP(input, m, g, j, k) * HP_g[g] / 2.0;
+            }
+        }
+
+        inner_sum
+    };
+
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            sum -= dAdA_DP(input, m, g, j, k) * TP_mg[m][g];
+            sum -= dAdA_DP(input, m, g, j, k) * PCP_g[g];
+        }
+    }
+
+    for a in relation.all_alternatives() {
+        sum -= dAdA_DA(input, a, j, k) * PCA_k[a];
+    }
+
+    sum
+}
+
+pub fn dAdA_NP0_approx(input: &Input, j: Product, k: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.A_g[k] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dA_NP0(&Evaluation::new(&new_input), j) - dA_NP0(&Evaluation::new(input), j)) / 0.0001
+}
+
+/// Derivative of `dA_NP0(j)` with respect to `rho_k` with the retailers'
+/// prices and advertising held fixed.
+pub fn drhodA_NP0(evaluation: &Evaluation, j: Product, k: Product) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut sum = 0.0;
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            if g.id == k.id {
+                sum -= 2.0 * evaluation.DP(m, g) * dA_DP(input, m, g, j);
+            }
+        }
+    }
+
+    sum
+}
+
+pub fn drhodA_NP0_approx(input: &Input, j: Product, k: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.rho_g[k] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dA_NP0(&Evaluation::new(&new_input), j) - dA_NP0(&Evaluation::new(input), j)) / 0.0001
+}
+
+/// Derivative of `dA_NP0(j)` with respect to `c_m`.
+pub fn dcdA_NP0(input: &Input, m: Retailer, j: Product) -> f64 {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let HR_mg = &input.constant.HR_mg;
+    let HP_g = &input.constant.HP_g;
+
+    let mut sum = 0.0;
+    for g in relation.products(m, decision) {
+        sum -= dA_DP(input, m, g, j) * HR_mg[m][g];
+        sum -= dA_DP(input, m, g, j) * HP_g[g] / 2.0;
+    }
+
+    sum
+}
+
+pub fn dcdA_NP0_approx(input: &Input, m: Retailer, j: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.c_m[m] += 0.000001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dA_NP0(&Evaluation::new(&new_input), j) - dA_NP0(&Evaluation::new(input), j)) / 0.000001
+}
+
+/// Partial derivative of NP0 with respect to `rho_j` with the retailers'
+/// prices and advertising held fixed. See `solver::RhoResponse` for the part
+/// that comes from the retailers reacting to the new wholesale price.
+pub fn drho_NP0(evaluation: &Evaluation, j: Product) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut sum = 0.0;
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            sum += evaluation.DP(m, g) * drho_pw(evaluation, m, g, j);
+        }
+    }
+
+    sum
+}
+
+pub fn drho_NP0_approx(input: &Input, j: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.rho_g[j] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (NP0(&new_input) - NP0(input)) / 0.0001
+}
+
+pub fn dc_NP0(evaluation: &Evaluation, m: Retailer) -> f64 {
+    let input = evaluation.input;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
 
@@ -285,9 +369,9 @@ pub fn dc_NP0(input: &Input, m: Retailer) -> f64 {
         let mut inner_sum = 0.0;
 
         for g in relation.products(m, decision) {
-            inner_sum += DP(input, m, g) * HR_mg[m][g];
+            inner_sum += evaluation.DP(m, g) * HR_mg[m][g];
             inner_sum += -OP_m[m] / (c_m[m] * c_m[m]);
-            inner_sum += DP(input, m, g) * HP_g[g] / 2.0;
+            inner_sum += evaluation.DP(m, g) * HP_g[g] / 2.0;
         }
 
         inner_sum
@@ -296,7 +380,6 @@ pub fn dc_NP0(input: &Input, m: Retailer) -> f64 {
     sum
 }
 
-#[allow(dead_code)]
 pub fn dc_NP0_approx(input: &Input, m: Retailer) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.c_m[m] += 0.000001;
@@ -309,7 +392,38 @@ pub fn dc_NP0_approx(input: &Input, m: Retailer) -> f64 {
     (NP0(&new_input) - NP0(input)) / 0.000001
 }
 
-pub fn dc_TVR_constraint(input: &Input, m: Retailer) -> f64 {
+/// Derivative of `dc_NP0(m)` with respect to `c_m`; the one with respect to
+/// any other `c_n` is zero.
+pub fn dcdc_NP0(input: &Input, m: Retailer) -> f64 {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let OP_m = &input.constant.OP_m;
+
+    let c_m = &input.mrgame.parameter.c_m;
+
+    let mut sum = 0.0;
+    for _ in relation.products(m, decision) {
+        sum -= 2.0 * OP_m[m] / (c_m[m] * c_m[m] * c_m[m]);
+    }
+
+    sum
+}
+
+pub fn dcdc_NP0_approx(input: &Input, m: Retailer) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.c_m[m] += 0.000001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dc_NP0(&Evaluation::new(&new_input), m) - dc_NP0(&Evaluation::new(input), m)) / 0.000001
+}
+
+pub fn dc_TVR_constraint(evaluation: &Evaluation, m: Retailer) -> f64 {
+    let input = evaluation.input;
     let relation = input.relation;
     let decision = &input.mrgame.decision;
 
@@ -318,17 +432,13 @@ pub fn dc_TVR_constraint(input: &Input, m: Retailer) -> f64 {
 
     let mut sum = 0.0;
     for g in relation.products(m, decision) {
-        sum += w_m[m] * DP(input, m, g) * V_g[g] / 2.0;
+        sum += w_m[m] * evaluation.DP(m, g) * V_g[g] / 2.0;
     }
 
     sum
 }
 
-#[allow(dead_code)]
 pub fn dc_TVR_constraint_approx(input: &Input, m: Retailer) -> f64 {
-    let relation = input.relation;
-    let decision = &input.mrgame.decision;
-
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.c_m[m] += 0.01;
 
@@ -355,7 +465,7 @@ pub fn dcrm_NP0(input: &Input, s: Supplier) -> f64 {
         let mut inner_sum = 0.0;
         inner_sum += -ORM_s[s] / (crm_s[s] * crm_s[s]);
 
-        for l in relation.materials(s) {
+        for &l in relation.materials(s) {
             inner_sum += drm_sl[s][l] * HRM_l[l] / 2.0;
         }
         inner_sum
@@ -364,7 +474,6 @@ pub fn dcrm_NP0(input: &Input, s: Supplier) -> f64 {
     sum
 }
 
-#[allow(dead_code)]
 pub fn dcrm_NP0_approx(input: &Input, s: Supplier) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.crm_s[s] += 0.000001;
@@ -377,6 +486,46 @@ pub fn dcrm_NP0_approx(input: &Input, s: Supplier) -> f64 {
     (NP0(&new_input) - NP0(input)) / 0.000001
 }
 
+pub fn dcrmdcrm_NP0(input: &Input, s: Supplier) -> f64 {
+    let ORM_s = &input.constant.ORM_s;
+
+    let crm_s = &input.mrgame.parameter.crm_s;
+
+    -2.0 * ORM_s[s] / (crm_s[s] * crm_s[s] * crm_s[s])
+}
+
+pub fn dcrmdcrm_NP0_approx(input: &Input, s: Supplier) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.crm_s[s] += 0.000001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dcrm_NP0(&new_input, s) - dcrm_NP0(input, s)) / 0.000001
+}
+
+/// Derivative of `dcrm_NP0(s)` with respect to `drm_sl`, which is also the
+/// derivative of `ddrm_NP0(s, l)` with respect to `crm_s`.
+pub fn dcrmddrm_NP0(input: &Input, _s: Supplier, l: Material) -> f64 {
+    let HRM_l = &input.constant.HRM_l;
+
+    -HRM_l[l] / 2.0
+}
+
+pub fn dcrmddrm_NP0_approx(input: &Input, s: Supplier, l: Material) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.crm_s[s] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (ddrm_NP0(&new_input, s, l) - ddrm_NP0(input, s, l)) / 0.0001
+}
+
 pub fn ddrm_NP0(input: &Input, s: Supplier, l: Material) -> f64 {
     let HRM_l = &input.constant.HRM_l;
     let PCR_sl = &input.constant.PCR_sl;
@@ -389,7 +538,6 @@ pub fn ddrm_NP0(input: &Input, s: Supplier, l: Material) -> f64 {
     sum
 }
 
-#[allow(dead_code)]
 pub fn ddrm_NP0_approx(input: &Input, s: Supplier, l: Material) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.drm_sl[s][l] += 0.0001;
@@ -402,8 +550,30 @@ pub fn ddrm_NP0_approx(input: &Input, s: Supplier, l: Material) -> f64 {
     (NP0(&new_input) - NP0(input)) / 0.0001
 }
 
-pub fn ddrm_NP0_bom_constraint(input: &Input, l: Material) -> f64 {
-    input.relation.suppliers_for_material(l).len() as f64
+pub fn ddrm_NP0_bom_constraint(input: &Input, s: Supplier, l: Material) -> f64 {
+    let relation = input.relation;
+
+    if relation
+        .suppliers_for_material(l)
+        .iter()
+        .any(|x| x.id == s.id)
+    {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+pub fn ddrm_NP0_bom_constraint_approx(input: &Input, s: Supplier, l: Material) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.drm_sl[s][l] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (NP0_bom_constraint(&new_input, l) - NP0_bom_constraint(input, l)) / 0.0001
 }
 
 pub fn dA_NP0_bom_constraint(input: &Input, l: Material, j: Product) -> f64 {
@@ -418,7 +588,6 @@ pub fn dA_NP0_bom_constraint(input: &Input, l: Material, j: Product) -> f64 {
     sum
 }
 
-#[allow(dead_code)]
 pub fn dA_NP0_bom_constraint_approx(input: &Input, l: Material, j: Product) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.A_g[j] += 0.0001;
@@ -431,35 +600,65 @@ pub fn dA_NP0_bom_constraint_approx(input: &Input, l: Material, j: Product) -> f
     (NP0_bom_constraint(&new_input, l) - NP0_bom_constraint(input, l)) / 0.0001
 }
 
+pub fn dAdA_NP0_bom_constraint(input: &Input, l: Material, j: Product, k: Product) -> f64 {
+    let relation = input.relation;
+    let sigma_kl = &input.constant.sigma_kl;
+
+    let mut sum = 0.0;
+    for a in relation.all_alternatives() {
+        sum -= sigma_kl[a][l] as f64 * dAdA_DA(input, a, j, k);
+    }
+
+    sum
+}
+
+pub fn dAdA_NP0_bom_constraint_approx(input: &Input, l: Material, j: Product, k: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.A_g[k] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dA_NP0_bom_constraint(&new_input, l, j) - dA_NP0_bom_constraint(input, l, j)) / 0.0001
+}
+
 pub fn NP0_TVP_constraint(input: &Input) -> f64 {
+    comp_generic::NP0_TVP_constraint(&DualInput::new(input))
+}
+
+pub fn dA_NP0_TVP_constraint(input: &Input, j: Product) -> f64 {
     let relation = input.relation;
     let decision = &input.mrgame.decision;
 
-    let crm_s = &input.mrgame.parameter.crm_s;
-    let drm_sl = &input.mrgame.parameter.drm_sl;
     let c_m = &input.mrgame.parameter.c_m;
 
-    let VRM_l = &input.constant.VRM_l;
     let V_g = &input.constant.V_g;
-    let TVP = input.constant.TVP;
 
     let mut sum = 0.0;
-    for s in relation.all_suppliers() {
-        for l in relation.materials(s) {
-            sum += crm_s[s] * drm_sl[s][l] * VRM_l[l] / 2.0;
-        }
-    }
-
     for m in relation.initial_retailers() {
         for g in relation.products(m, decision) {
-            sum += c_m[m] * DP(input, m, g) * V_g[g] / 2.0
+            sum += c_m[m] * dA_DP(input, m, g, j) * V_g[g] / 2.0
         }
     }
 
-    sum - TVP
+    sum
 }
 
-pub fn dA_NP0_TVP_constraint(input: &Input, j: Product) -> f64 {
+pub fn dA_NP0_TVP_constraint_approx(input: &Input, j: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.A_g[j] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (NP0_TVP_constraint(&new_input) - NP0_TVP_constraint(input)) / 0.0001
+}
+
+pub fn dAdA_NP0_TVP_constraint(input: &Input, j: Product, k: Product) -> f64 {
     let relation = input.relation;
     let decision = &input.mrgame.decision;
 
@@ -470,17 +669,96 @@ pub fn dA_NP0_TVP_constraint(input: &Input, j: Product) -> f64 {
     let mut sum = 0.0;
     for m in relation.initial_retailers() {
         for g in relation.products(m, decision) {
-            sum += c_m[m] * dA_DP(input, m, g, j) * V_g[g] / 2.0
+            sum += c_m[m] * dAdA_DP(input, m, g, j, k) * V_g[g] / 2.0
         }
     }
 
     sum
 }
 
-#[allow(dead_code)]
-pub fn dA_NP0_TVP_constraint_approx(input: &Input, j: Product) -> f64 {
+pub fn dAdA_NP0_TVP_constraint_approx(input: &Input, j: Product, k: Product) -> f64 {
     let mut mrgame = input.mrgame.clone();
-    mrgame.parameter.A_g[j] += 0.0001;
+    mrgame.parameter.A_g[k] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dA_NP0_TVP_constraint(&new_input, j) - dA_NP0_TVP_constraint(input, j)) / 0.0001
+}
+
+/// Derivative of `dA_NP0_TVP_constraint(j)` with respect to `c_m`.
+pub fn dcdA_NP0_TVP_constraint(input: &Input, m: Retailer, j: Product) -> f64 {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let V_g = &input.constant.V_g;
+
+    let mut sum = 0.0;
+    for g in relation.products(m, decision) {
+        sum += dA_DP(input, m, g, j) * V_g[g] / 2.0;
+    }
+
+    sum
+}
+
+pub fn dcdA_NP0_TVP_constraint_approx(input: &Input, m: Retailer, j: Product) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.c_m[m] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (dA_NP0_TVP_constraint(&new_input, j) - dA_NP0_TVP_constraint(input, j)) / 0.0001
+}
+
+pub fn dc_NP0_TVP_constraint(evaluation: &Evaluation, m: Retailer) -> f64 {
+    let input = evaluation.input;
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let V_g = &input.constant.V_g;
+
+    let mut sum = 0.0;
+    for g in relation.products(m, decision) {
+        sum += evaluation.DP(m, g) * V_g[g] / 2.0;
+    }
+
+    sum
+}
+
+pub fn dc_NP0_TVP_constraint_approx(input: &Input, m: Retailer) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.c_m[m] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (NP0_TVP_constraint(&new_input) - NP0_TVP_constraint(input)) / 0.0001
+}
+
+pub fn dcrm_NP0_TVP_constraint(input: &Input, s: Supplier) -> f64 {
+    let relation = input.relation;
+
+    let drm_sl = &input.mrgame.parameter.drm_sl;
+    let VRM_l = &input.constant.VRM_l;
+
+    let mut sum = 0.0;
+    for &l in relation.materials(s) {
+        sum += drm_sl[s][l] * VRM_l[l] / 2.0;
+    }
+
+    sum
+}
+
+pub fn dcrm_NP0_TVP_constraint_approx(input: &Input, s: Supplier) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.crm_s[s] += 0.0001;
 
     let new_input = Input {
         mrgame: &mrgame,
@@ -497,7 +775,6 @@ pub fn ddrm_NP0_TVP_constraint(input: &Input, s: Supplier, l: Material) -> f64 {
     crm_s[s] * VRM_l[l] / 2.0
 }
 
-#[allow(dead_code)]
 pub fn ddrm_NP0_TVP_constraint_approx(input: &Input, s: Supplier, l: Material) -> f64 {
     let mut mrgame = input.mrgame.clone();
     mrgame.parameter.drm_sl[s][l] += 0.0001;
@@ -509,3 +786,22 @@ pub fn ddrm_NP0_TVP_constraint_approx(input: &Input, s: Supplier, l: Material) -
 
     (NP0_TVP_constraint(&new_input) - NP0_TVP_constraint(input)) / 0.0001
 }
+
+/// Derivative of `dcrm_NP0_TVP_constraint(s)` with respect to `drm_sl`.
+pub fn dcrmddrm_NP0_TVP_constraint(input: &Input, _s: Supplier, l: Material) -> f64 {
+    let VRM_l = &input.constant.VRM_l;
+
+    VRM_l[l] / 2.0
+}
+
+pub fn dcrmddrm_NP0_TVP_constraint_approx(input: &Input, s: Supplier, l: Material) -> f64 {
+    let mut mrgame = input.mrgame.clone();
+    mrgame.parameter.crm_s[s] += 0.0001;
+
+    let new_input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    (ddrm_NP0_TVP_constraint(&new_input, s, l) - ddrm_NP0_TVP_constraint(input, s, l)) / 0.0001
+}
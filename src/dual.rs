@@ -0,0 +1,348 @@
+//! Forward-mode automatic differentiation. A Dual carries the derivative along
+//! one seeded direction next to the value; a HyperDual carries the derivatives
+//! along two seeded directions and the mixed second derivative, so one
+//! evaluation yields one entry of a Hessian.
+
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+pub trait Scalar:
+    Copy
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Add<f64, Output = Self>
+    + Sub<f64, Output = Self>
+    + Mul<f64, Output = Self>
+    + Div<f64, Output = Self>
+    + AddAssign
+    + SubAssign
+{
+    fn constant(x: f64) -> Self;
+
+    /// `x` as a variable; `first` and `second` are its components of the
+    /// first and the second seeded direction, 1 and 0 for a single variable.
+    fn seeded(x: f64, first: f64, second: f64) -> Self;
+
+    fn value(self) -> f64;
+
+    fn powf(self, n: f64) -> Self;
+
+    fn recip(self) -> Self;
+}
+
+impl Scalar for f64 {
+    fn constant(x: f64) -> Self {
+        x
+    }
+
+    fn seeded(x: f64, _first: f64, _second: f64) -> Self {
+        x
+    }
+
+    fn value(self) -> f64 {
+        self
+    }
+
+    fn powf(self, n: f64) -> Self {
+        f64::powf(self, n)
+    }
+
+    fn recip(self) -> Self {
+        1.0 / self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Dual {
+    pub re: f64,
+    pub eps: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HyperDual {
+    pub re: f64,
+    pub e1: f64,
+    pub e2: f64,
+    pub e12: f64,
+}
+
+impl Dual {
+    // f(x) with f'(x) = df
+    fn chain(self, f: f64, df: f64) -> Self {
+        Dual {
+            re: f,
+            eps: df * self.eps,
+        }
+    }
+}
+
+impl HyperDual {
+    // f(x) with f'(x) = df and f''(x) = ddf
+    fn chain(self, f: f64, df: f64, ddf: f64) -> Self {
+        HyperDual {
+            re: f,
+            e1: df * self.e1,
+            e2: df * self.e2,
+            e12: df * self.e12 + ddf * self.e1 * self.e2,
+        }
+    }
+}
+
+impl Scalar for Dual {
+    fn constant(x: f64) -> Self {
+        Dual { re: x, eps: 0.0 }
+    }
+
+    fn seeded(x: f64, first: f64, _second: f64) -> Self {
+        Dual { re: x, eps: first }
+    }
+
+    fn value(self) -> f64 {
+        self.re
+    }
+
+    fn powf(self, n: f64) -> Self {
+        self.chain(self.re.powf(n), n * self.re.powf(n - 1.0))
+    }
+
+    fn recip(self) -> Self {
+        let x = self.re;
+        self.chain(1.0 / x, -1.0 / (x * x))
+    }
+}
+
+impl Scalar for HyperDual {
+    fn constant(x: f64) -> Self {
+        HyperDual {
+            re: x,
+            e1: 0.0,
+            e2: 0.0,
+            e12: 0.0,
+        }
+    }
+
+    fn seeded(x: f64, first: f64, second: f64) -> Self {
+        HyperDual {
+            re: x,
+            e1: first,
+            e2: second,
+            e12: 0.0,
+        }
+    }
+
+    fn value(self) -> f64 {
+        self.re
+    }
+
+    fn powf(self, n: f64) -> Self {
+        let x = self.re;
+        self.chain(
+            x.powf(n),
+            n * x.powf(n - 1.0),
+            n * (n - 1.0) * x.powf(n - 2.0),
+        )
+    }
+
+    fn recip(self) -> Self {
+        let x = self.re;
+        self.chain(1.0 / x, -1.0 / (x * x), 2.0 / (x * x * x))
+    }
+}
+
+impl Add for Dual {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Dual {
+            re: self.re + other.re,
+            eps: self.eps + other.eps,
+        }
+    }
+}
+
+impl Sub for Dual {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Dual {
+            re: self.re - other.re,
+            eps: self.eps - other.eps,
+        }
+    }
+}
+
+impl Mul for Dual {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Dual {
+            re: self.re * other.re,
+            eps: self.re * other.eps + self.eps * other.re,
+        }
+    }
+}
+
+impl Div for Dual {
+    type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Self) -> Self {
+        self * other.recip()
+    }
+}
+
+impl Neg for Dual {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Dual {
+            re: -self.re,
+            eps: -self.eps,
+        }
+    }
+}
+
+impl Add for HyperDual {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        HyperDual {
+            re: self.re + other.re,
+            e1: self.e1 + other.e1,
+            e2: self.e2 + other.e2,
+            e12: self.e12 + other.e12,
+        }
+    }
+}
+
+impl Sub for HyperDual {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        HyperDual {
+            re: self.re - other.re,
+            e1: self.e1 - other.e1,
+            e2: self.e2 - other.e2,
+            e12: self.e12 - other.e12,
+        }
+    }
+}
+
+impl Mul for HyperDual {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        HyperDual {
+            re: self.re * other.re,
+            e1: self.re * other.e1 + self.e1 * other.re,
+            e2: self.re * other.e2 + self.e2 * other.re,
+            e12: self.re * other.e12
+                + self.e1 * other.e2
+                + self.e2 * other.e1
+                + self.e12 * other.re,
+        }
+    }
+}
+
+impl Div for HyperDual {
+    type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Self) -> Self {
+        self * other.recip()
+    }
+}
+
+impl Neg for HyperDual {
+    type Output = Self;
+    fn neg(self) -> Self {
+        HyperDual {
+            re: -self.re,
+            e1: -self.e1,
+            e2: -self.e2,
+            e12: -self.e12,
+        }
+    }
+}
+
+// Arithmetic with constants and the compound assignments follow from the
+// operations above.
+macro_rules! impl_scalar_ops {
+    ($t:ty) => {
+        impl Add<f64> for $t {
+            type Output = Self;
+            fn add(self, other: f64) -> Self {
+                self + <$t>::constant(other)
+            }
+        }
+
+        impl Sub<f64> for $t {
+            type Output = Self;
+            fn sub(self, other: f64) -> Self {
+                self - <$t>::constant(other)
+            }
+        }
+
+        impl Mul<f64> for $t {
+            type Output = Self;
+            fn mul(self, other: f64) -> Self {
+                self * <$t>::constant(other)
+            }
+        }
+
+        impl Div<f64> for $t {
+            type Output = Self;
+            fn div(self, other: f64) -> Self {
+                self * <$t>::constant(1.0 / other)
+            }
+        }
+
+        impl AddAssign for $t {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $t {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+    };
+}
+
+impl_scalar_ops!(Dual);
+impl_scalar_ops!(HyperDual);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn f<T: Scalar>(x: T, y: T) -> T {
+        x * x * y + (x / y) - y.powf(1.5) * 2.0 + 3.0
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let (x, y) = (1.5, 2.5);
+
+        let dx = f(Dual::seeded(x, 1.0, 0.0), Dual::seeded(y, 0.0, 0.0));
+        assert_approx_eq!(dx.re, f(x, y));
+        assert_approx_eq!(dx.eps, 2.0 * x * y + 1.0 / y);
+
+        let dy = f(Dual::seeded(x, 0.0, 0.0), Dual::seeded(y, 1.0, 0.0));
+        assert_approx_eq!(dy.eps, x * x - x / (y * y) - 3.0 * y.sqrt());
+
+        // Along the direction (1, 2) the derivative is dx + 2 dy.
+        let along = f(Dual::seeded(x, 1.0, 0.0), Dual::seeded(y, 2.0, 0.0));
+        assert_approx_eq!(along.eps, dx.eps + 2.0 * dy.eps);
+
+        let dxy = f(
+            HyperDual::seeded(x, 1.0, 0.0),
+            HyperDual::seeded(y, 0.0, 1.0),
+        );
+        assert_approx_eq!(dxy.e1, dx.eps);
+        assert_approx_eq!(dxy.e2, dy.eps);
+        assert_approx_eq!(dxy.e12, 2.0 * x - 1.0 / (y * y));
+
+        let dyy = f(
+            HyperDual::seeded(x, 0.0, 0.0),
+            HyperDual::seeded(y, 1.0, 1.0),
+        );
+        assert_approx_eq!(dyy.e12, 2.0 * x / (y * y * y) - 1.5 / y.sqrt());
+    }
+}
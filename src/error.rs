@@ -0,0 +1,98 @@
+use crate::validation::ValidationError;
+use std::fmt;
+
+/// Errors of the fallible construction and input APIs.
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// An id outside the range of its entity, or an entity without the
+    /// relation a lookup asks for, e.g. an alternative without a module.
+    UnknownEntity {
+        entity: &'static str,
+        id: usize,
+    },
+    Dimension {
+        field: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    /// A pair list that sets the entity counts is empty.
+    EmptyRelation {
+        pairs: &'static str,
+    },
+    NonFinite {
+        field: &'static str,
+        value: f64,
+    },
+    Invalid(Vec<ValidationError>),
+    /// A search would enumerate more than `limit` candidates.
+    TooManyCandidates {
+        limit: usize,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "cannot read scenario: {}", e),
+            Error::Parse(e) => write!(f, "invalid scenario: {}", e),
+            Error::Serialize(e) => write!(f, "cannot write scenario: {}", e),
+            Error::UnknownEntity { entity, id } => write!(f, "unknown {} {}", entity, id),
+            Error::Dimension {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "field `{}`: expected {} entries, found {}",
+                field, expected, found
+            ),
+            Error::EmptyRelation { pairs } => write!(f, "`{}` is empty", pairs),
+            Error::NonFinite { field, value } => {
+                write!(f, "field `{}`: {} is not a finite number", field, value)
+            }
+            Error::Invalid(errors) => {
+                write!(f, "invalid constants:")?;
+                for e in errors.iter() {
+                    write!(f, "\n\t{}", e)?;
+                }
+                Ok(())
+            }
+            Error::TooManyCandidates { limit } => {
+                write!(f, "more than {} candidates to search", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Vec<ValidationError>> for Error {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        Error::Invalid(errors)
+    }
+}
+
+/// `Error::Dimension` unless `found == expected`.
+pub fn check_len(field: &'static str, found: usize, expected: usize) -> Result<()> {
+    if found != expected {
+        return Err(Error::Dimension {
+            field,
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// `value`, or `Error::NonFinite` when it is NaN or infinite.
+pub fn finite(field: &'static str, value: f64) -> Result<f64> {
+    if !value.is_finite() {
+        return Err(Error::NonFinite { field, value });
+    }
+    Ok(value)
+}
@@ -0,0 +1,47 @@
+//! Stackelberg model of a software manufacturer selling through competing
+//! retailers.
+//!
+//! A scenario fixes the entities and their relations ([`Relation`]), the
+//! model constants ([`Constant`]) and a starting point for the manufacturer's
+//! game ([`MRGame`]) and the retailers' game ([`RRGame`]). [`Input`] bundles
+//! the four for the evaluation functions of [`computation`], and the
+//! [`solver`] module searches for the equilibrium.
+//!
+//! ```no_run
+//! use software_economics::{solver, Input, Scenario};
+//!
+//! let scenario = Scenario::load("scenarios/example.toml").unwrap();
+//! let (relation, constant, mrgame, rrgame) = scenario.build_validated().unwrap();
+//!
+//! let input = Input {
+//!     relation: &relation,
+//!     constant: &constant,
+//!     mrgame: &mrgame,
+//!     rrgame: &rrgame,
+//! };
+//! let result = solver::stackelberg_solve(&input, &solver::StackelbergOptions::default());
+//! result.show(&relation);
+//! ```
+
+#![allow(non_snake_case)]
+
+pub mod bounds;
+pub mod computation;
+pub mod dual;
+pub mod error;
+pub mod mrgame;
+pub mod newton;
+mod parallel;
+pub mod relation;
+pub mod rrgame;
+pub mod scenario;
+pub mod second_order;
+pub mod solver;
+pub mod validation;
+
+pub use computation::Input;
+pub use error::{Error, Result};
+pub use mrgame::MRGame;
+pub use relation::{Constant, Relation};
+pub use rrgame::RRGame;
+pub use scenario::Scenario;
@@ -3,61 +3,41 @@
 // KSTN - CNTT - K60 //
 ///////////////////////
 
-mod computation;
-mod input;
-mod mrgame;
-mod newton;
-mod relation;
-mod rrgame;
-mod solver;
-
-use mrgame::MRGame;
-use relation::Material;
-use relation::Retailer;
-use rrgame::RRGame;
+use software_economics::relation::{show_header, Retailer};
+use software_economics::{computation, solver, Scenario};
+use std::env;
+use std::process;
 
 fn main() {
-    let (relation, constant) = input::input();
-    let mut mrgame = MRGame::new(&relation);
-
-    mrgame.decision.input(&relation, &[true, true, true, true]);
-    // mrgame.decision.show(&relation);
-
-    mrgame.parameter.input_A_g_c_m(
-        &relation,
-        &[3787.0, 3562.0, 1000.0, 6200.0],
-        &[0.1721, 0.1403],
-    );
-    mrgame
-        .parameter
-        .input_crm_s(&relation, &[0.1673, 0.4874, 0.3463]);
-    mrgame.parameter.input_drm_sl(
-        &relation,
-        &[
-            &[2000.0, 1000.0, 3000.0],
-            &[2000.0, 0.0, 4000.0],
-            &[5000.0, 1500.0, 0.0],
-        ],
-    );
-    mrgame.parameter.show(&relation);
-
-    let mut rrgame = RRGame::new(&relation);
-    rrgame.parameter.input_p_mg(
-        &relation,
-        &[&[67.88, 61.02, 0.0, 55.72], &[70.69, 0.0, 0.0, 63.57]],
-    );
-    rrgame.parameter.show_p_mg(&relation);
-
-    rrgame.parameter.input_a_mg(
-        &relation,
-        &[
-            &[1049.85, 2850.10, 0.0, 631.43],
-            &[2509.19, 0.0, 0.0, 1501.64],
-        ],
-    );
-    rrgame.parameter.show_a_mg(&relation);
-
-    {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!(
+                "usage: software_economics <scenario.toml> [--portfolio | --multistart | --sensitivity] [--interior] [--verbose]"
+            );
+            process::exit(2);
+        }
+    };
+
+    let scenario = Scenario::load(&path).and_then(|scenario| scenario.build_validated());
+    let (relation, constant, mrgame, rrgame) = match scenario {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let flags: Vec<String> = env::args().skip(2).collect();
+    let verbose = flags.iter().any(|flag| flag == "--verbose");
+
+    // The starting point and the hand-written derivatives next to their
+    // finite-difference checks.
+    if verbose {
+        mrgame.parameter.show(&relation);
+        rrgame.parameter.show_p_mg(&relation);
+        rrgame.parameter.show_a_mg(&relation);
+
         let input = computation::Input {
             relation: &relation,
             constant: &constant,
@@ -65,129 +45,161 @@ fn main() {
             rrgame: &rrgame,
         };
 
+        let evaluation = computation::Evaluation::new(&input);
+
         let retailer1 = Retailer { id: 0 };
         for g in relation.products(retailer1, &mrgame.decision) {
-            let demand = computation::DP(&input, retailer1, g);
-            println!("DP_{}{}: {}", retailer1.id, g.id, demand);
+            let demand = evaluation.DP(retailer1, g);
+            let (retailer, product) = (
+                relation.retailer_label(retailer1),
+                relation.product_label(g),
+            );
+            println!("DP[{}, {}]: {}", retailer, product, demand);
         }
 
         for m in relation.initial_retailers() {
-            println!("TVR_constraint: {}", computation::TVR_constraint(&input, m));
-            println!("Ta_constraint: {}", computation::Ta_constraint(&input, m));
+            let retailer = relation.retailer_label(m);
+            println!(
+                "TVR_constraint {}: {}",
+                retailer,
+                evaluation.TVR_constraint(m)
+            );
+            println!(
+                "Ta_constraint {}: {}",
+                retailer,
+                evaluation.Ta_constraint(m)
+            );
         }
 
         for m in relation.initial_retailers() {
-            let profit = computation::NP(&input, m);
-            println!("Profit: {}", profit);
+            let profit = evaluation.NP(m);
+            println!("Profit {}: {}", relation.retailer_label(m), profit);
         }
 
+        let retailers = || {
+            relation
+                .initial_retailers()
+                .map(|m| relation.retailer_label(m))
+        };
+        let products = || relation.all_products().map(|g| relation.product_label(g));
+
         println!("dc_NP0");
+        show_header(retailers());
+        print!("\t");
         for m in relation.initial_retailers() {
-            print!("{}\t", computation::dc_NP0(&input, m));
+            print!("{}\t", computation::dc_NP0(&evaluation, m));
         }
-        println!("");
+        println!();
 
         println!("dc_NP0_approx");
+        show_header(retailers());
+        print!("\t");
         for m in relation.initial_retailers() {
             print!("{}\t", computation::dc_NP0_approx(&input, m));
         }
-        println!("");
+        println!();
 
-        println!("NP0 = {}", computation::NP0(&input));
-        println!(
-            "NP0 TVP constraint: {}",
-            computation::NP0_TVP_constraint(&input)
-        );
-        println!("NP0 BOM constraint: ");
-        for l in relation.all_materials() {
-            print!("{}\t", computation::NP0_bom_constraint(&input, l));
+        println!("drho_NP0");
+        show_header(products());
+        print!("\t");
+        for g in relation.all_products() {
+            print!("{}\t", computation::drho_NP0(&evaluation, g));
         }
-        println!("");
-    }
+        println!();
 
-    for _step in 0..2 {
-        for m in relation.initial_retailers() {
-            println!("----------------------------------");
-            let new_parameter = {
-                let input = computation::Input {
-                    relation: &relation,
-                    constant: &constant,
-                    rrgame: &rrgame,
-                    mrgame: &mrgame,
-                };
-                solver::rrgame_solve(&input, m)
-            };
-            match new_parameter {
-                Some(new_parameter) => {
-                    println!("m: {}", m.id);
-                    new_parameter.show_p_mg(&relation);
-                    new_parameter.show_a_mg(&relation);
-
-                    for g in relation.products(m, &mrgame.decision) {
-                        rrgame.parameter.p_mg[m][g] = new_parameter.p_mg[m][g];
-                        rrgame.parameter.a_mg[m][g] = new_parameter.a_mg[m][g];
-                    }
-                }
-                None => {}
-            }
+        println!("drho_NP0_approx");
+        show_header(products());
+        print!("\t");
+        for g in relation.all_products() {
+            print!("{}\t", computation::drho_NP0_approx(&input, g));
         }
-    }
-
-    {
-        println!("======================================");
-        let input = computation::Input {
-            relation: &relation,
-            constant: &constant,
-            rrgame: &rrgame,
-            mrgame: &mrgame,
-        };
+        println!();
 
-        let constraints = solver::MRGameConstraints {};
-        let lambdas = solver::MRGameLambdas::new(&relation);
-        let parameter = solver::mrgame_solve_constraints(&input, constraints);
-
-        println!("Old A_g");
+        println!("drho_NP0_response");
+        show_header(products());
+        print!("\t");
         for g in relation.all_products() {
-            print!("{}\t", input.mrgame.parameter.A_g[g]);
+            let value = solver::drho_NP0_response(&input, g).unwrap_or(f64::NAN);
+            print!("{}\t", value);
         }
-        println!("");
-        if let Some(parameter) = parameter {
-            println!("New A_g");
+        println!();
+
+        println!("drho_NP");
+        show_header(products());
+        for m in relation.initial_retailers() {
+            print!("{}\t", relation.retailer_label(m));
             for g in relation.all_products() {
-                print!("{}\t", parameter.A_g[g]);
+                if relation.products(m, &mrgame.decision).any(|x| x == g) {
+                    print!("{}\t", computation::drho_NP(&evaluation, m, g));
+                } else {
+                    print!("-\t");
+                }
             }
-            println!("");
+            println!();
+        }
 
-            println!("New c_m");
-            for m in relation.initial_retailers() {
-                print!("{}\t", parameter.c_m[m]);
-            }
-            println!("");
+        println!("NP0 = {}", evaluation.NP0());
+        println!("NP0 TVP constraint: {}", evaluation.NP0_TVP_constraint());
+        println!("NP0 BOM constraint: ");
+        show_header(relation.all_materials().map(|l| relation.material_label(l)));
+        print!("\t");
+        for l in relation.all_materials() {
+            print!("{}\t", evaluation.NP0_bom_constraint(l));
+        }
+        println!();
+        println!("======================================");
+    }
 
-            println!("New crm_s");
-            for s in relation.all_suppliers() {
-                print!("{}\t", parameter.crm_s[s]);
+    let input = computation::Input {
+        relation: &relation,
+        constant: &constant,
+        rrgame: &rrgame,
+        mrgame: &mrgame,
+    };
+    let mut stackelberg = solver::StackelbergOptions::default();
+    if flags.iter().any(|flag| flag == "--interior") {
+        stackelberg.nash.method = solver::RRGameMethod::InteriorPoint;
+    }
+    if flags.iter().any(|flag| flag == "--multistart") {
+        let options = solver::MultiStartOptions {
+            method: stackelberg.nash.method,
+            ..solver::MultiStartOptions::default()
+        };
+        for m in relation.initial_retailers() {
+            println!("RR game, retailer {}", relation.retailer_label(m));
+            solver::rrgame_multistart_solve(&input, m, &options).show(&relation);
+        }
+        println!("MR game");
+        solver::mrgame_multistart_solve(&input, &options).show(&relation);
+    } else if flags.iter().any(|flag| flag == "--sensitivity") {
+        let options = solver::SensitivityOptions {
+            stackelberg,
+            ..solver::SensitivityOptions::default()
+        };
+        solver::sensitivity_solve(&input, &options).show(&relation);
+    } else if flags.iter().any(|flag| flag == "--portfolio") {
+        let options = solver::PortfolioOptions {
+            stackelberg,
+            ..solver::PortfolioOptions::default()
+        };
+        let result = match solver::portfolio_solve(&input, &options) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                process::exit(1);
             }
-            println!("");
-
-            println!("Old NP0 = {}", computation::NP0(&input));
-            let decision = input.mrgame.decision.clone();
-            let mrgame = mrgame::MRGame {
-                parameter: parameter,
-                decision: decision,
-            };
-            let new_input = computation::Input {
-                mrgame: &mrgame,
-                ..input
-            };
-
-            println!("New NP0 = {}", computation::NP0(&new_input));
-
-            println!("dA_NP0");
-            for j in relation.all_products() {
-                print!("{}\t", computation::dA_NP0(&new_input, j));
+        };
+        if verbose {
+            if let Some((_, result)) = &result.best {
+                result.show_reports(&relation);
             }
-            println!("");
         }
+        result.show(&relation);
+    } else {
+        let result = solver::stackelberg_solve(&input, &stackelberg);
+        if verbose {
+            result.show_reports(&relation);
+        }
+        result.show(&relation);
     }
 }
@@ -1,6 +1,7 @@
+use crate::error::{check_len, finite, Result};
+use crate::relation::{relative_change, show_header, RetailerMap, SupplierMap};
 use crate::relation::{Alternative, Module, Product};
 use crate::relation::{AlternativeMap, MaterialMap, ProductMap, Relation};
-use crate::relation::{RetailerMap, SupplierMap};
 
 #[derive(Clone)]
 pub struct Decision {
@@ -17,6 +18,8 @@ pub struct Parameter {
     pub drm_sl: SupplierMap<MaterialMap<f64>>,
 }
 
+/// The manufacturer's game: which products and alternatives are offered and
+/// the parameters the manufacturer sets.
 #[derive(Clone)]
 pub struct MRGame {
     pub decision: Decision,
@@ -24,7 +27,7 @@ pub struct MRGame {
 }
 
 fn bool_to_float(b: bool) -> f64 {
-    if b == true {
+    if b {
         1.0
     } else {
         0.0
@@ -56,20 +59,23 @@ impl Decision {
         )
     }
 
-    #[allow(dead_code)]
-    pub fn input(&mut self, relation: &Relation, data: &[bool]) {
+    pub fn input(&mut self, relation: &Relation, data: &[bool]) -> Result<()> {
+        check_len("product", data.len(), relation.all_products().count())?;
+
         for g in relation.all_products() {
             self.product[g] = data[g.id];
         }
+        Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn show(&self, relation: &Relation) {
         println!("Product Decision");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
+        print!("\t");
         for g in relation.all_products() {
             print!("{}\t", self.product[g]);
         }
-        println!("");
+        println!();
     }
 }
 
@@ -84,61 +90,104 @@ impl Parameter {
         }
     }
 
-    #[allow(dead_code)]
-    pub fn input_A_g_c_m(&mut self, relation: &Relation, A_g: &[f64], c_m: &[f64]) {
+    /// Largest `relative_change` between the variables of `self` and `other`.
+    pub fn max_change(&self, relation: &Relation, other: &Parameter) -> f64 {
+        let mut result: f64 = 0.0;
+        for g in relation.all_products() {
+            result = result.max(relative_change(self.A_g[g], other.A_g[g]));
+            result = result.max(relative_change(self.rho_g[g], other.rho_g[g]));
+        }
+
+        for m in relation.initial_retailers() {
+            result = result.max(relative_change(self.c_m[m], other.c_m[m]));
+        }
+
+        for s in relation.all_suppliers() {
+            result = result.max(relative_change(self.crm_s[s], other.crm_s[s]));
+            for &l in relation.materials(s) {
+                result = result.max(relative_change(self.drm_sl[s][l], other.drm_sl[s][l]));
+            }
+        }
+
+        result
+    }
+
+    pub fn input_A_g_c_m(&mut self, relation: &Relation, A_g: &[f64], c_m: &[f64]) -> Result<()> {
+        check_len("A_g", A_g.len(), relation.all_products().count())?;
+        check_len("c_m", c_m.len(), relation.initial_retailers().count())?;
+
         for g in relation.all_products() {
-            self.A_g[g] = A_g[g.id];
+            self.A_g[g] = finite("A_g", A_g[g.id])?;
         }
 
         for m in relation.initial_retailers() {
-            self.c_m[m] = c_m[m.id];
+            self.c_m[m] = finite("c_m", c_m[m.id])?;
         }
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn input_crm_s(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_crm_s(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("crm_s", data.len(), relation.all_suppliers().count())?;
+
         for s in relation.all_suppliers() {
-            self.crm_s[s] = data[s.id];
+            self.crm_s[s] = finite("crm_s", data[s.id])?;
         }
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn input_drm_sl(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_drm_sl(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_len("drm_sl", data.len(), relation.all_suppliers().count())?;
+
         for s in relation.all_suppliers() {
+            check_len("drm_sl", data[s.id].len(), relation.all_materials().count())?;
             for l in relation.all_materials() {
                 self.drm_sl[s][l] = data[s.id][l.id];
             }
+            for &l in relation.materials(s) {
+                finite("drm_sl", self.drm_sl[s][l])?;
+            }
         }
+        Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn show(&self, relation: &Relation) {
         println!("A_g");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
+        print!("\t");
         for g in relation.all_products() {
             print!("{}\t", self.A_g[g]);
         }
-        println!("");
+        println!();
 
         println!("c_m");
+        show_header(
+            relation
+                .initial_retailers()
+                .map(|m| relation.retailer_label(m)),
+        );
+        print!("\t");
         for m in relation.initial_retailers() {
             print!("{}\t", self.c_m[m]);
         }
-        println!("");
+        println!();
 
         println!("crm_s");
+        show_header(relation.all_suppliers().map(|s| relation.supplier_label(s)));
+        print!("\t");
         for s in relation.all_suppliers() {
             print!("{}\t", self.crm_s[s]);
         }
-        println!("");
+        println!();
 
         println!("drm_sl");
         for s in relation.all_suppliers() {
-            for l in relation.materials(s) {
-                print!("{}\t", self.drm_sl[s][l]);
+            print!("{}\t", relation.supplier_label(s));
+            for &l in relation.materials(s) {
+                print!("{}: {}\t", relation.material_label(l), self.drm_sl[s][l]);
             }
-            println!("");
+            println!();
         }
-        println!("");
+        println!();
     }
 }
 
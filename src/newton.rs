@@ -1,42 +1,47 @@
+use crate::bounds::{Bound, BoundSide};
+use crate::parallel::par_map;
 use ndarray::{Array1, Array2};
 use ndarray_linalg::Solve;
 
 const N: usize = 10;
 
-#[allow(dead_code)]
 pub fn simple_newton_method(f: impl Fn(f64) -> f64, df: impl Fn(f64) -> f64, x0: f64) -> f64 {
     let mut x = x0;
     for _ in 0..N {
         x = x - f(x) / df(x);
     }
-    return x;
+    x
 }
 
+/// Forward difference Jacobian of `f` at `x0` with the step `dx0[col]` for
+/// column `col`. The columns are independent and are built concurrently with
+/// the `parallel` feature.
 pub fn jacobi(
-    f: &impl Fn(&Array1<f64>) -> Array1<f64>,
+    f: &(impl Fn(&Array1<f64>) -> Array1<f64> + Sync),
     x0: &Array1<f64>,
     dx0: &Array1<f64>,
 ) -> Array2<f64> {
     let len = x0.len();
-    let mut jmatrix = Array2::zeros((len, len));
-    for col in 0..len {
+    let fx0 = f(x0);
+    let columns = par_map(len, |col| {
         let delta = dx0[col];
         let mut x = x0.clone();
         x[col] += delta;
-        let df_col = (f(&x) - f(x0)) / delta;
+        (f(&x) - &fx0) / delta
+    });
 
+    let mut jmatrix = Array2::zeros((len, len));
+    for (col, df_col) in columns.iter().enumerate() {
         for row in 0..len {
             jmatrix[(row, col)] = df_col[row];
         }
     }
 
-    // println!("{}", jmatrix);
     jmatrix
 }
 
-#[allow(dead_code)]
 pub fn newton_method(
-    f: &impl Fn(&Array1<f64>) -> Array1<f64>,
+    f: &(impl Fn(&Array1<f64>) -> Array1<f64> + Sync),
     x0: &Array1<f64>,
     dx0: &Array1<f64>,
     epsilon: f64,
@@ -51,17 +56,357 @@ pub fn newton_method(
             Err(_) => return None,
         };
         x = x + epsilon * dx;
-        // println!("x: {}", x);
     }
 
     Some(x)
 }
 
+#[derive(Debug, Clone)]
+pub struct NewtonOptions {
+    /// Converged once `‖f(x)‖` falls below this value.
+    pub f_tolerance: f64,
+    /// Stalled once `‖dx‖ <= dx_tolerance * (1 + ‖x‖)` while `‖f(x)‖` is
+    /// still above `f_tolerance`.
+    pub dx_tolerance: f64,
+    pub max_iterations: usize,
+    /// Sufficient decrease constant of the Armijo condition on the merit
+    /// function `‖f‖² / 2`.
+    pub armijo: f64,
+    /// Factor the step length is multiplied by on every backtrack.
+    pub backtrack: f64,
+    /// Stalled once the step length falls below this value.
+    pub min_step: f64,
+    /// Diverged once `‖f‖` grows beyond this value.
+    pub max_f_norm: f64,
+    /// Stalled once the last `progress_window` iterations reduced `‖f‖` by
+    /// less than the share `min_progress` together.
+    pub progress_window: usize,
+    pub min_progress: f64,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct NewtonStats {
+    pub iterations: usize,
+    /// Evaluations of `f` outside of the Jacobian callback.
+    pub evaluations: usize,
+    pub jacobians: usize,
+    pub backtracks: usize,
+    pub f_norm: f64,
+    pub dx_norm: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum NewtonResult {
+    Converged(Array1<f64>, NewtonStats),
+    /// The line search found no decrease, the step or the decrease of `‖f‖`
+    /// became negligible before `‖f‖` fell below `f_tolerance`, or
+    /// `max_iterations` ran out.
+    Stalled(Array1<f64>, NewtonStats),
+    /// The Jacobian could not be factorised; holds the last iterate.
+    Singular(Array1<f64>, NewtonStats),
+    /// `f` became non-finite or exceeded `max_f_norm`.
+    Diverged(Array1<f64>, NewtonStats),
+    /// `bounded_newton_solve` still changed the bounds it holds after its
+    /// last round; holds the last iterate.
+    BoundCycle(Array1<f64>, NewtonStats),
+}
+
+impl Default for NewtonOptions {
+    fn default() -> Self {
+        Self {
+            f_tolerance: 0.000001,
+            dx_tolerance: 0.0000000001,
+            max_iterations: 50,
+            armijo: 0.0001,
+            backtrack: 0.5,
+            min_step: 0.00000001,
+            max_f_norm: 1.0e20,
+            progress_window: 10,
+            min_progress: 0.01,
+        }
+    }
+}
+
+impl NewtonResult {
+    pub fn x(&self) -> &Array1<f64> {
+        match self {
+            NewtonResult::Converged(x, _) => x,
+            NewtonResult::Stalled(x, _) => x,
+            NewtonResult::Singular(x, _) => x,
+            NewtonResult::Diverged(x, _) => x,
+            NewtonResult::BoundCycle(x, _) => x,
+        }
+    }
+
+    pub fn stats(&self) -> &NewtonStats {
+        match self {
+            NewtonResult::Converged(_, stats) => stats,
+            NewtonResult::Stalled(_, stats) => stats,
+            NewtonResult::Singular(_, stats) => stats,
+            NewtonResult::Diverged(_, stats) => stats,
+            NewtonResult::BoundCycle(_, stats) => stats,
+        }
+    }
+
+    /// The solution, or `None` unless the method converged.
+    pub fn converged(self) -> Option<Array1<f64>> {
+        match self {
+            NewtonResult::Converged(x, _) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+fn norm(x: &Array1<f64>) -> f64 {
+    x.dot(x).sqrt()
+}
+
+/// Newton's method with a backtracking line search on `‖f‖² / 2`. Pass
+/// `|x| jacobi(f, x, dx0)` as `jacobian` when no analytic Jacobian is at hand.
+pub fn newton_solve(
+    f: &impl Fn(&Array1<f64>) -> Array1<f64>,
+    jacobian: &impl Fn(&Array1<f64>) -> Array2<f64>,
+    x0: &Array1<f64>,
+    options: &NewtonOptions,
+) -> NewtonResult {
+    projected_newton_solve(
+        f,
+        jacobian,
+        x0,
+        &|_: &Array1<f64>, _: &mut Array1<f64>| false,
+        options,
+    )
+    .0
+}
+
+/// `newton_solve` with every trial point of the line search passed through
+/// `project` together with the current iterate, which tells whether it moved
+/// the point, so that `f` is only evaluated at projected points. Stops after
+/// the first step the projection cut short and tells so next to the result,
+/// which is then `Stalled` unless it converged.
+fn projected_newton_solve(
+    f: &impl Fn(&Array1<f64>) -> Array1<f64>,
+    jacobian: &impl Fn(&Array1<f64>) -> Array2<f64>,
+    x0: &Array1<f64>,
+    project: &impl Fn(&Array1<f64>, &mut Array1<f64>) -> bool,
+    options: &NewtonOptions,
+) -> (NewtonResult, bool) {
+    let mut stats = NewtonStats::default();
+
+    let mut x = x0.clone();
+    let mut fx = f(&x);
+    stats.evaluations += 1;
+    stats.f_norm = norm(&fx);
+    let mut f_norms = vec![stats.f_norm];
+
+    loop {
+        if !stats.f_norm.is_finite() || stats.f_norm > options.max_f_norm {
+            return (NewtonResult::Diverged(x, stats), false);
+        }
+
+        if stats.f_norm <= options.f_tolerance {
+            return (NewtonResult::Converged(x, stats), false);
+        }
+
+        if stats.iterations >= options.max_iterations {
+            return (NewtonResult::Stalled(x, stats), false);
+        }
+
+        let jx = jacobian(&x);
+        stats.jacobians += 1;
+
+        let dx = match jx.solve_into(-&fx) {
+            Ok(dx) if dx.iter().all(|v| v.is_finite()) => dx,
+            _ => return (NewtonResult::Singular(x, stats), false),
+        };
+        stats.iterations += 1;
+        let dx_norm = norm(&dx);
+
+        // Along the Newton direction the merit function decreases at the rate
+        // ‖f‖², so the Armijo condition reads ‖f_new‖² <= (1 - 2 c t) ‖f‖².
+        let merit = stats.f_norm * stats.f_norm;
+        let mut step = 1.0;
+        let clipped = loop {
+            let mut x_new = &x + &(step * &dx);
+            let clipped = project(&x, &mut x_new);
+            if x_new == x {
+                return (NewtonResult::Stalled(x, stats), clipped);
+            }
+            let f_new = f(&x_new);
+            stats.evaluations += 1;
+
+            let f_norm = norm(&f_new);
+            if f_norm * f_norm <= (1.0 - 2.0 * options.armijo * step) * merit {
+                stats.dx_norm = norm(&(&x_new - &x));
+                x = x_new;
+                fx = f_new;
+                stats.f_norm = f_norm;
+                break clipped;
+            }
+
+            step *= options.backtrack;
+            stats.backtracks += 1;
+            if step < options.min_step {
+                return (NewtonResult::Stalled(x, stats), false);
+            }
+        };
+
+        if stats.f_norm <= options.f_tolerance {
+            return (NewtonResult::Converged(x, stats), clipped);
+        }
+
+        if clipped || dx_norm <= options.dx_tolerance * (1.0 + norm(&x)) {
+            return (NewtonResult::Stalled(x, stats), clipped);
+        }
+
+        f_norms.push(stats.f_norm);
+        if let Some(past) = f_norms
+            .len()
+            .checked_sub(options.progress_window + 1)
+            .map(|index| f_norms[index])
+        {
+            if stats.f_norm > (1.0 - options.min_progress) * past {
+                return (NewtonResult::Stalled(x, stats), false);
+            }
+        }
+    }
+}
+
+/// Rounds of holding and releasing bounds `bounded_newton_solve` tries.
+const MAX_BOUND_ROUNDS: usize = 50;
+
+/// The share of the distance to a bound a projected step may cover when the
+/// variable is not at that bound yet.
+const BOUND_STEP: f64 = 0.995;
+
+/// `newton_solve` for the stationarity conditions of a maximisation over a
+/// box: rows `0..bounds.len()` of `f` must be `∂L/∂x_i` of the variables
+/// `x[0..bounds.len()]`, the remaining rows are left alone.
+///
+/// The line search projects every trial point onto the box, so `f` is never
+/// evaluated outside of it, and a round ends at every step the projection
+/// cuts short. A projected step stops short of a bound its variable is not
+/// at by `1 - BOUND_STEP` of the distance, so that a variable only lands on
+/// a bound in the few steps it takes to get within `f_tolerance` of it, and
+/// not in a single step past a kink of `f` there. A variable that starts or
+/// ends a round at a bound it is pushed against is held there, its row
+/// replaced by `x_i - bound = 0`. A held variable is released again when its
+/// multiplier, `-∂L/∂x_i` at a lower and `∂L/∂x_i` at an upper bound, is
+/// negative. Returns the last result with the bound each variable is held at,
+/// `BoundCycle` when the held bounds have not settled after
+/// `MAX_BOUND_ROUNDS` rounds.
+pub fn bounded_newton_solve(
+    f: &impl Fn(&Array1<f64>) -> Array1<f64>,
+    jacobian: &impl Fn(&Array1<f64>) -> Array2<f64>,
+    x0: &Array1<f64>,
+    bounds: &[Bound],
+    options: &NewtonOptions,
+) -> (NewtonResult, Vec<Option<BoundSide>>) {
+    let epsilon = options.f_tolerance;
+    let mut held: Vec<Option<BoundSide>> = vec![None; bounds.len()];
+
+    let project = |x: &Array1<f64>, x_new: &mut Array1<f64>| {
+        let mut clipped = false;
+        for (i, bound) in bounds.iter().enumerate() {
+            let lower = x[i] - BOUND_STEP * (x[i] - bound.lower);
+            let upper = x[i] + BOUND_STEP * (bound.upper - x[i]);
+            let clamped = x_new[i].max(lower).min(upper);
+            if clamped != x_new[i] {
+                x_new[i] = clamped;
+                clipped = true;
+            }
+        }
+        clipped
+    };
+    let mut x = x0.clone();
+    for (i, bound) in bounds.iter().enumerate() {
+        x[i] = bound.clamp(x[i]);
+    }
+
+    // A variable that starts at a bound it is pushed against is held from the
+    // first round on.
+    let gradient = f(&x);
+    for (i, bound) in bounds.iter().enumerate() {
+        held[i] = bound.blocking(x[i], gradient[i], epsilon);
+    }
+
+    let mut round = 0;
+    loop {
+        let held_f = |x: &Array1<f64>| {
+            let mut fx = f(x);
+            for (i, side) in held.iter().enumerate() {
+                if let Some(side) = side {
+                    fx[i] = x[i] - bounds[i].value(*side);
+                }
+            }
+            fx
+        };
+        let held_jacobian = |x: &Array1<f64>| {
+            let mut jx = jacobian(x);
+            for (i, side) in held.iter().enumerate() {
+                if side.is_some() {
+                    jx.row_mut(i).fill(0.0);
+                    jx[(i, i)] = 1.0;
+                }
+            }
+            jx
+        };
+
+        let (result, clipped) =
+            projected_newton_solve(&held_f, &held_jacobian, &x, &project, options);
+        match result {
+            NewtonResult::Singular(_, _) | NewtonResult::Diverged(_, _) => return (result, held),
+            _ => {}
+        }
+
+        let gradient = f(result.x());
+        let mut changed = false;
+        for (i, bound) in bounds.iter().enumerate() {
+            if held[i].is_none() {
+                if let Some(side) = bound.blocking(result.x()[i], gradient[i], epsilon) {
+                    held[i] = Some(side);
+                    changed = true;
+                }
+            }
+        }
+
+        // Release only once no variable is newly held and the last step
+        // reached the point Newton's method aimed at, so that a variable is
+        // not released on a multiplier that holding another one changes.
+        if !changed && !clipped {
+            for (i, side) in held.iter_mut().enumerate() {
+                let released = match side {
+                    Some(BoundSide::Lower) => gradient[i] > epsilon,
+                    Some(BoundSide::Upper) => gradient[i] < -epsilon,
+                    None => false,
+                };
+                if released {
+                    *side = None;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return (result, held);
+            }
+        }
+
+        round += 1;
+        if round >= MAX_BOUND_ROUNDS {
+            let stats = *result.stats();
+            return (NewtonResult::BoundCycle(result.x().clone(), stats), held);
+        }
+
+        x = result.x().clone();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use assert_approx_eq::assert_approx_eq;
-    use ndarray::arr1;
+    use ndarray::{arr1, arr2};
+    use std::cell::Cell;
 
     #[test]
     fn test_simple_newton_method() {
@@ -83,7 +428,7 @@ mod tests {
         let f = |x: &Array1<f64>| arr1(&[2.0 * x[0] + x[1] - 5.0, 4.0 * x[0] - 3.0 * x[1] + 5.0]);
         let x0 = arr1(&[10.0, 10.0]);
         let dx0 = arr1(&[0.000001, 0.000001]);
-        let x = newton_method(&f, &x0, &dx0).unwrap();
+        let x = newton_method(&f, &x0, &dx0, 1.0, 10).unwrap();
         assert_approx_eq!(x[0], 1.0);
         assert_approx_eq!(x[1], 3.0);
     }
@@ -101,4 +446,95 @@ mod tests {
         assert_approx_eq!(j[(1, 0)], -4.0, 0.0001);
         assert_approx_eq!(j[(1, 1)], 2.0, 0.0001);
     }
+
+    #[test]
+    fn test_newton_solve() {
+        let options = NewtonOptions::default();
+        let dx0 = arr1(&[0.000001, 0.000001]);
+
+        let f = |x: &Array1<f64>| arr1(&[2.0 * x[0] + x[1] - 5.0, 4.0 * x[0] - 3.0 * x[1] + 5.0]);
+        let j = |x: &Array1<f64>| jacobi(&f, x, &dx0);
+        let result = newton_solve(&f, &j, &arr1(&[10.0, 10.0]), &options);
+        let stats = *result.stats();
+        let x = result.converged().unwrap();
+        assert_approx_eq!(x[0], 1.0);
+        assert_approx_eq!(x[1], 3.0);
+        assert!(stats.iterations <= 2);
+
+        // Far from the root a full step overshoots, so the line search has to
+        // shorten it.
+        let f = |x: &Array1<f64>| arr1(&[x[0].atan(), x[1] - 1.0]);
+        let j = |x: &Array1<f64>| jacobi(&f, x, &dx0);
+        let result = newton_solve(&f, &j, &arr1(&[10.0, 0.0]), &options);
+        assert!(result.stats().backtracks > 0);
+        let x = result.clone().converged().unwrap();
+        assert_approx_eq!(x[0], 0.0);
+        assert_approx_eq!(x[1], 1.0);
+
+        let j = |x: &Array1<f64>| arr2(&[[1.0 / (1.0 + x[0] * x[0]), 0.0], [0.0, 1.0]]);
+        let analytic = newton_solve(&f, &j, &arr1(&[10.0, 0.0]), &options);
+        assert_eq!(analytic.stats().iterations, result.stats().iterations);
+        let x = analytic.converged().unwrap();
+        assert_approx_eq!(x[0], 0.0);
+
+        let f = |x: &Array1<f64>| arr1(&[x[0] + x[1], 2.0 * x[0] + 2.0 * x[1] - 1.0]);
+        let j = |x: &Array1<f64>| jacobi(&f, x, &dx0);
+        let result = newton_solve(&f, &j, &arr1(&[1.0, 1.0]), &options);
+        assert!(matches!(result, NewtonResult::Singular(_, _)));
+
+        let f = |x: &Array1<f64>| arr1(&[x[0] * x[0] + 1.0, x[1]]);
+        let j = |x: &Array1<f64>| jacobi(&f, x, &dx0);
+        let result = newton_solve(&f, &j, &arr1(&[1.0, 1.0]), &options);
+        assert!(matches!(result, NewtonResult::Stalled(_, _)));
+
+        let f = |x: &Array1<f64>| arr1(&[x[0] * f64::INFINITY, x[1]]);
+        let j = |x: &Array1<f64>| jacobi(&f, x, &dx0);
+        let result = newton_solve(&f, &j, &arr1(&[1.0, 1.0]), &options);
+        assert!(matches!(result, NewtonResult::Diverged(_, _)));
+    }
+
+    #[test]
+    fn test_bounded_newton_solve() {
+        let options = NewtonOptions::default();
+
+        // Maximises -(x0 - 3)² - (x1 + 1)² + x1 x2 / 10 - x2² subject to
+        // x2 = 0 over x0 <= 2, x1 >= 0; the last row is the equality.
+        let f =
+            |x: &Array1<f64>| arr1(&[-2.0 * (x[0] - 3.0), -2.0 * (x[1] + 1.0) + x[2] / 10.0, x[2]]);
+        let j = |_: &Array1<f64>| arr2(&[[-2.0, 0.0, 0.0], [0.0, -2.0, 0.1], [0.0, 0.0, 1.0]]);
+        let bounds = [Bound::new(f64::NEG_INFINITY, 2.0), Bound::non_negative()];
+
+        let (result, held) =
+            bounded_newton_solve(&f, &j, &arr1(&[0.0, 5.0, 1.0]), &bounds, &options);
+        assert_eq!(held, vec![Some(BoundSide::Upper), Some(BoundSide::Lower)]);
+        let x = result.converged().unwrap();
+        assert_approx_eq!(x[0], 2.0);
+        assert_approx_eq!(x[1], 0.0);
+        assert_approx_eq!(x[2], 0.0);
+
+        // With a box around the maximum no bound is held.
+        let bounds = [Bound::new(0.0, 4.0), Bound::unbounded()];
+        let (result, held) =
+            bounded_newton_solve(&f, &j, &arr1(&[0.0, 5.0, 1.0]), &bounds, &options);
+        assert_eq!(held, vec![None, None]);
+        let x = result.converged().unwrap();
+        assert_approx_eq!(x[0], 3.0);
+        assert_approx_eq!(x[1], -1.0);
+
+        // The full Newton step from x0 = 1 lands at -1, outside the box, where
+        // f must not be evaluated.
+        let outside = Cell::new(false);
+        let f = |x: &Array1<f64>| {
+            if x[0] < 0.0 {
+                outside.set(true);
+            }
+            arr1(&[-2.0 * (x[0] + 1.0)])
+        };
+        let j = |_: &Array1<f64>| arr2(&[[-2.0]]);
+        let (result, held) =
+            bounded_newton_solve(&f, &j, &arr1(&[1.0]), &[Bound::non_negative()], &options);
+        assert!(!outside.get());
+        assert_eq!(held, vec![Some(BoundSide::Lower)]);
+        assert_approx_eq!(result.converged().unwrap()[0], 0.0);
+    }
 }
@@ -0,0 +1,30 @@
+//! Independent evaluations that run on the rayon thread pool with the
+//! `parallel` feature and one after another without it.
+
+/// `[f(0), f(1), ..., f(len - 1)]`, in this order with or without threads.
+#[cfg(feature = "parallel")]
+pub fn par_map<T: Send>(len: usize, f: impl Fn(usize) -> T + Sync + Send) -> Vec<T> {
+    use rayon::prelude::*;
+
+    (0..len).into_par_iter().map(f).collect()
+}
+
+/// `[f(0), f(1), ..., f(len - 1)]`, in this order with or without threads.
+#[cfg(not(feature = "parallel"))]
+pub fn par_map<T: Send>(len: usize, f: impl Fn(usize) -> T + Sync + Send) -> Vec<T> {
+    (0..len).map(f).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_map_order() {
+        let squares = par_map(1000, |i| i * i);
+        assert_eq!(squares.len(), 1000);
+        for (i, square) in squares.iter().enumerate() {
+            assert_eq!(*square, i * i);
+        }
+    }
+}
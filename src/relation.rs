@@ -1,36 +1,54 @@
+use crate::bounds::Bounds;
+use crate::error::{check_len, finite, Error, Result};
 use crate::mrgame;
+use ndarray::{s, Array1, Array2, Array4, ArrayView2};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Supplier {
     pub id: usize,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Material {
     pub id: usize,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Alternative {
     pub id: usize,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Module {
     pub id: usize,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Product {
     pub id: usize,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Retailer {
     pub id: usize,
 }
 
+/// Name and free-form metadata of an entity. Reports label an entity by its
+/// name, or by its index letter and id, e.g. `g2`, when it has none.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntityInfo {
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Entity counts and the pairs linking suppliers, materials, alternatives,
+/// modules, products and retailers.
 pub struct Relation {
     supplier_count: usize,
     material_count: usize,
@@ -38,11 +56,113 @@ pub struct Relation {
     retailer_count: usize,
     alternative_count: usize,
     module_count: usize,
-    pub supplier_materials: Vec<(Supplier, Material)>,
+    supplier_materials: Vec<(Supplier, Material)>,
     retailer_products: Vec<(Retailer, Product)>,
     alternative_modules: Vec<(Alternative, Module)>,
     material_alternatives: Vec<(Material, Alternative)>,
     alternative_products: Vec<(Alternative, Product)>,
+
+    /// Built by the pair setters. The pair lists are private so the indexes
+    /// cannot get out of sync with them.
+    retailer_product_index: Adjacency<Product>,
+    product_retailer_index: Adjacency<Retailer>,
+    supplier_material_index: Adjacency<Material>,
+    material_supplier_index: Adjacency<Supplier>,
+    alternative_module_index: Adjacency<Module>,
+    module_alternative_index: Adjacency<Alternative>,
+    alternative_product_index: Adjacency<Product>,
+    product_alternative_index: Adjacency<Alternative>,
+    material_alternative_index: Adjacency<Alternative>,
+    alternative_material_index: Adjacency<Material>,
+
+    /// Reset by the pair setter that sets the count of their entity, so
+    /// entities are named after the pairs are set.
+    pub supplier_info: SupplierMap<EntityInfo>,
+    pub material_info: MaterialMap<EntityInfo>,
+    pub product_info: ProductMap<EntityInfo>,
+    pub retailer_info: RetailerMap<EntityInfo>,
+    pub alternative_info: AlternativeMap<EntityInfo>,
+    pub module_info: ModuleMap<EntityInfo>,
+}
+
+/// Compressed sparse row adjacency: the neighbours of the entity with id
+/// `id` are `targets[offsets[id]..offsets[id + 1]]`, in the order of the pairs.
+#[derive(Debug, Clone, PartialEq)]
+struct Adjacency<E> {
+    offsets: Vec<usize>,
+    targets: Vec<E>,
+}
+
+impl<E: Copy> Adjacency<E> {
+    fn new() -> Self {
+        Adjacency {
+            offsets: vec![0],
+            targets: Vec::new(),
+        }
+    }
+
+    /// Groups `pairs` by `source`, keeping their order within a group.
+    fn build<P>(pairs: &[P], source: impl Fn(&P) -> usize, target: impl Fn(&P) -> E) -> Self {
+        let count = pairs.iter().map(&source).max().map_or(0, |id| id + 1);
+
+        let mut offsets = vec![0; count + 1];
+        for p in pairs.iter() {
+            offsets[source(p) + 1] += 1;
+        }
+        for id in 0..count {
+            offsets[id + 1] += offsets[id];
+        }
+
+        let mut sorted: Vec<&P> = pairs.iter().collect();
+        sorted.sort_by_key(|p| source(p));
+        let targets = sorted.into_iter().map(target).collect();
+
+        Adjacency { offsets, targets }
+    }
+
+    /// Empty for ids past the largest source id.
+    fn get(&self, id: usize) -> &[E] {
+        if id + 1 >= self.offsets.len() {
+            return &[];
+        }
+        &self.targets[self.offsets[id]..self.offsets[id + 1]]
+    }
+}
+
+/// Prints the column labels of a table whose rows start with a label.
+pub fn show_header(labels: impl Iterator<Item = String>) {
+    print!("\t");
+    for label in labels {
+        print!("{}\t", label);
+    }
+    println!();
+}
+
+fn label(info: &EntityInfo, letter: &str, id: usize) -> String {
+    match &info.name {
+        Some(name) => name.clone(),
+        None => format!("{}{}", letter, id),
+    }
+}
+
+/// One past the largest id that `id` picks from `pairs`.
+fn count(pairs: &[(usize, usize)], id: impl Fn(&(usize, usize)) -> usize) -> usize {
+    pairs.iter().map(id).max().map_or(0, |id| id + 1)
+}
+
+/// `Error::Dimension` unless `data` has `rows` rows of `cols` entries.
+fn check_rows<T>(field: &'static str, data: &[&[T]], rows: usize, cols: usize) -> Result<()> {
+    check_len(field, data.len(), rows)?;
+    for row in data.iter() {
+        check_len(field, row.len(), cols)?;
+    }
+    Ok(())
+}
+
+impl Default for Relation {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Relation {
@@ -60,34 +180,91 @@ impl Relation {
             alternative_modules: Vec::new(),
             material_alternatives: Vec::new(),
             alternative_products: Vec::new(),
+
+            retailer_product_index: Adjacency::new(),
+            product_retailer_index: Adjacency::new(),
+            supplier_material_index: Adjacency::new(),
+            material_supplier_index: Adjacency::new(),
+            alternative_module_index: Adjacency::new(),
+            module_alternative_index: Adjacency::new(),
+            alternative_product_index: Adjacency::new(),
+            product_alternative_index: Adjacency::new(),
+            material_alternative_index: Adjacency::new(),
+            alternative_material_index: Adjacency::new(),
+
+            supplier_info: EntityMap::from_vec(Vec::new()),
+            material_info: EntityMap::from_vec(Vec::new()),
+            product_info: EntityMap::from_vec(Vec::new()),
+            retailer_info: EntityMap::from_vec(Vec::new()),
+            alternative_info: EntityMap::from_vec(Vec::new()),
+            module_info: EntityMap::from_vec(Vec::new()),
         }
     }
 
-    pub fn supplier_material_pairs(&mut self, pairs: &[(usize, usize)]) {
-        self.supplier_count = pairs.iter().map(|p| p.0).max().unwrap() + 1;
-        self.material_count = pairs.iter().map(|p| p.1).max().unwrap() + 1;
+    pub fn supplier_material_pairs(&mut self, pairs: &[(usize, usize)]) -> Result<()> {
+        if pairs.is_empty() {
+            return Err(Error::EmptyRelation {
+                pairs: "supplier_material_pairs",
+            });
+        }
+
+        self.supplier_count = count(pairs, |p| p.0);
+        self.material_count = count(pairs, |p| p.1);
         self.supplier_materials = pairs
             .iter()
             .map(|p| (Supplier { id: p.0 }, Material { id: p.1 }))
             .collect();
-    }
+        self.supplier_material_index =
+            Adjacency::build(&self.supplier_materials, |p| p.0.id, |p| p.1);
+        self.material_supplier_index =
+            Adjacency::build(&self.supplier_materials, |p| p.1.id, |p| p.0);
+        self.supplier_info = SupplierMap::new(self, EntityInfo::default());
+        self.material_info = MaterialMap::new(self, EntityInfo::default());
+        Ok(())
+    }
+
+    pub fn retailer_product_pairs(&mut self, pairs: &[(usize, usize)]) -> Result<()> {
+        if pairs.is_empty() {
+            return Err(Error::EmptyRelation {
+                pairs: "retailer_product_pairs",
+            });
+        }
 
-    pub fn retailer_product_pairs(&mut self, pairs: &[(usize, usize)]) {
-        self.product_count = pairs.iter().map(|p| p.1).max().unwrap() + 1;
-        self.retailer_count = pairs.iter().map(|p| p.0).max().unwrap() + 1;
+        self.product_count = count(pairs, |p| p.1);
+        self.retailer_count = count(pairs, |p| p.0);
         self.retailer_products = pairs
             .iter()
             .map(|p| (Retailer { id: p.0 }, Product { id: p.1 }))
             .collect();
-    }
+        self.retailer_product_index =
+            Adjacency::build(&self.retailer_products, |p| p.0.id, |p| p.1);
+        self.product_retailer_index =
+            Adjacency::build(&self.retailer_products, |p| p.1.id, |p| p.0);
+        self.product_info = ProductMap::new(self, EntityInfo::default());
+        self.retailer_info = RetailerMap::new(self, EntityInfo::default());
+        Ok(())
+    }
+
+    pub fn alternative_module_pairs(&mut self, pairs: &[(usize, usize)]) -> Result<()> {
+        if pairs.is_empty() {
+            return Err(Error::EmptyRelation {
+                pairs: "alternative_module_pairs",
+            });
+        }
 
-    pub fn alternative_module_pairs(&mut self, pairs: &[(usize, usize)]) {
-        self.module_count = pairs.iter().map(|p| p.1).max().unwrap() + 1;
-        self.alternative_count = pairs.iter().map(|p| p.0).max().unwrap() + 1;
+        self.module_count = count(pairs, |p| p.1);
+        self.alternative_count = count(pairs, |p| p.0);
         self.alternative_modules = pairs
             .iter()
             .map(|p| (Alternative { id: p.0 }, Module { id: p.1 }))
             .collect();
+        self.alternative_module_index =
+            Adjacency::build(&self.alternative_modules, |p| p.0.id, |p| p.1);
+        self.module_alternative_index =
+            Adjacency::build(&self.alternative_modules, |p| p.1.id, |p| p.0);
+        self.module_info = ModuleMap::new(self, EntityInfo::default());
+        self.alternative_info = AlternativeMap::new(self, EntityInfo::default());
+        Ok(())
     }
 
     pub fn material_alternative_pairs(&mut self, pairs: &[(usize, usize)]) {
@@ -95,6 +272,10 @@ impl Relation {
             .iter()
             .map(|p| (Material { id: p.0 }, Alternative { id: p.1 }))
             .collect();
+        self.material_alternative_index =
+            Adjacency::build(&self.material_alternatives, |p| p.0.id, |p| p.1);
+        self.alternative_material_index =
+            Adjacency::build(&self.material_alternatives, |p| p.1.id, |p| p.0);
     }
 
     pub fn alternative_product_pairs(&mut self, pairs: &[(usize, usize)]) {
@@ -102,19 +283,45 @@ impl Relation {
             .iter()
             .map(|p| (Alternative { id: p.0 }, Product { id: p.1 }))
             .collect();
+        self.alternative_product_index =
+            Adjacency::build(&self.alternative_products, |p| p.0.id, |p| p.1);
+        self.product_alternative_index =
+            Adjacency::build(&self.alternative_products, |p| p.1.id, |p| p.0);
     }
 
-    pub fn initial_products(&self, retailer: Retailer) -> impl Iterator<Item = Product> + '_ {
-        self.retailer_products
-            .iter()
-            .filter(move |p| p.0.id == retailer.id)
-            .map(|p| p.1)
+    pub fn supplier_materials(&self) -> &[(Supplier, Material)] {
+        &self.supplier_materials
+    }
+
+    pub fn retailer_products(&self) -> &[(Retailer, Product)] {
+        &self.retailer_products
+    }
+
+    pub fn alternative_modules(&self) -> &[(Alternative, Module)] {
+        &self.alternative_modules
+    }
+
+    pub fn material_alternatives(&self) -> &[(Material, Alternative)] {
+        &self.material_alternatives
+    }
+
+    pub fn alternative_products(&self) -> &[(Alternative, Product)] {
+        &self.alternative_products
+    }
+
+    pub fn initial_products(&self, retailer: Retailer) -> &[Product] {
+        self.retailer_product_index.get(retailer.id)
     }
 
-    pub fn products(&self, retailer: Retailer, decision: &mrgame::Decision) -> Vec<Product> {
+    pub fn products<'a>(
+        &'a self,
+        retailer: Retailer,
+        decision: &'a mrgame::Decision,
+    ) -> impl Iterator<Item = Product> + 'a {
         self.initial_products(retailer)
-            .filter(|g| decision.product[*g])
-            .collect()
+            .iter()
+            .copied()
+            .filter(move |g| decision.product[*g])
     }
 
     pub fn all_products(&self) -> impl Iterator<Item = Product> {
@@ -122,7 +329,7 @@ impl Relation {
     }
 
     pub fn initial_retailers(&self) -> impl Iterator<Item = Retailer> {
-        (0..self.retailer_count).map(|id| Retailer { id: id })
+        (0..self.retailer_count).map(|id| Retailer { id })
     }
 
     pub fn all_suppliers(&self) -> impl Iterator<Item = Supplier> {
@@ -133,12 +340,8 @@ impl Relation {
         (0..self.material_count).map(|id| Material { id })
     }
 
-    pub fn suppliers_for_material(&self, l: Material) -> Vec<Supplier> {
-        self.supplier_materials
-            .iter()
-            .filter(|p| p.1.id == l.id)
-            .map(|p| p.0)
-            .collect()
+    pub fn suppliers_for_material(&self, l: Material) -> &[Supplier] {
+        self.material_supplier_index.get(l.id)
     }
 
     pub fn all_alternatives(&self) -> impl Iterator<Item = Alternative> {
@@ -149,220 +352,342 @@ impl Relation {
         (0..self.module_count).map(|id| Module { id })
     }
 
-    pub fn materials(&self, supplier: Supplier) -> Vec<Material> {
-        self.supplier_materials
-            .iter()
-            .filter(|p| p.0.id == supplier.id)
-            .map(|p| p.1)
-            .collect()
+    pub fn materials(&self, supplier: Supplier) -> &[Material] {
+        self.supplier_material_index.get(supplier.id)
     }
 
-    pub fn module(&self, alternative: Alternative) -> Module {
-        self.alternative_modules
-            .iter()
-            .filter(|p| p.0.id == alternative.id)
-            .map(|p| p.1)
-            .next()
-            .unwrap()
+    pub fn module(&self, alternative: Alternative) -> Result<Module> {
+        self.alternative_module_index
+            .get(alternative.id)
+            .first()
+            .copied()
+            .ok_or(Error::UnknownEntity {
+                entity: "alternative",
+                id: alternative.id,
+            })
     }
 
-    pub fn alternatives_of_module(&self, module: Module) -> Vec<Alternative> {
-        self.alternative_modules
-            .iter()
-            .filter(|p| p.1.id == module.id)
-            .map(|p| p.0)
-            .collect()
+    pub fn alternatives_of_module(&self, module: Module) -> &[Alternative] {
+        self.module_alternative_index.get(module.id)
     }
 
-    pub fn products_for_alternative(
-        &self,
+    pub fn alternatives_of_product(&self, product: Product) -> &[Alternative] {
+        self.product_alternative_index.get(product.id)
+    }
+
+    pub fn alternatives_of_material(&self, material: Material) -> &[Alternative] {
+        self.material_alternative_index.get(material.id)
+    }
+
+    pub fn materials_of_alternative(&self, alternative: Alternative) -> &[Material] {
+        self.alternative_material_index.get(alternative.id)
+    }
+
+    pub fn products_for_alternative<'a>(
+        &'a self,
         alternative: Alternative,
-        decision: &mrgame::Decision,
-    ) -> Vec<Product> {
-        self.alternative_products
+        decision: &'a mrgame::Decision,
+    ) -> impl Iterator<Item = Product> + 'a {
+        let products: &[Product] = if decision.alternative[alternative] {
+            self.alternative_product_index.get(alternative.id)
+        } else {
+            &[]
+        };
+
+        products
             .iter()
-            .filter(|p| p.0.id == alternative.id)
-            .map(|p| p.1)
-            .filter(|g| decision.product[*g])
-            .collect()
+            .copied()
+            .filter(move |g| decision.product[*g])
     }
 
-    pub fn retailers(&self, product: Product) -> Vec<Retailer> {
-        self.retailer_products
-            .iter()
-            .filter(|p| p.1.id == product.id)
-            .map(|p| p.0)
-            .collect()
+    pub fn retailers(&self, product: Product) -> &[Retailer] {
+        self.product_retailer_index.get(product.id)
     }
-}
 
-#[derive(Clone)]
-pub struct RetailerMap<T: Clone> {
-    data: Vec<T>,
-}
+    pub fn supplier_label(&self, s: Supplier) -> String {
+        label(&self.supplier_info[s], "s", s.id)
+    }
 
-impl<T: Clone> RetailerMap<T> {
-    pub fn new(relation: &Relation, v: T) -> Self {
-        let mut data = Vec::new();
-        data.resize_with(relation.retailer_count, || v.clone());
-        Self { data }
+    pub fn supplier_by_name(&self, name: &str) -> Option<Supplier> {
+        self.all_suppliers()
+            .find(|s| self.supplier_info[*s].name.as_deref() == Some(name))
     }
-}
 
-impl<T: Clone> Index<Retailer> for RetailerMap<T> {
-    type Output = T;
+    pub fn material_label(&self, l: Material) -> String {
+        label(&self.material_info[l], "l", l.id)
+    }
 
-    fn index(&self, index: Retailer) -> &Self::Output {
-        &self.data[index.id]
+    pub fn material_by_name(&self, name: &str) -> Option<Material> {
+        self.all_materials()
+            .find(|l| self.material_info[*l].name.as_deref() == Some(name))
     }
-}
 
-impl<T: Clone> IndexMut<Retailer> for RetailerMap<T> {
-    fn index_mut(&mut self, index: Retailer) -> &mut Self::Output {
-        &mut self.data[index.id]
+    pub fn product_label(&self, g: Product) -> String {
+        label(&self.product_info[g], "g", g.id)
     }
-}
 
-#[derive(Clone)]
-pub struct ProductMap<T: Clone> {
-    data: Vec<T>,
-}
+    pub fn product_by_name(&self, name: &str) -> Option<Product> {
+        self.all_products()
+            .find(|g| self.product_info[*g].name.as_deref() == Some(name))
+    }
 
-impl<T: Clone> ProductMap<T> {
-    pub fn new(relation: &Relation, v: T) -> Self {
-        let mut data = Vec::new();
-        data.resize_with(relation.product_count, || v.clone());
-        Self { data }
+    pub fn retailer_label(&self, m: Retailer) -> String {
+        label(&self.retailer_info[m], "m", m.id)
     }
-}
 
-impl<T: Clone> Index<Product> for ProductMap<T> {
-    type Output = T;
+    pub fn retailer_by_name(&self, name: &str) -> Option<Retailer> {
+        self.initial_retailers()
+            .find(|m| self.retailer_info[*m].name.as_deref() == Some(name))
+    }
 
-    fn index(&self, index: Product) -> &Self::Output {
-        &self.data[index.id]
+    pub fn alternative_label(&self, k: Alternative) -> String {
+        label(&self.alternative_info[k], "k", k.id)
+    }
+
+    pub fn alternative_by_name(&self, name: &str) -> Option<Alternative> {
+        self.all_alternatives()
+            .find(|k| self.alternative_info[*k].name.as_deref() == Some(name))
+    }
+
+    pub fn module_label(&self, j: Module) -> String {
+        label(&self.module_info[j], "j", j.id)
+    }
+
+    pub fn module_by_name(&self, name: &str) -> Option<Module> {
+        self.all_modules()
+            .find(|j| self.module_info[*j].name.as_deref() == Some(name))
     }
 }
 
-impl<T: Clone> IndexMut<Product> for ProductMap<T> {
-    fn index_mut(&mut self, index: Product) -> &mut Self::Output {
-        &mut self.data[index.id]
+/// Change between two values relative to the larger of them, 0.0 when they
+/// are equal.
+pub fn relative_change(a: f64, b: f64) -> f64 {
+    if a == b {
+        0.0
+    } else {
+        (a - b).abs() / f64::max(a.abs(), b.abs())
     }
 }
 
-#[derive(Clone)]
-pub struct SupplierMap<T: Clone> {
+/// An entity kind, e.g. `Product`: an id that `Relation` counts.
+pub trait Entity: Copy {
+    fn id(self) -> usize;
+    fn from_id(id: usize) -> Self;
+    /// Number of entities of this kind in `relation`.
+    fn count(relation: &Relation) -> usize;
+}
+
+macro_rules! impl_entity {
+    ($entity:ident, $count:ident) => {
+        impl Entity for $entity {
+            fn id(self) -> usize {
+                self.id
+            }
+
+            fn from_id(id: usize) -> Self {
+                Self { id }
+            }
+
+            fn count(relation: &Relation) -> usize {
+                relation.$count
+            }
+        }
+    };
+}
+
+impl_entity!(Supplier, supplier_count);
+impl_entity!(Material, material_count);
+impl_entity!(Product, product_count);
+impl_entity!(Retailer, retailer_count);
+impl_entity!(Alternative, alternative_count);
+impl_entity!(Module, module_count);
+
+/// A value for every entity of kind `E`, indexed by the entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityMap<E: Entity, T> {
     data: Vec<T>,
+    entity: PhantomData<E>,
 }
 
-impl<T: Clone> SupplierMap<T> {
+pub type RetailerMap<T> = EntityMap<Retailer, T>;
+pub type ProductMap<T> = EntityMap<Product, T>;
+pub type SupplierMap<T> = EntityMap<Supplier, T>;
+pub type MaterialMap<T> = EntityMap<Material, T>;
+pub type AlternativeMap<T> = EntityMap<Alternative, T>;
+pub type ModuleMap<T> = EntityMap<Module, T>;
+
+impl<E: Entity, T: Clone> EntityMap<E, T> {
     pub fn new(relation: &Relation, v: T) -> Self {
-        let mut data = Vec::new();
-        data.resize_with(relation.supplier_count, || v.clone());
-        Self { data }
+        Self::from_vec(vec![v; E::count(relation)])
     }
 }
 
-impl<T: Clone> Index<Supplier> for SupplierMap<T> {
-    type Output = T;
+impl<E: Entity, T> EntityMap<E, T> {
+    fn from_vec(data: Vec<T>) -> Self {
+        Self {
+            data,
+            entity: PhantomData,
+        }
+    }
 
-    fn index(&self, index: Supplier) -> &Self::Output {
-        &self.data[index.id]
+    pub fn from_fn(relation: &Relation, f: impl FnMut(E) -> T) -> Self {
+        Self::from_vec((0..E::count(relation)).map(E::from_id).map(f).collect())
     }
-}
 
-impl<T: Clone> IndexMut<Supplier> for SupplierMap<T> {
-    fn index_mut(&mut self, index: Supplier) -> &mut Self::Output {
-        &mut self.data[index.id]
+    pub fn len(&self) -> usize {
+        self.data.len()
     }
-}
 
-#[derive(Clone)]
-pub struct MaterialMap<T: Clone> {
-    data: Vec<T>,
-}
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
 
-impl<T: Clone> MaterialMap<T> {
-    pub fn new(relation: &Relation, v: T) -> Self {
-        let mut data = Vec::new();
-        data.resize_with(relation.material_count, || v.clone());
-        Self { data }
+    pub fn iter(&self) -> impl Iterator<Item = (E, &T)> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(id, v)| (E::from_id(id), v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (E, &mut T)> {
+        self.data
+            .iter_mut()
+            .enumerate()
+            .map(|(id, v)| (E::from_id(id), v))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.data.iter()
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(E, &T) -> U) -> EntityMap<E, U> {
+        EntityMap::from_vec(self.iter().map(|(e, v)| f(e, v)).collect())
+    }
+
+    /// Combines the values of two maps over the same entities.
+    pub fn zip_with<U, V>(
+        &self,
+        other: &EntityMap<E, U>,
+        mut f: impl FnMut(E, &T, &U) -> V,
+    ) -> EntityMap<E, V> {
+        assert_eq!(self.len(), other.len());
+        EntityMap::from_vec(
+            self.iter()
+                .zip(other.values())
+                .map(|((e, a), b)| f(e, a, b))
+                .collect(),
+        )
     }
 }
 
-impl<T: Clone> Index<Material> for MaterialMap<T> {
-    type Output = T;
+impl<E: Entity> EntityMap<E, f64> {
+    pub fn to_array(&self) -> Array1<f64> {
+        Array1::from(self.data.clone())
+    }
 
-    fn index(&self, index: Material) -> &Self::Output {
-        &self.data[index.id]
+    pub fn from_array(relation: &Relation, array: &Array1<f64>) -> Result<Self> {
+        check_len("array", array.len(), E::count(relation))?;
+        Ok(Self::from_vec(array.to_vec()))
     }
 }
 
-impl<T: Clone> IndexMut<Material> for MaterialMap<T> {
-    fn index_mut(&mut self, index: Material) -> &mut Self::Output {
-        &mut self.data[index.id]
+impl<E: Entity, F: Entity> EntityMap<E, EntityMap<F, f64>> {
+    /// Rows indexed by `E`, columns by `F`.
+    pub fn to_array2(&self) -> Array2<f64> {
+        let columns = self.data.first().map_or(0, |row| row.len());
+        Array2::from_shape_fn((self.len(), columns), |(i, j)| self.data[i].data[j])
+    }
+
+    pub fn from_array2(relation: &Relation, array: &Array2<f64>) -> Result<Self> {
+        let (rows, columns) = array.dim();
+        check_len("array", rows, E::count(relation))?;
+        check_len("array", columns, F::count(relation))?;
+        Ok(Self::from_vec(
+            array
+                .outer_iter()
+                .map(|row| EntityMap::from_vec(row.to_vec()))
+                .collect(),
+        ))
     }
 }
 
-#[derive(Clone)]
-pub struct AlternativeMap<T: Clone> {
-    data: Vec<T>,
+/// A value for every combination of entities of kinds `A`, `B`, `C` and `D`,
+/// stored contiguously so the `(C, D)` block of an `(A, B)` pair is one slice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityArray4<A: Entity, B: Entity, C: Entity, D: Entity> {
+    data: Array4<f64>,
+    entity: PhantomData<(A, B, C, D)>,
 }
 
-impl<T: Clone> AlternativeMap<T> {
-    pub fn new(relation: &Relation, v: T) -> Self {
-        let mut data = Vec::new();
-        data.resize_with(relation.alternative_count, || v.clone());
-        Self { data }
+/// Cross effects of product `y` at retailer `x` on the demand of product `g`
+/// at retailer `m`, indexed `[(m, g, x, y)]`.
+pub type CrossMap = EntityArray4<Retailer, Product, Retailer, Product>;
+
+impl<A: Entity, B: Entity, C: Entity, D: Entity> EntityArray4<A, B, C, D> {
+    pub fn new(relation: &Relation, v: f64) -> Self {
+        let shape = (
+            A::count(relation),
+            B::count(relation),
+            C::count(relation),
+            D::count(relation),
+        );
+        Self {
+            data: Array4::from_elem(shape, v),
+            entity: PhantomData,
+        }
     }
-}
 
-impl<T: Clone> Index<Alternative> for AlternativeMap<T> {
-    type Output = T;
+    /// The values of every `(c, d)` for `a` and `b`.
+    pub fn view(&self, a: A, b: B) -> ArrayView2<'_, f64> {
+        self.data.slice(s![a.id(), b.id(), .., ..])
+    }
 
-    fn index(&self, index: Alternative) -> &Self::Output {
-        &self.data[index.id]
+    pub fn array(&self) -> &Array4<f64> {
+        &self.data
     }
-}
 
-impl<T: Clone> IndexMut<Alternative> for AlternativeMap<T> {
-    fn index_mut(&mut self, index: Alternative) -> &mut Self::Output {
-        &mut self.data[index.id]
+    pub fn array_mut(&mut self) -> &mut Array4<f64> {
+        &mut self.data
     }
 }
 
-#[derive(Clone)]
-pub struct ModuleMap<T: Clone> {
-    data: Vec<T>,
+impl<A: Entity, B: Entity, C: Entity, D: Entity> Index<(A, B, C, D)> for EntityArray4<A, B, C, D> {
+    type Output = f64;
+
+    fn index(&self, (a, b, c, d): (A, B, C, D)) -> &Self::Output {
+        &self.data[(a.id(), b.id(), c.id(), d.id())]
+    }
 }
 
-impl<T: Clone> ModuleMap<T> {
-    pub fn new(relation: &Relation, v: T) -> Self {
-        let mut data = Vec::new();
-        data.resize_with(relation.alternative_count, || v.clone());
-        Self { data }
+impl<A: Entity, B: Entity, C: Entity, D: Entity> IndexMut<(A, B, C, D)>
+    for EntityArray4<A, B, C, D>
+{
+    fn index_mut(&mut self, (a, b, c, d): (A, B, C, D)) -> &mut Self::Output {
+        &mut self.data[(a.id(), b.id(), c.id(), d.id())]
     }
 }
 
-impl<T: Clone> Index<Module> for ModuleMap<T> {
+impl<E: Entity, T> Index<E> for EntityMap<E, T> {
     type Output = T;
 
-    fn index(&self, index: Module) -> &Self::Output {
-        &self.data[index.id]
+    fn index(&self, index: E) -> &Self::Output {
+        &self.data[index.id()]
     }
 }
 
-impl<T: Clone> IndexMut<Module> for ModuleMap<T> {
-    fn index_mut(&mut self, index: Module) -> &mut Self::Output {
-        &mut self.data[index.id]
+impl<E: Entity, T> IndexMut<E> for EntityMap<E, T> {
+    fn index_mut(&mut self, index: E) -> &mut Self::Output {
+        &mut self.data[index.id()]
     }
 }
 
+/// The model constants, indexed by the entities of a `Relation`.
+#[derive(Clone)]
 pub struct Constant {
-    pub v_mgxy: RetailerMap<ProductMap<RetailerMap<ProductMap<f64>>>>,
-    pub ea_mgxy: RetailerMap<ProductMap<RetailerMap<ProductMap<f64>>>>,
-    pub beta_mgxy: RetailerMap<ProductMap<RetailerMap<ProductMap<f64>>>>,
-    pub ep_mgxy: RetailerMap<ProductMap<RetailerMap<ProductMap<f64>>>>,
+    pub v_mgxy: CrossMap,
+    pub ea_mgxy: CrossMap,
+    pub beta_mgxy: CrossMap,
+    pub ep_mgxy: CrossMap,
     pub K_mg: RetailerMap<ProductMap<f64>>,
     pub zeta_mg: RetailerMap<ProductMap<f64>>,
     pub HR_mg: RetailerMap<ProductMap<f64>>,
@@ -390,39 +715,18 @@ pub struct Constant {
     pub HP_g: ProductMap<f64>,
     pub VRM_l: MaterialMap<f64>,
     pub TVP: f64,
+
+    /// Bounds on the decision variables, enforced by the solvers.
+    pub bounds: Bounds,
 }
 
 impl Constant {
     pub fn new(relation: &Relation) -> Self {
         Self {
-            v_mgxy: RetailerMap::new(
-                relation,
-                ProductMap::new(
-                    relation,
-                    RetailerMap::new(relation, ProductMap::new(relation, 0.0)),
-                ),
-            ),
-            ea_mgxy: RetailerMap::new(
-                relation,
-                ProductMap::new(
-                    relation,
-                    RetailerMap::new(relation, ProductMap::new(relation, 0.0)),
-                ),
-            ),
-            beta_mgxy: RetailerMap::new(
-                relation,
-                ProductMap::new(
-                    relation,
-                    RetailerMap::new(relation, ProductMap::new(relation, 0.0)),
-                ),
-            ),
-            ep_mgxy: RetailerMap::new(
-                relation,
-                ProductMap::new(
-                    relation,
-                    RetailerMap::new(relation, ProductMap::new(relation, 1.0)),
-                ),
-            ),
+            v_mgxy: CrossMap::new(relation, 0.0),
+            ea_mgxy: CrossMap::new(relation, 0.0),
+            beta_mgxy: CrossMap::new(relation, 0.0),
+            ep_mgxy: CrossMap::new(relation, 1.0),
             eA_mgy: RetailerMap::new(
                 relation,
                 ProductMap::new(relation, ProductMap::new(relation, 0.0)),
@@ -456,439 +760,868 @@ impl Constant {
             HP_g: ProductMap::new(relation, 0.0),
             VRM_l: MaterialMap::new(relation, 1.0),
             TVP: 3500.0,
+
+            bounds: Bounds::new(relation),
         }
     }
 
-    pub fn input_v_mgxy(&mut self, relation: &Relation, m: usize, g: usize, data: &[&[f64]]) {
+    pub fn input_v_mgxy(
+        &mut self,
+        relation: &Relation,
+        m: usize,
+        g: usize,
+        data: &[&[f64]],
+    ) -> Result<()> {
+        if m >= relation.initial_retailers().count() {
+            return Err(Error::UnknownEntity {
+                entity: "retailer",
+                id: m,
+            });
+        }
+        if g >= relation.all_products().count() {
+            return Err(Error::UnknownEntity {
+                entity: "product",
+                id: g,
+            });
+        }
+        check_rows(
+            "v_mgxy",
+            data,
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        )?;
+
         let m = Retailer { id: m };
         let g = Product { id: g };
         for x in relation.initial_retailers() {
-            for y in relation.initial_products(x) {
-                self.v_mgxy[m][g][x][y] = data[x.id][y.id];
+            for &y in relation.initial_products(x) {
+                self.v_mgxy[(m, g, x, y)] = finite("v_mgxy", data[x.id][y.id])?;
             }
         }
-    }
+        Ok(())
+    }
+
+    pub fn input_ea_mgxy(
+        &mut self,
+        relation: &Relation,
+        m: usize,
+        g: usize,
+        data: &[&[f64]],
+    ) -> Result<()> {
+        if m >= relation.initial_retailers().count() {
+            return Err(Error::UnknownEntity {
+                entity: "retailer",
+                id: m,
+            });
+        }
+        if g >= relation.all_products().count() {
+            return Err(Error::UnknownEntity {
+                entity: "product",
+                id: g,
+            });
+        }
+        check_rows(
+            "ea_mgxy",
+            data,
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        )?;
 
-    pub fn input_ea_mgxy(&mut self, relation: &Relation, m: usize, g: usize, data: &[&[f64]]) {
         let m = Retailer { id: m };
         let g = Product { id: g };
         for x in relation.initial_retailers() {
-            for y in relation.initial_products(x) {
-                self.ea_mgxy[m][g][x][y] = data[x.id][y.id];
+            for &y in relation.initial_products(x) {
+                self.ea_mgxy[(m, g, x, y)] = finite("ea_mgxy", data[x.id][y.id])?;
             }
         }
-    }
+        Ok(())
+    }
+
+    pub fn input_beta_mgxy(
+        &mut self,
+        relation: &Relation,
+        m: usize,
+        g: usize,
+        data: &[&[f64]],
+    ) -> Result<()> {
+        if m >= relation.initial_retailers().count() {
+            return Err(Error::UnknownEntity {
+                entity: "retailer",
+                id: m,
+            });
+        }
+        if g >= relation.all_products().count() {
+            return Err(Error::UnknownEntity {
+                entity: "product",
+                id: g,
+            });
+        }
+        check_rows(
+            "beta_mgxy",
+            data,
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        )?;
 
-    pub fn input_beta_mgxy(&mut self, relation: &Relation, m: usize, g: usize, data: &[&[f64]]) {
         let m = Retailer { id: m };
         let g = Product { id: g };
         for x in relation.initial_retailers() {
-            for y in relation.initial_products(x) {
-                self.beta_mgxy[m][g][x][y] = data[x.id][y.id];
+            for &y in relation.initial_products(x) {
+                self.beta_mgxy[(m, g, x, y)] = finite("beta_mgxy", data[x.id][y.id])?;
             }
         }
+        Ok(())
     }
 
-    pub fn input_K_mg(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_K_mg(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_rows(
+            "K_mg",
+            data,
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        )?;
+
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                self.K_mg[m][g] = data[m.id][g.id];
+            for &g in relation.initial_products(m) {
+                self.K_mg[m][g] = finite("K_mg", data[m.id][g.id])?;
             }
         }
+        Ok(())
     }
 
-    pub fn input_zeta_mg(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_zeta_mg(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_rows(
+            "zeta_mg",
+            data,
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        )?;
+
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                self.zeta_mg[m][g] = data[m.id][g.id];
+            for &g in relation.initial_products(m) {
+                self.zeta_mg[m][g] = finite("zeta_mg", data[m.id][g.id])?;
             }
         }
+        Ok(())
     }
 
-    pub fn input_HR_mg(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_HR_mg(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_rows(
+            "HR_mg",
+            data,
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        )?;
+
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                self.HR_mg[m][g] = data[m.id][g.id];
+            for &g in relation.initial_products(m) {
+                self.HR_mg[m][g] = finite("HR_mg", data[m.id][g.id])?;
             }
         }
+        Ok(())
     }
 
-    pub fn input_TP_mg(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_TP_mg(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_rows(
+            "TP_mg",
+            data,
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        )?;
+
         for m in relation.initial_retailers() {
             for g in relation.all_products() {
                 self.TP_mg[m][g] = data[m.id][g.id];
             }
+            for &g in relation.initial_products(m) {
+                finite("TP_mg", self.TP_mg[m][g])?;
+            }
         }
+        Ok(())
     }
 
-    pub fn input_eA_mgy(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_eA_mgy(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_rows(
+            "eA_mgy",
+            data,
+            relation.retailer_products.len(),
+            relation.all_products().count(),
+        )?;
+
         let mut row: usize = 0;
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
+            for &g in relation.initial_products(m) {
                 for y in relation.all_products() {
-                    self.eA_mgy[m][g][y] = data[row][y.id];
+                    self.eA_mgy[m][g][y] = finite("eA_mgy", data[row][y.id])?;
                 }
                 row += 1;
             }
         }
+        Ok(())
     }
 
-    pub fn input_u_mgy(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_u_mgy(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_rows(
+            "u_mgy",
+            data,
+            relation.retailer_products.len(),
+            relation.all_products().count(),
+        )?;
+
         let mut row: usize = 0;
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
+            for &g in relation.initial_products(m) {
                 for y in relation.all_products() {
-                    self.u_mgy[m][g][y] = data[row][y.id];
+                    self.u_mgy[m][g][y] = finite("u_mgy", data[row][y.id])?;
                 }
                 row += 1;
             }
         }
+        Ok(())
     }
 
-    pub fn input_pw_g0(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_pw_g0(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("pw_g0", data.len(), relation.all_products().count())?;
+
         for g in relation.all_products() {
-            self.pw_g0[g] = data[g.id];
+            self.pw_g0[g] = finite("pw_g0", data[g.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_PCP_g(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_PCP_g(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("PCP_g", data.len(), relation.all_products().count())?;
+
         for g in relation.all_products() {
-            self.PCP_g[g] = data[g.id];
+            self.PCP_g[g] = finite("PCP_g", data[g.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_ORM_s(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_ORM_s(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("ORM_s", data.len(), relation.all_suppliers().count())?;
+
         for s in relation.all_suppliers() {
-            self.ORM_s[s] = data[s.id];
+            self.ORM_s[s] = finite("ORM_s", data[s.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_HRM_l(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_HRM_l(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("HRM_l", data.len(), relation.all_materials().count())?;
+
         for l in relation.all_materials() {
-            self.HRM_l[l] = data[l.id];
+            self.HRM_l[l] = finite("HRM_l", data[l.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_FCA_k(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_FCA_k(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("FCA_k", data.len(), relation.all_alternatives().count())?;
+
         for k in relation.all_alternatives() {
-            self.FCA_k[k] = data[k.id];
+            self.FCA_k[k] = finite("FCA_k", data[k.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_PCA_k(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_PCA_k(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("PCA_k", data.len(), relation.all_alternatives().count())?;
+
         for k in relation.all_alternatives() {
-            self.PCA_k[k] = data[k.id];
+            self.PCA_k[k] = finite("PCA_k", data[k.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_PCR_sl(&mut self, relation: &Relation, data: &[&[f64]]) {
+    pub fn input_PCR_sl(&mut self, relation: &Relation, data: &[&[f64]]) -> Result<()> {
+        check_rows(
+            "PCR_sl",
+            data,
+            relation.all_suppliers().count(),
+            relation.all_materials().count(),
+        )?;
+
         for s in relation.all_suppliers() {
             for l in relation.all_materials() {
                 self.PCR_sl[s][l] = data[s.id][l.id];
             }
+            for &l in relation.materials(s) {
+                finite("PCR_sl", self.PCR_sl[s][l])?;
+            }
         }
+        Ok(())
     }
 
-    pub fn input_TVR_m(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_TVR_m(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("TVR_m", data.len(), relation.initial_retailers().count())?;
+
         for m in relation.initial_retailers() {
-            self.TVR_m[m] = data[m.id];
+            self.TVR_m[m] = finite("TVR_m", data[m.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_Ta_m(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_Ta_m(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("Ta_m", data.len(), relation.initial_retailers().count())?;
+
         for m in relation.initial_retailers() {
-            self.Ta_m[m] = data[m.id];
+            self.Ta_m[m] = finite("Ta_m", data[m.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_OP_m(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_OP_m(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("OP_m", data.len(), relation.initial_retailers().count())?;
+
         for m in relation.initial_retailers() {
-            self.OP_m[m] = data[m.id];
+            self.OP_m[m] = finite("OP_m", data[m.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_delta_gk(&mut self, relation: &Relation, data: &[&[usize]]) {
+    pub fn input_delta_gk(&mut self, relation: &Relation, data: &[&[usize]]) -> Result<()> {
+        check_rows(
+            "delta_gk",
+            data,
+            relation.all_products().count(),
+            relation.all_alternatives().count(),
+        )?;
+
         for g in relation.all_products() {
             for k in relation.all_alternatives() {
                 self.delta_gk[g][k] = data[g.id][k.id];
             }
         }
+        Ok(())
     }
 
-    pub fn input_sigma_kl(&mut self, relation: &Relation, data: &[&[usize]]) {
+    pub fn input_sigma_kl(&mut self, relation: &Relation, data: &[&[usize]]) -> Result<()> {
+        check_rows(
+            "sigma_kl",
+            data,
+            relation.all_alternatives().count(),
+            relation.all_materials().count(),
+        )?;
+
         for k in relation.all_alternatives() {
             for l in relation.all_materials() {
                 self.sigma_kl[k][l] = data[k.id][l.id];
             }
         }
+        Ok(())
     }
 
-    pub fn input_FCM_j(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_FCM_j(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("FCM_j", data.len(), relation.all_modules().count())?;
+
         for j in relation.all_modules() {
-            self.FCM_j[j] = data[j.id];
+            self.FCM_j[j] = finite("FCM_j", data[j.id])?;
         }
+        Ok(())
     }
 
-    pub fn input_HP_g(&mut self, relation: &Relation, data: &[f64]) {
+    pub fn input_HP_g(&mut self, relation: &Relation, data: &[f64]) -> Result<()> {
+        check_len("HP_g", data.len(), relation.all_products().count())?;
+
         for g in relation.all_products() {
-            self.HP_g[g] = data[g.id];
+            self.HP_g[g] = finite("HP_g", data[g.id])?;
         }
+        Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn show_v_mgxy(&self, relation: &Relation) {
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                println!("{} {}", m.id, g.id);
+            for &g in relation.initial_products(m) {
+                println!(
+                    "v_mgxy[{}, {}]",
+                    relation.retailer_label(m),
+                    relation.product_label(g)
+                );
+                show_header(relation.all_products().map(|y| relation.product_label(y)));
                 for x in relation.initial_retailers() {
+                    print!("{}\t", relation.retailer_label(x));
                     for y in relation.all_products() {
-                        print!("{}\t", self.v_mgxy[m][g][x][y]);
+                        print!("{}\t", self.v_mgxy[(m, g, x, y)]);
                     }
-                    println!("");
+                    println!();
                 }
                 println!("-----------------------");
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_ea_mgxy(&self, relation: &Relation) {
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                println!("{} {}", m.id, g.id);
+            for &g in relation.initial_products(m) {
+                println!(
+                    "ea_mgxy[{}, {}]",
+                    relation.retailer_label(m),
+                    relation.product_label(g)
+                );
+                show_header(relation.all_products().map(|y| relation.product_label(y)));
                 for x in relation.initial_retailers() {
+                    print!("{}\t", relation.retailer_label(x));
                     for y in relation.all_products() {
-                        print!("{}\t", self.ea_mgxy[m][g][x][y]);
+                        print!("{}\t", self.ea_mgxy[(m, g, x, y)]);
                     }
-                    println!("");
+                    println!();
                 }
                 println!("-----------------------");
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_beta_mgxy(&self, relation: &Relation) {
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                println!("{} {}", m.id, g.id);
+            for &g in relation.initial_products(m) {
+                println!(
+                    "beta_mgxy[{}, {}]",
+                    relation.retailer_label(m),
+                    relation.product_label(g)
+                );
+                show_header(relation.all_products().map(|y| relation.product_label(y)));
                 for x in relation.initial_retailers() {
+                    print!("{}\t", relation.retailer_label(x));
                     for y in relation.all_products() {
-                        print!("{}\t", self.beta_mgxy[m][g][x][y]);
+                        print!("{}\t", self.beta_mgxy[(m, g, x, y)]);
                     }
-                    println!("");
+                    println!();
                 }
                 println!("-----------------------");
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_ep_mgxy(&self, relation: &Relation) {
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                println!("{} {}", m.id, g.id);
+            for &g in relation.initial_products(m) {
+                println!(
+                    "ep_mgxy[{}, {}]",
+                    relation.retailer_label(m),
+                    relation.product_label(g)
+                );
+                show_header(relation.all_products().map(|y| relation.product_label(y)));
                 for x in relation.initial_retailers() {
+                    print!("{}\t", relation.retailer_label(x));
                     for y in relation.all_products() {
-                        print!("{}\t", self.ep_mgxy[m][g][x][y]);
+                        print!("{}\t", self.ep_mgxy[(m, g, x, y)]);
                     }
-                    println!("");
+                    println!();
                 }
                 println!("-----------------------");
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_K_mg(&self, relation: &Relation) {
         println!("K_mg");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
         for m in relation.initial_retailers() {
+            print!("{}\t", relation.retailer_label(m));
             for g in relation.all_products() {
                 print!("{}\t", self.K_mg[m][g]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_zeta_mg(&self, relation: &Relation) {
         println!("zeta_mg");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
         for m in relation.initial_retailers() {
+            print!("{}\t", relation.retailer_label(m));
             for g in relation.all_products() {
                 print!("{}\t", self.zeta_mg[m][g]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_HR_mg(&self, relation: &Relation) {
         println!("HR_mg");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
         for m in relation.initial_retailers() {
+            print!("{}\t", relation.retailer_label(m));
             for g in relation.all_products() {
                 print!("{}\t", self.HR_mg[m][g]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_TP_mg(&self, relation: &Relation) {
         println!("TP_mg");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
         for m in relation.initial_retailers() {
+            print!("{}\t", relation.retailer_label(m));
             for g in relation.all_products() {
                 print!("{}\t", self.TP_mg[m][g]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_eA_mgy(&self, relation: &Relation) {
+        println!("eA_mgy");
+        show_header(relation.all_products().map(|y| relation.product_label(y)));
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                print!("eA{}{}y | ", m.id, g.id);
+            for &g in relation.initial_products(m) {
+                print!(
+                    "{}, {}\t",
+                    relation.retailer_label(m),
+                    relation.product_label(g)
+                );
                 for y in relation.all_products() {
                     print!("{}\t", self.eA_mgy[m][g][y]);
                 }
-                println!("");
+                println!();
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_u_mgy(&self, relation: &Relation) {
+        println!("u_mgy");
+        show_header(relation.all_products().map(|y| relation.product_label(y)));
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
-                print!("u{}{}y | ", m.id, g.id);
+            for &g in relation.initial_products(m) {
+                print!(
+                    "{}, {}\t",
+                    relation.retailer_label(m),
+                    relation.product_label(g)
+                );
                 for y in relation.all_products() {
                     print!("{}\t", self.u_mgy[m][g][y]);
                 }
-                println!("");
+                println!();
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_pw_g0(&self, relation: &Relation) {
         println!("pw_g0");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
+        print!("\t");
         for g in relation.all_products() {
             print!("{}\t", self.pw_g0[g]);
         }
-        println!("");
+        println!();
     }
 
-    #[allow(dead_code)]
     pub fn show_PCP_g(&self, relation: &Relation) {
         println!("PCP_g");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
+        print!("\t");
         for g in relation.all_products() {
             print!("{}\t", self.PCP_g[g]);
         }
-        println!("");
+        println!();
     }
 
-    #[allow(dead_code)]
     pub fn show_ORM_s(&self, relation: &Relation) {
         println!("ORM_s");
+        show_header(relation.all_suppliers().map(|s| relation.supplier_label(s)));
+        print!("\t");
         for s in relation.all_suppliers() {
             print!("{}\t", self.ORM_s[s]);
         }
-        println!("");
+        println!();
     }
 
-    #[allow(dead_code)]
     pub fn show_HRM_l(&self, relation: &Relation) {
         println!("HRM_l");
+        show_header(relation.all_materials().map(|l| relation.material_label(l)));
+        print!("\t");
         for l in relation.all_materials() {
             print!("{}\t", self.HRM_l[l]);
         }
-        println!("");
+        println!();
     }
 
-    #[allow(dead_code)]
     pub fn show_FCA_k(&self, relation: &Relation) {
         println!("FCA_k");
         for k in relation.all_alternatives() {
-            let j = relation.module(k);
-            println!("{}: {}", j.id, self.FCA_k[k]);
+            if let Ok(j) = relation.module(k) {
+                let (alternative, module) =
+                    (relation.alternative_label(k), relation.module_label(j));
+                println!("{} ({}): {}", alternative, module, self.FCA_k[k]);
+            }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_PCA_k(&self, relation: &Relation) {
         println!("PCA_k");
         for k in relation.all_alternatives() {
-            let j = relation.module(k);
-            println!("{}: {}", j.id, self.PCA_k[k]);
+            if let Ok(j) = relation.module(k) {
+                let (alternative, module) =
+                    (relation.alternative_label(k), relation.module_label(j));
+                println!("{} ({}): {}", alternative, module, self.PCA_k[k]);
+            }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_PCR_sl(&self, relation: &Relation) {
         println!("PCR_sl");
+        show_header(relation.all_materials().map(|l| relation.material_label(l)));
         for s in relation.all_suppliers() {
+            print!("{}\t", relation.supplier_label(s));
             for l in relation.all_materials() {
                 print!("{}\t", self.PCR_sl[s][l]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_TVR_m(&self, relation: &Relation) {
         println!("TVR_m");
+        show_header(
+            relation
+                .initial_retailers()
+                .map(|m| relation.retailer_label(m)),
+        );
+        print!("\t");
         for m in relation.initial_retailers() {
             print!("{}\t", self.TVR_m[m]);
         }
-        println!("");
+        println!();
     }
 
-    #[allow(dead_code)]
     pub fn show_Ta_m(&self, relation: &Relation) {
         println!("Ta_m");
+        show_header(
+            relation
+                .initial_retailers()
+                .map(|m| relation.retailer_label(m)),
+        );
+        print!("\t");
         for m in relation.initial_retailers() {
             print!("{}\t", self.Ta_m[m]);
         }
-        println!("");
+        println!();
     }
 
-    #[allow(dead_code)]
     pub fn show_delta_gk(&self, relation: &Relation) {
         println!("delta_gk");
+        show_header(
+            relation
+                .all_alternatives()
+                .map(|k| relation.alternative_label(k)),
+        );
         for g in relation.all_products() {
+            print!("{}\t", relation.product_label(g));
             for k in relation.all_alternatives() {
                 print!("{}\t", self.delta_gk[g][k]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_sigma_kl(&self, relation: &Relation) {
         println!("sigma_kl");
+        show_header(relation.all_materials().map(|l| relation.material_label(l)));
         for k in relation.all_alternatives() {
+            print!("{}\t", relation.alternative_label(k));
             for l in relation.all_materials() {
                 print!("{}\t", self.sigma_kl[k][l]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_FCM_j(&self, relation: &Relation) {
         println!("FCM_j");
+        show_header(relation.all_modules().map(|j| relation.module_label(j)));
+        print!("\t");
         for j in relation.all_modules() {
             print!("{}\t", self.FCM_j[j]);
         }
-        println!("");
+        println!();
     }
 
-    #[allow(dead_code)]
     pub fn show_HP_g(&self, relation: &Relation) {
         println!("HP_g");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
+        print!("\t");
         for g in relation.all_products() {
             print!("{}\t", self.HP_g[g]);
         }
-        println!("");
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation() -> Relation {
+        let mut relation = Relation::new();
+        relation.supplier_material_pairs(&[(0, 0), (1, 1)]).unwrap();
+        relation
+            .retailer_product_pairs(&[(0, 0), (0, 1), (1, 1)])
+            .unwrap();
+        relation
+            .alternative_module_pairs(&[(0, 0), (1, 0)])
+            .unwrap();
+        relation
+    }
+
+    #[test]
+    fn test_module_lookup() {
+        let mut relation = relation();
+        assert_eq!(
+            relation.module(Alternative { id: 1 }).unwrap(),
+            Module { id: 0 }
+        );
+
+        relation
+            .alternative_module_pairs(&[(0, 0), (2, 0)])
+            .unwrap();
+        match relation.module(Alternative { id: 1 }) {
+            Err(Error::UnknownEntity {
+                entity: "alternative",
+                id: 1,
+            }) => {}
+            _ => panic!("expected an unknown alternative"),
+        }
+    }
+
+    #[test]
+    fn test_input_errors() {
+        let relation = relation();
+        let mut constant = Constant::new(&relation);
+
+        constant
+            .input_K_mg(&relation, &[&[1.0, 2.0], &[0.0, 3.0]])
+            .unwrap();
+        assert_eq!(constant.K_mg[Retailer { id: 1 }][Product { id: 1 }], 3.0);
+
+        match constant.input_K_mg(&relation, &[&[1.0, 2.0]]) {
+            Err(Error::Dimension {
+                field: "K_mg",
+                expected: 2,
+                found: 1,
+            }) => {}
+            _ => panic!("expected a dimension error on K_mg"),
+        }
+
+        match constant.input_pw_g0(&relation, &[1.0, f64::INFINITY]) {
+            Err(Error::NonFinite { field: "pw_g0", .. }) => {}
+            _ => panic!("expected a non-finite error on pw_g0"),
+        }
+
+        let data: &[&[f64]] = &[&[0.0, 0.0], &[0.0, 0.0]];
+        match constant.input_v_mgxy(&relation, 2, 0, data) {
+            Err(Error::UnknownEntity {
+                entity: "retailer",
+                id: 2,
+            }) => {}
+            _ => panic!("expected an unknown retailer"),
+        }
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let mut relation = relation();
+        relation
+            .retailer_product_pairs(&[(1, 1), (0, 1), (0, 0)])
+            .unwrap();
+
+        // Neighbours keep the order of the pairs.
+        assert_eq!(
+            relation.initial_products(Retailer { id: 0 }),
+            &[Product { id: 1 }, Product { id: 0 }]
+        );
+        assert_eq!(
+            relation.retailers(Product { id: 1 }),
+            &[Retailer { id: 1 }, Retailer { id: 0 }]
+        );
+        assert_eq!(
+            relation.alternatives_of_module(Module { id: 0 }),
+            &[Alternative { id: 0 }, Alternative { id: 1 }]
+        );
+        assert!(relation.materials(Supplier { id: 5 }).is_empty());
+
+        relation.alternative_product_pairs(&[(1, 0), (1, 1), (0, 0)]);
+        assert_eq!(
+            relation.alternatives_of_product(Product { id: 0 }),
+            &[Alternative { id: 1 }, Alternative { id: 0 }]
+        );
+        let mut decision = mrgame::Decision::new(&relation);
+        decision.product[Product { id: 0 }] = false;
+        assert_eq!(
+            relation
+                .products_for_alternative(Alternative { id: 1 }, &decision)
+                .collect::<Vec<_>>(),
+            vec![Product { id: 1 }]
+        );
+        assert_eq!(
+            relation
+                .products_for_alternative(Alternative { id: 0 }, &decision)
+                .count(),
+            0
+        );
+
+        relation.material_alternative_pairs(&[(1, 0), (0, 1), (1, 1)]);
+        assert_eq!(
+            relation.alternatives_of_material(Material { id: 1 }),
+            &[Alternative { id: 0 }, Alternative { id: 1 }]
+        );
+        assert_eq!(
+            relation.materials_of_alternative(Alternative { id: 1 }),
+            &[Material { id: 0 }, Material { id: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_entity_map() {
+        let relation = relation();
+
+        // Two alternatives of one module.
+        assert_eq!(ModuleMap::new(&relation, 0.0).len(), 1);
+        assert_eq!(AlternativeMap::new(&relation, 0.0).len(), 2);
+
+        let A_g = ProductMap::from_fn(&relation, |g| g.id as f64 + 1.0);
+        let twice = A_g.map(|_, A| 2.0 * A);
+        let sum = A_g.zip_with(&twice, |_, a, b| a + b);
+        assert_eq!(sum.values().cloned().collect::<Vec<f64>>(), vec![3.0, 6.0]);
+        assert_eq!(sum.iter().map(|(g, _)| g).last(), Some(Product { id: 1 }));
+
+        let array = sum.to_array();
+        assert_eq!(ProductMap::from_array(&relation, &array).unwrap(), sum);
+
+        let p_mg = RetailerMap::from_fn(&relation, |m| {
+            ProductMap::from_fn(&relation, |g| (10 * m.id + g.id) as f64)
+        });
+        let array = p_mg.to_array2();
+        assert_eq!(array[(1, 0)], 10.0);
+        assert_eq!(RetailerMap::from_array2(&relation, &array).unwrap(), p_mg);
+        match ModuleMap::<f64>::from_array(&relation, &array.row(0).to_owned()) {
+            Err(Error::Dimension {
+                expected: 1,
+                found: 2,
+                ..
+            }) => {}
+            _ => panic!("expected a dimension error"),
+        }
+    }
+
+    #[test]
+    fn test_cross_map() {
+        let relation = relation();
+        let (m, g, x, y) = (
+            Retailer { id: 1 },
+            Product { id: 0 },
+            Retailer { id: 0 },
+            Product { id: 1 },
+        );
+
+        let mut beta_mgxy = CrossMap::new(&relation, 1.0);
+        assert_eq!(beta_mgxy.array().dim(), (2, 2, 2, 2));
+        beta_mgxy[(m, g, x, y)] = 3.0;
+        assert_eq!(beta_mgxy.array()[(1, 0, 0, 1)], 3.0);
+
+        let view = beta_mgxy.view(m, g);
+        assert_eq!(view[(x.id, y.id)], 3.0);
+        assert_eq!(view.sum(), 6.0);
     }
 }
@@ -1,4 +1,4 @@
-use crate::relation::{ProductMap, Relation, RetailerMap};
+use crate::relation::{relative_change, show_header, ProductMap, Relation, RetailerMap};
 
 #[derive(Clone)]
 pub struct Parameter {
@@ -6,6 +6,8 @@ pub struct Parameter {
     pub a_mg: RetailerMap<ProductMap<f64>>,
 }
 
+/// The retailers' game: prices and advertising budgets per retailer and
+/// product.
 #[derive(Clone)]
 pub struct RRGame {
     pub parameter: Parameter,
@@ -19,43 +21,57 @@ impl Parameter {
         }
     }
 
-    #[allow(dead_code)]
+    /// Largest `relative_change` of `p_mg` and `a_mg` over the products of
+    /// each retailer.
+    pub fn max_change(&self, relation: &Relation, other: &Parameter) -> f64 {
+        let mut result: f64 = 0.0;
+        for m in relation.initial_retailers() {
+            for &g in relation.initial_products(m) {
+                result = result.max(relative_change(self.p_mg[m][g], other.p_mg[m][g]));
+                result = result.max(relative_change(self.a_mg[m][g], other.a_mg[m][g]));
+            }
+        }
+
+        result
+    }
+
     pub fn input_p_mg(&mut self, relation: &Relation, data: &[&[f64]]) {
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
+            for &g in relation.initial_products(m) {
                 self.p_mg[m][g] = data[m.id][g.id];
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn input_a_mg(&mut self, relation: &Relation, data: &[&[f64]]) {
         for m in relation.initial_retailers() {
-            for g in relation.initial_products(m) {
+            for &g in relation.initial_products(m) {
                 self.a_mg[m][g] = data[m.id][g.id];
             }
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_p_mg(&self, relation: &Relation) {
         println!("p_mg");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
         for m in relation.initial_retailers() {
+            print!("{}\t", relation.retailer_label(m));
             for g in relation.all_products() {
                 print!("{}\t", self.p_mg[m][g]);
             }
-            println!("");
+            println!();
         }
     }
 
-    #[allow(dead_code)]
     pub fn show_a_mg(&self, relation: &Relation) {
         println!("a_mg");
+        show_header(relation.all_products().map(|g| relation.product_label(g)));
         for m in relation.initial_retailers() {
+            print!("{}\t", relation.retailer_label(m));
             for g in relation.all_products() {
                 print!("{}\t", self.a_mg[m][g]);
             }
-            println!("");
+            println!();
         }
     }
 }
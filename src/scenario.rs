@@ -0,0 +1,793 @@
+use crate::bounds::Bounds;
+use crate::error::{check_len, finite, Error, Result};
+use crate::mrgame::MRGame;
+use crate::relation::{Constant, EntityInfo, Relation};
+use crate::rrgame::RRGame;
+use crate::validation;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+type Vec2<T> = Vec<Vec<T>>;
+type Vec3<T> = Vec<Vec<Vec<T>>>;
+type Vec4<T> = Vec<Vec<Vec<Vec<T>>>>;
+
+#[derive(Serialize, Deserialize)]
+pub struct RelationData {
+    pub supplier_materials: Vec<(usize, usize)>,
+    pub retailer_products: Vec<(usize, usize)>,
+    pub alternative_modules: Vec<(usize, usize)>,
+    pub material_alternatives: Vec<(usize, usize)>,
+    pub alternative_products: Vec<(usize, usize)>,
+
+    /// Names and metadata by entity id. An empty list leaves the entities
+    /// unnamed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suppliers: Vec<EntityInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<EntityInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub products: Vec<EntityInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retailers: Vec<EntityInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<EntityInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<EntityInfo>,
+}
+
+/// Every field is a dense array indexed by entity ids, e.g. `K_mg[m][g]`.
+/// A missing field keeps the default of `Constant::new`.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ConstantData {
+    pub v_mgxy: Option<Vec4<f64>>,
+    pub ea_mgxy: Option<Vec4<f64>>,
+    pub beta_mgxy: Option<Vec4<f64>>,
+    pub ep_mgxy: Option<Vec4<f64>>,
+    pub K_mg: Option<Vec2<f64>>,
+    pub zeta_mg: Option<Vec2<f64>>,
+    pub HR_mg: Option<Vec2<f64>>,
+    pub TP_mg: Option<Vec2<f64>>,
+    pub eA_mgy: Option<Vec3<f64>>,
+    pub u_mgy: Option<Vec3<f64>>,
+    pub pw_g0: Option<Vec<f64>>,
+    pub PCP_g: Option<Vec<f64>>,
+    pub ORM_s: Option<Vec<f64>>,
+    pub HRM_l: Option<Vec<f64>>,
+    pub FCA_k: Option<Vec<f64>>,
+    pub PCA_k: Option<Vec<f64>>,
+    pub PCR_sl: Option<Vec2<f64>>,
+
+    pub V_g: Option<Vec<f64>>,
+    pub w_0: Option<f64>,
+    pub w_m: Option<Vec<f64>>,
+    pub TVR_m: Option<Vec<f64>>,
+    pub Ta_m: Option<Vec<f64>>,
+    pub OP_m: Option<Vec<f64>>,
+
+    pub delta_gk: Option<Vec2<usize>>,
+    pub sigma_kl: Option<Vec2<usize>>,
+    pub FCM_j: Option<Vec<f64>>,
+    pub HP_g: Option<Vec<f64>>,
+    pub VRM_l: Option<Vec<f64>>,
+    pub TVP: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MRGameData {
+    pub product: Option<Vec<bool>>,
+    pub alternative: Option<Vec<bool>>,
+    pub A_g: Option<Vec<f64>>,
+    pub c_m: Option<Vec<f64>>,
+    pub rho_g: Option<Vec<f64>>,
+    pub crm_s: Option<Vec<f64>>,
+    pub drm_sl: Option<Vec2<f64>>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RRGameData {
+    pub p_mg: Option<Vec2<f64>>,
+    pub a_mg: Option<Vec2<f64>>,
+}
+
+/// Lower and upper bounds of one variable, shaped like the variable. A
+/// missing side keeps the default of `Bounds::new`; `inf` and `-inf` lift it.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct BoundData<T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lower: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upper: Option<T>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct BoundsData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p_mg: Option<BoundData<Vec2<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub a_mg: Option<BoundData<Vec2<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub A_g: Option<BoundData<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rho_g: Option<BoundData<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c_m: Option<BoundData<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crm_s: Option<BoundData<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drm_sl: Option<BoundData<Vec2<f64>>>,
+}
+
+/// A scenario file: the pair lists, constants and starting parameters, as
+/// read from TOML. `build` turns it into the model types.
+#[derive(Serialize, Deserialize)]
+pub struct Scenario {
+    pub relation: RelationData,
+    #[serde(default)]
+    pub constant: ConstantData,
+    #[serde(default)]
+    pub mrgame: MRGameData,
+    #[serde(default)]
+    pub rrgame: RRGameData,
+    #[serde(default)]
+    pub bounds: BoundsData,
+}
+
+trait Dimension {
+    fn check(&self, field: &'static str, dims: &[usize]) -> Result<()>;
+}
+
+// Infinities mark entries of pairs the relation does not declare, e.g.
+// `PCR_sl` of a material a supplier does not sell, and are left to
+// `validation`. NaN is never meaningful.
+impl Dimension for f64 {
+    fn check(&self, field: &'static str, _dims: &[usize]) -> Result<()> {
+        if self.is_nan() {
+            return Err(Error::NonFinite {
+                field,
+                value: *self,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Dimension for usize {
+    fn check(&self, _field: &'static str, _dims: &[usize]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Dimension for bool {
+    fn check(&self, _field: &'static str, _dims: &[usize]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: Dimension> Dimension for Vec<T> {
+    fn check(&self, field: &'static str, dims: &[usize]) -> Result<()> {
+        check_len(field, self.len(), dims[0])?;
+        for e in self.iter() {
+            e.check(field, &dims[1..])?;
+        }
+        Ok(())
+    }
+}
+
+fn checked<'a, T: Dimension>(
+    data: &'a Option<T>,
+    field: &'static str,
+    dims: &[usize],
+) -> Result<Option<&'a T>> {
+    match data {
+        Some(data) => {
+            data.check(field, dims)?;
+            Ok(Some(data))
+        }
+        None => Ok(None),
+    }
+}
+
+fn checked_bounds<'a, T: Dimension>(
+    data: &'a Option<BoundData<T>>,
+    field: &'static str,
+    dims: &[usize],
+) -> Result<(Option<&'a T>, Option<&'a T>)> {
+    match data {
+        Some(data) => Ok((
+            checked(&data.lower, field, dims)?,
+            checked(&data.upper, field, dims)?,
+        )),
+        None => Ok((None, None)),
+    }
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(Error::Io)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(Error::Parse)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let text = toml::to_string_pretty(self).map_err(Error::Serialize)?;
+        fs::write(path, text).map_err(Error::Io)
+    }
+
+    pub fn relation(&self) -> Result<Relation> {
+        let data = &self.relation;
+        let mut relation = Relation::new();
+        relation.supplier_material_pairs(&data.supplier_materials)?;
+        relation.retailer_product_pairs(&data.retailer_products)?;
+        relation.alternative_module_pairs(&data.alternative_modules)?;
+        relation.material_alternative_pairs(&data.material_alternatives);
+        relation.alternative_product_pairs(&data.alternative_products);
+
+        if !data.suppliers.is_empty() {
+            check_len(
+                "suppliers",
+                data.suppliers.len(),
+                relation.all_suppliers().count(),
+            )?;
+            for s in relation.all_suppliers() {
+                relation.supplier_info[s] = data.suppliers[s.id].clone();
+            }
+        }
+
+        if !data.materials.is_empty() {
+            check_len(
+                "materials",
+                data.materials.len(),
+                relation.all_materials().count(),
+            )?;
+            for l in relation.all_materials() {
+                relation.material_info[l] = data.materials[l.id].clone();
+            }
+        }
+
+        if !data.products.is_empty() {
+            check_len(
+                "products",
+                data.products.len(),
+                relation.all_products().count(),
+            )?;
+            for g in relation.all_products() {
+                relation.product_info[g] = data.products[g.id].clone();
+            }
+        }
+
+        if !data.retailers.is_empty() {
+            check_len(
+                "retailers",
+                data.retailers.len(),
+                relation.initial_retailers().count(),
+            )?;
+            for m in relation.initial_retailers() {
+                relation.retailer_info[m] = data.retailers[m.id].clone();
+            }
+        }
+
+        if !data.alternatives.is_empty() {
+            check_len(
+                "alternatives",
+                data.alternatives.len(),
+                relation.all_alternatives().count(),
+            )?;
+            for k in relation.all_alternatives() {
+                relation.alternative_info[k] = data.alternatives[k.id].clone();
+            }
+        }
+
+        if !data.modules.is_empty() {
+            check_len(
+                "modules",
+                data.modules.len(),
+                relation.all_modules().count(),
+            )?;
+            for j in relation.all_modules() {
+                relation.module_info[j] = data.modules[j.id].clone();
+            }
+        }
+
+        Ok(relation)
+    }
+
+    pub fn build(&self) -> Result<(Relation, Constant, MRGame, RRGame)> {
+        let relation = self.relation()?;
+        let constant = self.constant(&relation)?;
+        let mrgame = self.mrgame(&relation)?;
+        let rrgame = self.rrgame(&relation)?;
+        Ok((relation, constant, mrgame, rrgame))
+    }
+
+    /// `build`, then `validation::validate`, whose errors are returned as
+    /// `Error::Invalid`.
+    pub fn build_validated(&self) -> Result<(Relation, Constant, MRGame, RRGame)> {
+        let (relation, constant, mrgame, rrgame) = self.build()?;
+        validation::validate(&relation, &constant)?;
+        Ok((relation, constant, mrgame, rrgame))
+    }
+
+    fn constant(&self, relation: &Relation) -> Result<Constant> {
+        let data = &self.constant;
+        let mut constant = Constant::new(relation);
+
+        let nm = relation.initial_retailers().count();
+        let ng = relation.all_products().count();
+        let ns = relation.all_suppliers().count();
+        let nl = relation.all_materials().count();
+        let nk = relation.all_alternatives().count();
+        let nj = relation.all_modules().count();
+
+        let mgxy = [nm, ng, nm, ng];
+        if let Some(v) = checked(&data.v_mgxy, "v_mgxy", &mgxy)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    for x in relation.initial_retailers() {
+                        for y in relation.all_products() {
+                            constant.v_mgxy[(m, g, x, y)] = v[m.id][g.id][x.id][y.id];
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.ea_mgxy, "ea_mgxy", &mgxy)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    for x in relation.initial_retailers() {
+                        for y in relation.all_products() {
+                            constant.ea_mgxy[(m, g, x, y)] = v[m.id][g.id][x.id][y.id];
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.beta_mgxy, "beta_mgxy", &mgxy)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    for x in relation.initial_retailers() {
+                        for y in relation.all_products() {
+                            constant.beta_mgxy[(m, g, x, y)] = v[m.id][g.id][x.id][y.id];
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.ep_mgxy, "ep_mgxy", &mgxy)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    for x in relation.initial_retailers() {
+                        for y in relation.all_products() {
+                            constant.ep_mgxy[(m, g, x, y)] = v[m.id][g.id][x.id][y.id];
+                        }
+                    }
+                }
+            }
+        }
+
+        let mg = [nm, ng];
+        if let Some(v) = checked(&data.K_mg, "K_mg", &mg)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    constant.K_mg[m][g] = v[m.id][g.id];
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.zeta_mg, "zeta_mg", &mg)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    constant.zeta_mg[m][g] = v[m.id][g.id];
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.HR_mg, "HR_mg", &mg)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    constant.HR_mg[m][g] = v[m.id][g.id];
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.TP_mg, "TP_mg", &mg)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    constant.TP_mg[m][g] = v[m.id][g.id];
+                }
+            }
+        }
+
+        let mgy = [nm, ng, ng];
+        if let Some(v) = checked(&data.eA_mgy, "eA_mgy", &mgy)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    for y in relation.all_products() {
+                        constant.eA_mgy[m][g][y] = v[m.id][g.id][y.id];
+                    }
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.u_mgy, "u_mgy", &mgy)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    for y in relation.all_products() {
+                        constant.u_mgy[m][g][y] = v[m.id][g.id][y.id];
+                    }
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.pw_g0, "pw_g0", &[ng])? {
+            constant.input_pw_g0(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.PCP_g, "PCP_g", &[ng])? {
+            constant.input_PCP_g(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.ORM_s, "ORM_s", &[ns])? {
+            constant.input_ORM_s(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.HRM_l, "HRM_l", &[nl])? {
+            constant.input_HRM_l(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.FCA_k, "FCA_k", &[nk])? {
+            constant.input_FCA_k(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.PCA_k, "PCA_k", &[nk])? {
+            constant.input_PCA_k(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.PCR_sl, "PCR_sl", &[ns, nl])? {
+            for s in relation.all_suppliers() {
+                for l in relation.all_materials() {
+                    constant.PCR_sl[s][l] = v[s.id][l.id];
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.V_g, "V_g", &[ng])? {
+            for g in relation.all_products() {
+                constant.V_g[g] = v[g.id];
+            }
+        }
+
+        if let Some(w_0) = data.w_0 {
+            constant.w_0 = finite("w_0", w_0)?;
+        }
+
+        if let Some(v) = checked(&data.w_m, "w_m", &[nm])? {
+            for m in relation.initial_retailers() {
+                constant.w_m[m] = v[m.id];
+            }
+        }
+
+        if let Some(v) = checked(&data.TVR_m, "TVR_m", &[nm])? {
+            constant.input_TVR_m(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.Ta_m, "Ta_m", &[nm])? {
+            constant.input_Ta_m(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.OP_m, "OP_m", &[nm])? {
+            constant.input_OP_m(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.delta_gk, "delta_gk", &[ng, nk])? {
+            for g in relation.all_products() {
+                for k in relation.all_alternatives() {
+                    constant.delta_gk[g][k] = v[g.id][k.id];
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.sigma_kl, "sigma_kl", &[nk, nl])? {
+            for k in relation.all_alternatives() {
+                for l in relation.all_materials() {
+                    constant.sigma_kl[k][l] = v[k.id][l.id];
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.FCM_j, "FCM_j", &[nj])? {
+            constant.input_FCM_j(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.HP_g, "HP_g", &[ng])? {
+            constant.input_HP_g(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.VRM_l, "VRM_l", &[nl])? {
+            for l in relation.all_materials() {
+                constant.VRM_l[l] = v[l.id];
+            }
+        }
+
+        if let Some(TVP) = data.TVP {
+            constant.TVP = finite("TVP", TVP)?;
+        }
+
+        constant.bounds = self.bounds(relation)?;
+
+        Ok(constant)
+    }
+
+    fn bounds(&self, relation: &Relation) -> Result<Bounds> {
+        let data = &self.bounds;
+        let mut bounds = Bounds::new(relation);
+
+        let nm = relation.initial_retailers().count();
+        let ng = relation.all_products().count();
+        let ns = relation.all_suppliers().count();
+        let nl = relation.all_materials().count();
+
+        let (lower, upper) = checked_bounds(&data.p_mg, "bounds.p_mg", &[nm, ng])?;
+        for m in relation.initial_retailers() {
+            for g in relation.all_products() {
+                let bound = &mut bounds.p_mg[m][g];
+                bound.lower = lower.map_or(bound.lower, |v| v[m.id][g.id]);
+                bound.upper = upper.map_or(bound.upper, |v| v[m.id][g.id]);
+            }
+        }
+
+        let (lower, upper) = checked_bounds(&data.a_mg, "bounds.a_mg", &[nm, ng])?;
+        for m in relation.initial_retailers() {
+            for g in relation.all_products() {
+                let bound = &mut bounds.a_mg[m][g];
+                bound.lower = lower.map_or(bound.lower, |v| v[m.id][g.id]);
+                bound.upper = upper.map_or(bound.upper, |v| v[m.id][g.id]);
+            }
+        }
+
+        let (lower, upper) = checked_bounds(&data.A_g, "bounds.A_g", &[ng])?;
+        for g in relation.all_products() {
+            let bound = &mut bounds.A_g[g];
+            bound.lower = lower.map_or(bound.lower, |v| v[g.id]);
+            bound.upper = upper.map_or(bound.upper, |v| v[g.id]);
+        }
+
+        let (lower, upper) = checked_bounds(&data.rho_g, "bounds.rho_g", &[ng])?;
+        for g in relation.all_products() {
+            let bound = &mut bounds.rho_g[g];
+            bound.lower = lower.map_or(bound.lower, |v| v[g.id]);
+            bound.upper = upper.map_or(bound.upper, |v| v[g.id]);
+        }
+
+        let (lower, upper) = checked_bounds(&data.c_m, "bounds.c_m", &[nm])?;
+        for m in relation.initial_retailers() {
+            let bound = &mut bounds.c_m[m];
+            bound.lower = lower.map_or(bound.lower, |v| v[m.id]);
+            bound.upper = upper.map_or(bound.upper, |v| v[m.id]);
+        }
+
+        let (lower, upper) = checked_bounds(&data.crm_s, "bounds.crm_s", &[ns])?;
+        for s in relation.all_suppliers() {
+            let bound = &mut bounds.crm_s[s];
+            bound.lower = lower.map_or(bound.lower, |v| v[s.id]);
+            bound.upper = upper.map_or(bound.upper, |v| v[s.id]);
+        }
+
+        let (lower, upper) = checked_bounds(&data.drm_sl, "bounds.drm_sl", &[ns, nl])?;
+        for s in relation.all_suppliers() {
+            for l in relation.all_materials() {
+                let bound = &mut bounds.drm_sl[s][l];
+                bound.lower = lower.map_or(bound.lower, |v| v[s.id][l.id]);
+                bound.upper = upper.map_or(bound.upper, |v| v[s.id][l.id]);
+            }
+        }
+
+        Ok(bounds)
+    }
+
+    fn mrgame(&self, relation: &Relation) -> Result<MRGame> {
+        let data = &self.mrgame;
+        let mut mrgame = MRGame::new(relation);
+        let decision = &mut mrgame.decision;
+        let parameter = &mut mrgame.parameter;
+
+        let nm = relation.initial_retailers().count();
+        let ng = relation.all_products().count();
+        let ns = relation.all_suppliers().count();
+        let nl = relation.all_materials().count();
+        let nk = relation.all_alternatives().count();
+
+        if let Some(v) = checked(&data.product, "product", &[ng])? {
+            decision.input(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.alternative, "alternative", &[nk])? {
+            for k in relation.all_alternatives() {
+                decision.alternative[k] = v[k.id];
+            }
+        }
+
+        if let Some(v) = checked(&data.A_g, "A_g", &[ng])? {
+            for g in relation.all_products() {
+                parameter.A_g[g] = v[g.id];
+            }
+        }
+
+        if let Some(v) = checked(&data.c_m, "c_m", &[nm])? {
+            for m in relation.initial_retailers() {
+                parameter.c_m[m] = v[m.id];
+            }
+        }
+
+        if let Some(v) = checked(&data.rho_g, "rho_g", &[ng])? {
+            for g in relation.all_products() {
+                parameter.rho_g[g] = v[g.id];
+            }
+        }
+
+        if let Some(v) = checked(&data.crm_s, "crm_s", &[ns])? {
+            parameter.input_crm_s(relation, v)?;
+        }
+
+        if let Some(v) = checked(&data.drm_sl, "drm_sl", &[ns, nl])? {
+            for s in relation.all_suppliers() {
+                for l in relation.all_materials() {
+                    parameter.drm_sl[s][l] = v[s.id][l.id];
+                }
+            }
+        }
+
+        Ok(mrgame)
+    }
+
+    fn rrgame(&self, relation: &Relation) -> Result<RRGame> {
+        let data = &self.rrgame;
+        let mut rrgame = RRGame::new(relation);
+        let parameter = &mut rrgame.parameter;
+
+        let mg = [
+            relation.initial_retailers().count(),
+            relation.all_products().count(),
+        ];
+
+        if let Some(v) = checked(&data.p_mg, "p_mg", &mg)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    parameter.p_mg[m][g] = v[m.id][g.id];
+                }
+            }
+        }
+
+        if let Some(v) = checked(&data.a_mg, "a_mg", &mg)? {
+            for m in relation.initial_retailers() {
+                for g in relation.all_products() {
+                    parameter.a_mg[m][g] = v[m.id][g.id];
+                }
+            }
+        }
+
+        Ok(rrgame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computation;
+    use crate::relation::{Product, Retailer, Supplier};
+    use assert_approx_eq::assert_approx_eq;
+
+    const EXAMPLE: &str = include_str!("../scenarios/example.toml");
+
+    #[test]
+    fn test_example_scenario() {
+        let scenario = Scenario::parse(EXAMPLE).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+
+        assert_eq!(relation.initial_retailers().count(), 2);
+        assert_eq!(relation.all_products().count(), 4);
+        assert_eq!(relation.all_suppliers().count(), 3);
+        assert_eq!(relation.all_materials().count(), 3);
+        assert_eq!(relation.all_alternatives().count(), 6);
+        assert_eq!(relation.all_modules().count(), 2);
+
+        assert_eq!(relation.product_by_name("Server"), Some(Product { id: 3 }));
+        assert_eq!(relation.retailer_label(Retailer { id: 1 }), "Reseller");
+        assert_eq!(
+            relation.retailer_info[Retailer { id: 1 }].metadata["region"],
+            "EU"
+        );
+        assert_eq!(relation.supplier_label(Supplier { id: 2 }), "s2");
+        assert_eq!(relation.supplier_by_name("s2"), None);
+
+        let input = computation::Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        assert_approx_eq!(computation::NP0(&input), 46915.930331659736, 1e-6);
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        let text = EXAMPLE.replace("pw_g0 = [36.0, 34.0, 32.0, 39.0]", "pw_g0 = [36.0]");
+        let scenario = Scenario::parse(&text).unwrap();
+        match scenario.build() {
+            Err(Error::Dimension {
+                field: "pw_g0",
+                expected: 4,
+                found: 1,
+            }) => {}
+            _ => panic!("expected a dimension error on pw_g0"),
+        }
+    }
+
+    #[test]
+    fn test_empty_relation() {
+        let text = EXAMPLE.replace(
+            "supplier_materials = [[0, 0], [0, 1], [0, 2], [1, 0], [1, 2], [2, 0], [2, 1]]",
+            "supplier_materials = []",
+        );
+        let scenario = Scenario::parse(&text).unwrap();
+        match scenario.build() {
+            Err(Error::EmptyRelation {
+                pairs: "supplier_material_pairs",
+            }) => {}
+            _ => panic!("expected an empty relation error"),
+        }
+    }
+
+    #[test]
+    fn test_nan_constant() {
+        let text = EXAMPLE.replace(
+            "pw_g0 = [36.0, 34.0, 32.0, 39.0]",
+            "pw_g0 = [36.0, nan, 32.0, 39.0]",
+        );
+        let scenario = Scenario::parse(&text).unwrap();
+        match scenario.build() {
+            Err(Error::NonFinite { field: "pw_g0", .. }) => {}
+            _ => panic!("expected a non-finite error on pw_g0"),
+        }
+    }
+
+    #[test]
+    fn test_build_validated() {
+        assert!(Scenario::parse(EXAMPLE).unwrap().build_validated().is_ok());
+
+        let text = EXAMPLE.replace(
+            "alternative_products = [[0, 0], [3, 0],",
+            "alternative_products = [[0, 0], [3, 0], [0, 7],",
+        );
+        let scenario = Scenario::parse(&text).unwrap();
+        assert!(scenario.build().is_ok());
+        match scenario.build_validated() {
+            Err(Error::Invalid(errors)) => assert!(!errors.is_empty()),
+            _ => panic!("expected the validation errors"),
+        }
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        let scenario = Scenario::parse(EXAMPLE).unwrap();
+        let text = toml::to_string_pretty(&scenario).unwrap();
+        let (relation, _, _, _) = Scenario::parse(&text).unwrap().build().unwrap();
+
+        assert_eq!(relation.product_label(Product { id: 0 }), "Suite");
+        assert_eq!(
+            relation.retailer_info[Retailer { id: 1 }].metadata["region"],
+            "EU"
+        );
+    }
+}
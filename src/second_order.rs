@@ -0,0 +1,224 @@
+//! Second-order conditions at the stationary points the solvers find. A point
+//! that solves the stationarity conditions of a maximisation is a strict local
+//! maximum when the Hessian of the Lagrangian is negative definite on the null
+//! space of the active constraints and bounds, the reduced Hessian.
+//!
+//! The check only asks for a negative semidefinite reduced Hessian, so a weak
+//! or degenerate maximum, with a zero eigenvalue, also counts as
+//! `Curvature::Maximum`. NP0 is linear in drm_sl, which leaves such flat
+//! directions in the manufacturer's problem.
+
+use crate::bounds::BoundSide;
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{EigValsh, UPLO};
+use std::fmt;
+
+/// Eigenvalues within this share of the largest Hessian entry count as zero.
+const EPSILON: f64 = 0.00000001;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Curvature {
+    /// The reduced Hessian is negative semidefinite, or there is no direction
+    /// left to move along.
+    Maximum,
+    Saddle,
+    /// The reduced Hessian is positive semidefinite and not zero.
+    Minimum,
+    /// The eigenvalues of the reduced Hessian could not be computed, e.g.
+    /// as it holds NaN.
+    Undetermined,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecondOrder {
+    pub curvature: Curvature,
+    /// Eigenvalues of the reduced Hessian in ascending order.
+    pub eigenvalues: Vec<f64>,
+}
+
+fn norm(x: &Array1<f64>) -> f64 {
+    x.dot(x).sqrt()
+}
+
+/// `v` with its components along the orthonormal `basis` removed. Projects
+/// twice, which keeps the result orthogonal in floating point.
+fn orthogonalize(v: &Array1<f64>, basis: &[Array1<f64>]) -> Array1<f64> {
+    let mut result = v.clone();
+    for _ in 0..2 {
+        for q in basis.iter() {
+            let dot = q.dot(&result);
+            result.scaled_add(-dot, q);
+        }
+    }
+    result
+}
+
+/// An orthonormal basis of the vectors orthogonal to every row of `rows`, each
+/// of length `n`.
+pub fn null_space(rows: &[Array1<f64>], n: usize) -> Vec<Array1<f64>> {
+    let mut range: Vec<Array1<f64>> = Vec::new();
+    for row in rows.iter() {
+        let v = orthogonalize(row, &range);
+        let v_norm = norm(&v);
+        if v_norm > EPSILON * norm(row) {
+            range.push(v / v_norm);
+        }
+    }
+
+    // Complete the basis with the unit vectors that stick out the most.
+    let mut result: Vec<Array1<f64>> = Vec::new();
+    while range.len() < n {
+        let mut best = Array1::zeros(n);
+        let mut best_norm = 0.0;
+        for i in 0..n {
+            let mut e = Array1::zeros(n);
+            e[i] = 1.0;
+            let v = orthogonalize(&e, &range);
+            let v_norm = norm(&v);
+            if v_norm > best_norm {
+                best = v;
+                best_norm = v_norm;
+            }
+        }
+        let q = best / best_norm;
+        range.push(q.clone());
+        result.push(q);
+    }
+    result
+}
+
+/// Classifies a stationary point of a maximisation from the Jacobian of its
+/// stationarity system, laid out as for `newton::bounded_newton_solve`: rows
+/// and columns `0..held.len()` are `∂L/∂x_i` by `x_j`, i.e. the Hessian of
+/// the Lagrangian, and each remaining row is an active constraint, whose
+/// first `held.len()` entries are its gradient. A variable held at a bound
+/// adds its unit vector to the active constraints.
+///
+/// The Hessian is symmetrised first, as parts of it may be differentiated
+/// numerically.
+pub fn second_order(jacobian: &Array2<f64>, held: &[Option<BoundSide>]) -> SecondOrder {
+    let n = held.len();
+
+    let mut rows = Vec::new();
+    for row in n..jacobian.nrows() {
+        let mut gradient = Array1::zeros(n);
+        for col in 0..n {
+            gradient[col] = jacobian[(row, col)];
+        }
+        rows.push(gradient);
+    }
+    for (i, side) in held.iter().enumerate() {
+        if side.is_some() {
+            let mut e = Array1::zeros(n);
+            e[i] = 1.0;
+            rows.push(e);
+        }
+    }
+
+    let mut hessian = Array2::zeros((n, n));
+    for i in 0..n {
+        for j in 0..n {
+            hessian[(i, j)] = 0.5 * (jacobian[(i, j)] + jacobian[(j, i)]);
+        }
+    }
+
+    let basis = null_space(&rows, n);
+    let d = basis.len();
+    let mut reduced = Array2::zeros((d, d));
+    for (i, zi) in basis.iter().enumerate() {
+        let h_zi = hessian.dot(zi);
+        for (j, zj) in basis.iter().enumerate() {
+            reduced[(i, j)] = zj.dot(&h_zi);
+        }
+    }
+
+    let eigenvalues = if d == 0 {
+        Some(Vec::new())
+    } else {
+        reduced.eigvalsh(UPLO::Lower).ok().map(|x| x.to_vec())
+    };
+    let eigenvalues = match eigenvalues {
+        Some(eigenvalues) if !eigenvalues.iter().any(|v| v.is_nan()) => eigenvalues,
+        _ => {
+            return SecondOrder {
+                curvature: Curvature::Undetermined,
+                eigenvalues: Vec::new(),
+            }
+        }
+    };
+    let scale = hessian.iter().fold(0.0, |acc: f64, v| acc.max(v.abs()));
+    let positive = eigenvalues.iter().any(|v| *v > EPSILON * scale);
+    let negative = eigenvalues.iter().any(|v| *v < -EPSILON * scale);
+    let curvature = match (positive, negative) {
+        (true, true) => Curvature::Saddle,
+        (true, false) => Curvature::Minimum,
+        (false, _) => Curvature::Maximum,
+    };
+
+    SecondOrder {
+        curvature,
+        eigenvalues,
+    }
+}
+
+impl fmt::Display for Curvature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Curvature::Maximum => write!(f, "local max"),
+            Curvature::Saddle => write!(f, "saddle"),
+            Curvature::Minimum => write!(f, "local min"),
+            Curvature::Undetermined => write!(f, "undetermined"),
+        }
+    }
+}
+
+impl fmt::Display for SecondOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.curvature)?;
+        if self.curvature != Curvature::Maximum {
+            write!(f, " (reduced Hessian eigenvalues {:?})", self.eigenvalues)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use ndarray::arr2;
+
+    #[test]
+    fn test_second_order() {
+        // L = x^2 - y^2, unconstrained: a saddle.
+        let free = arr2(&[[2.0, 0.0], [0.0, -2.0]]);
+        let result = second_order(&free, &[None, None]);
+        assert_eq!(result.curvature, Curvature::Saddle);
+        assert_eq!(result.eigenvalues, vec![-2.0, 2.0]);
+
+        // With y fixed by a constraint only x can move, which increases L.
+        let fixed_y = arr2(&[[2.0, 0.0, 0.0], [0.0, -2.0, -1.0], [0.0, 1.0, 0.0]]);
+        let result = second_order(&fixed_y, &[None, None]);
+        assert_eq!(result.curvature, Curvature::Minimum);
+
+        // With x held at a bound only y can move, which decreases L.
+        let result = second_order(&free, &[Some(BoundSide::Lower), None]);
+        assert_eq!(result.curvature, Curvature::Maximum);
+        assert_eq!(result.eigenvalues, vec![-2.0]);
+
+        // Along x + y = c the curvature is (2 - 2) / 2 = 0, a weak maximum.
+        let diagonal = arr2(&[[2.0, 0.0, -1.0], [0.0, -2.0, -1.0], [1.0, 1.0, 0.0]]);
+        let result = second_order(&diagonal, &[None, None]);
+        assert_eq!(result.curvature, Curvature::Maximum);
+        assert_approx_eq!(result.eigenvalues[0], 0.0, 0.000000001);
+
+        let nan = arr2(&[[f64::NAN, 0.0], [0.0, -2.0]]);
+        let result = second_order(&nan, &[None, None]);
+        assert_eq!(result.curvature, Curvature::Undetermined);
+
+        // Nothing left to move: trivially a maximum.
+        let result = second_order(&free, &[Some(BoundSide::Lower), Some(BoundSide::Upper)]);
+        assert_eq!(result.curvature, Curvature::Maximum);
+        assert!(result.eigenvalues.is_empty());
+    }
+}
@@ -1,30 +1,117 @@
+pub mod solver_interior;
 pub mod solver_mrgame;
-
+pub mod solver_multistart;
+pub mod solver_nash;
+pub mod solver_portfolio;
+pub mod solver_response;
+pub mod solver_sensitivity;
+pub mod solver_stackelberg;
+
+use crate::bounds::{ActiveBound, Bound, BoundSide};
 use crate::computation;
-use crate::computation::Input;
+use crate::computation::comp_generic::Variable;
+use crate::computation::{Evaluation, Input};
 use crate::newton;
-use crate::relation::Retailer;
+use crate::newton::{NewtonOptions, NewtonResult, NewtonStats};
+use crate::relation::{Relation, Retailer};
 use crate::rrgame;
 use crate::rrgame::RRGame;
+use crate::second_order;
+use crate::second_order::{Curvature, SecondOrder};
 use computation::{da_NP, da_TVR_constraint, da_Ta_constraint};
+use computation::{dada_NP, dada_TVR_constraint, dpda_NP, dpdp_NP, dpdp_TVR_constraint};
 use computation::{dp_NP, dp_TVR_constraint};
-use computation::{TVR_constraint, Ta_constraint};
-use ndarray::arr1;
-use ndarray::Array1;
+use ndarray::{Array1, Array2};
+pub use solver_interior::*;
 pub use solver_mrgame::*;
+pub use solver_multistart::*;
+pub use solver_nash::*;
+pub use solver_portfolio::*;
+pub use solver_response::*;
+pub use solver_sensitivity::*;
+pub use solver_stackelberg::*;
+use std::fmt;
+
+/// Why an active-set combination was kept or dropped. Combinations are tried
+/// in order and each `Accepted` one replaces the previous best, so the last
+/// `Accepted` combination of a report is the solution.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SolveOutcome {
+    Accepted,
+    /// Newton's method stopped without converging, see `NewtonResult`.
+    Stalled,
+    Singular,
+    Diverged,
+    /// The bounds held by `newton::bounded_newton_solve` did not settle.
+    BoundCycle,
+    /// The Jacobian of the retailers' response to rho_g is singular.
+    SingularResponse,
+    NegativeMultiplier,
+    Infeasible,
+    /// Stationary, but a saddle or a minimum, see `second_order::Curvature`.
+    NotMaximum,
+    /// Feasible, but no better than the best solution found before it.
+    NotImproving,
+}
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 pub struct RRGameConstraints {
     pub TVR_active: bool,
     pub Ta_active: bool,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 pub struct RRGameLambdas {
     pub TVR: f64,
     pub Ta: f64,
 }
 
+#[derive(Clone)]
+pub struct RRGameSolution {
+    pub parameter: rrgame::Parameter,
+    pub lambdas: RRGameLambdas,
+    pub active_bounds: Vec<ActiveBound>,
+    pub second_order: SecondOrder,
+    pub TVR_constraint: f64,
+    pub Ta_constraint: f64,
+    pub NP: f64,
+}
+
+#[derive(Clone)]
+pub struct RRGameAttempt {
+    pub constraints: RRGameConstraints,
+    pub outcome: SolveOutcome,
+    pub newton: NewtonStats,
+    /// `None` when Newton's method did not converge.
+    pub solution: Option<RRGameSolution>,
+}
+
+#[derive(Clone)]
+pub struct RRGameReport {
+    pub retailer: Retailer,
+    pub start_NP: f64,
+    pub attempts: Vec<RRGameAttempt>,
+    /// The best feasible solution, `None` when none beats the starting point.
+    pub parameter: Option<rrgame::Parameter>,
+}
+
+pub struct RRGameReportDisplay<'a> {
+    report: &'a RRGameReport,
+    relation: &'a Relation,
+}
+
+/// The outcome of an active-set combination whose Newton's method did not
+/// converge, `None` when it did.
+pub fn newton_outcome(result: &NewtonResult) -> Option<SolveOutcome> {
+    match result {
+        NewtonResult::Converged(_, _) => None,
+        NewtonResult::Stalled(_, _) => Some(SolveOutcome::Stalled),
+        NewtonResult::Singular(_, _) => Some(SolveOutcome::Singular),
+        NewtonResult::Diverged(_, _) => Some(SolveOutcome::Diverged),
+        NewtonResult::BoundCycle(_, _) => Some(SolveOutcome::BoundCycle),
+    }
+}
+
 impl RRGameConstraints {
     fn array_len(&self, product_count: usize) -> usize {
         let mut result = product_count * 2;
@@ -71,45 +158,24 @@ impl RRGameConstraints {
 
     fn append_constraints(
         &self,
-        input: &Input,
+        evaluation: &Evaluation,
         m: Retailer,
         array: &mut Array1<f64>,
         index: usize,
     ) {
         let mut index = index;
         if self.TVR_active {
-            array[index] = TVR_constraint(input, m);
+            array[index] = evaluation.TVR_constraint(m);
             index += 1;
         }
 
         if self.Ta_active {
-            array[index] = Ta_constraint(input, m);
-        }
-    }
-
-    fn accept_result(
-        &self,
-        lambdas: RRGameLambdas,
-        parameter: rrgame::Parameter,
-    ) -> Option<rrgame::Parameter> {
-        if self.TVR_active && lambdas.TVR < 0.0 {
-            return None;
-        }
-
-        if self.Ta_active && lambdas.Ta < 0.0 {
-            return None;
+            array[index] = evaluation.Ta_constraint(m);
         }
-
-        Some(parameter)
     }
 
-    fn print(&self, lambdas: RRGameLambdas) {
-        if self.TVR_active {
-            println!("Lambda TVR: {}", lambdas.TVR);
-        }
-        if self.Ta_active {
-            println!("Lambda Ta: {}", lambdas.Ta);
-        }
+    fn negative_multiplier(&self, lambdas: RRGameLambdas) -> bool {
+        (self.TVR_active && lambdas.TVR < 0.0) || (self.Ta_active && lambdas.Ta < 0.0)
     }
 }
 
@@ -124,7 +190,7 @@ pub fn rrgame_input_to_array(
     let p_mg = &input.rrgame.parameter.p_mg;
     let a_mg = &input.rrgame.parameter.a_mg;
 
-    let len = relation.products(m, decision).len();
+    let len = relation.products(m, decision).count();
 
     let mut result = Array1::zeros(constraints.array_len(len));
 
@@ -178,26 +244,24 @@ pub fn rrgame_f(
     constraints: RRGameConstraints,
 ) -> Array1<f64> {
     let (parameter, lambdas) = rrgame_array_to_parameter(old_input, m, array, constraints);
-    let rrgame = rrgame::RRGame {
-        parameter,
-        ..(*old_input.rrgame)
-    };
+    let rrgame = rrgame::RRGame { parameter };
 
     let input = Input {
         rrgame: &rrgame,
         ..(*old_input)
     };
+    let evaluation = Evaluation::new(&input);
 
     let relation = input.relation;
     let decision = &input.mrgame.decision;
 
-    let products = relation.products(m, decision);
+    let products: Vec<_> = relation.products(m, decision).collect();
     let len = products.len();
     let mut result = Array1::zeros(constraints.array_len(len));
 
     let mut index = 0;
     for j in products.iter() {
-        result[index] = dp_NP(&input, m, *j);
+        result[index] = dp_NP(&evaluation, m, *j);
         if constraints.TVR_active {
             result[index] -= lambdas.TVR * dp_TVR_constraint(&input, m, *j);
         }
@@ -205,7 +269,7 @@ pub fn rrgame_f(
     }
 
     for j in products.iter() {
-        result[index] = da_NP(&input, m, *j);
+        result[index] = da_NP(&evaluation, m, *j);
         if constraints.TVR_active {
             result[index] -= lambdas.TVR * da_TVR_constraint(&input, m, *j)
         }
@@ -215,88 +279,436 @@ pub fn rrgame_f(
         index += 1;
     }
 
-    constraints.append_constraints(&input, m, &mut result, index);
+    constraints.append_constraints(&evaluation, m, &mut result, index);
 
     result
 }
 
+/// The Jacobian of `rrgame_f` with respect to `array`.
+pub fn rrgame_jacobian(
+    old_input: &Input,
+    m: Retailer,
+    array: &Array1<f64>,
+    constraints: RRGameConstraints,
+) -> Array2<f64> {
+    let (parameter, lambdas) = rrgame_array_to_parameter(old_input, m, array, constraints);
+    let rrgame = rrgame::RRGame { parameter };
+
+    let input = Input {
+        rrgame: &rrgame,
+        ..(*old_input)
+    };
+    let evaluation = Evaluation::new(&input);
+
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let products: Vec<_> = relation.products(m, decision).collect();
+    let len = products.len();
+    let mut result = Array2::zeros((array.len(), array.len()));
+
+    let TVR_index = 2 * len;
+    let Ta_index = if constraints.TVR_active {
+        TVR_index + 1
+    } else {
+        TVR_index
+    };
+
+    for (row, j) in products.iter().enumerate() {
+        for (col, k) in products.iter().enumerate() {
+            result[(row, col)] = dpdp_NP(&evaluation, m, *j, *k);
+            if constraints.TVR_active {
+                result[(row, col)] -= lambdas.TVR * dpdp_TVR_constraint(&input, m, *j, *k);
+            }
+            result[(row, len + col)] = dpda_NP(&input, m, *j, *k);
+        }
+        if constraints.TVR_active {
+            result[(row, TVR_index)] = -dp_TVR_constraint(&input, m, *j);
+        }
+    }
+
+    for (row, j) in products.iter().enumerate() {
+        let row = len + row;
+        for (col, k) in products.iter().enumerate() {
+            result[(row, col)] = dpda_NP(&input, m, *k, *j);
+            result[(row, len + col)] = dada_NP(&evaluation, m, *j, *k);
+            if constraints.TVR_active {
+                result[(row, len + col)] -= lambdas.TVR * dada_TVR_constraint(&input, m, *j, *k);
+            }
+        }
+        if constraints.TVR_active {
+            result[(row, TVR_index)] = -da_TVR_constraint(&input, m, *j);
+        }
+        if constraints.Ta_active {
+            result[(row, Ta_index)] = -da_Ta_constraint(&input, m, *j);
+        }
+    }
+
+    for (col, k) in products.iter().enumerate() {
+        if constraints.TVR_active {
+            result[(TVR_index, col)] = dp_TVR_constraint(&input, m, *k);
+            result[(TVR_index, len + col)] = da_TVR_constraint(&input, m, *k);
+        }
+        if constraints.Ta_active {
+            result[(Ta_index, len + col)] = da_Ta_constraint(&input, m, *k);
+        }
+    }
+
+    result
+}
+
+/// The variables of retailer `m` in the order of `rrgame_input_to_array`.
+pub fn rrgame_variables(input: &Input, m: Retailer) -> Vec<Variable> {
+    let products: Vec<_> = input.relation.products(m, &input.mrgame.decision).collect();
+
+    let mut result = Vec::new();
+    for g in products.iter() {
+        result.push(Variable::p_mg(m, *g));
+    }
+    for g in products.iter() {
+        result.push(Variable::a_mg(m, *g));
+    }
+    result
+}
+
 fn rrgame_solve_constraints(
     input: &Input,
     m: Retailer,
     constraints: RRGameConstraints,
-) -> Option<rrgame::Parameter> {
+) -> (NewtonResult, Vec<Option<BoundSide>>) {
     let f = |a: &Array1<f64>| rrgame_f(input, m, a, constraints);
+    let jacobian = |a: &Array1<f64>| rrgame_jacobian(input, m, a, constraints);
     let x0 = rrgame_input_to_array(input, m, constraints, RRGameLambdas { TVR: 1.0, Ta: 1.0 });
-    let len = input.relation.products(m, &input.mrgame.decision).len();
-    let arr: Vec<f64> = (0..(constraints.array_len(len)))
-        .map(|_| 0.000001)
+    let bounds: Vec<Bound> = rrgame_variables(input, m)
+        .into_iter()
+        .map(|x| input.constant.bounds.bound(x))
         .collect();
-    let dx0 = arr1(&arr);
-
-    let x = newton::newton_method(&f, &x0, &dx0, 0.5, 10)?;
-
-    let (parameter, lambdas) = rrgame_array_to_parameter(input, m, &x, constraints);
-    constraints.print(lambdas);
 
-    return constraints.accept_result(lambdas, parameter);
+    newton::bounded_newton_solve(&f, &jacobian, &x0, &bounds, &NewtonOptions::default())
 }
 
 fn rrgame_try_constraint(
-    old_parameter: Option<rrgame::Parameter>,
+    report: &mut RRGameReport,
     input: &Input,
     m: Retailer,
     profit: &mut f64,
     TVR_active: bool,
     Ta_active: bool,
-) -> Option<rrgame::Parameter> {
+) {
     let constraints = RRGameConstraints {
         TVR_active,
         Ta_active,
     };
-    if let Some(parameter) = rrgame_solve_constraints(input, m, constraints) {
-        let rrgame = RRGame {
-            parameter: parameter,
-            ..(*input.rrgame)
-        };
 
-        let (cst1, cst2, new_profit) = {
-            let new_input = Input {
-                rrgame: &rrgame,
-                ..(*input)
-            };
-            (
-                computation::TVR_constraint(&new_input, m),
-                computation::Ta_constraint(&new_input, m),
-                computation::NP(&new_input, m),
-            )
+    let (result, held) = rrgame_solve_constraints(input, m, constraints);
+    let newton = *result.stats();
+    if let Some(outcome) = newton_outcome(&result) {
+        report.attempts.push(RRGameAttempt {
+            constraints,
+            outcome,
+            newton,
+            solution: None,
+        });
+        return;
+    }
+
+    let x = result.x();
+    let (parameter, lambdas) = rrgame_array_to_parameter(input, m, x, constraints);
+    let active_bounds = ActiveBound::collect(
+        &rrgame_variables(input, m),
+        &held,
+        rrgame_f(input, m, x, constraints).as_slice().unwrap(),
+    );
+    let jacobian = rrgame_jacobian(input, m, x, constraints);
+    let second_order = second_order::second_order(&jacobian, &held);
+
+    let rrgame = RRGame { parameter };
+    let (TVR_constraint, Ta_constraint, NP) = {
+        let new_input = Input {
+            rrgame: &rrgame,
+            ..(*input)
         };
+        (
+            computation::TVR_constraint(&new_input, m),
+            computation::Ta_constraint(&new_input, m),
+            computation::NP(&new_input, m),
+        )
+    };
 
-        println!("TVR constraint: {}", cst1);
-        println!("Ta constraint: {}", cst2);
+    let epsilon = 0.000001;
+    let outcome = if constraints.negative_multiplier(lambdas) {
+        SolveOutcome::NegativeMultiplier
+    } else if TVR_constraint > epsilon || Ta_constraint > epsilon {
+        SolveOutcome::Infeasible
+    } else if second_order.curvature != Curvature::Maximum {
+        SolveOutcome::NotMaximum
+    } else if NP <= *profit {
+        SolveOutcome::NotImproving
+    } else {
+        SolveOutcome::Accepted
+    };
 
-        let epsilon = 0.000001;
-        if new_profit > *profit && cst1 <= epsilon && cst2 <= epsilon {
-            *profit = new_profit;
-            println!("New profit: {}", *profit);
-            return Some(rrgame.parameter);
-        }
+    if outcome == SolveOutcome::Accepted {
+        *profit = NP;
+        report.parameter = Some(rrgame.parameter.clone());
     }
 
-    old_parameter
+    report.attempts.push(RRGameAttempt {
+        constraints,
+        outcome,
+        newton,
+        solution: Some(RRGameSolution {
+            parameter: rrgame.parameter,
+            lambdas,
+            active_bounds,
+            second_order,
+            TVR_constraint,
+            Ta_constraint,
+            NP,
+        }),
+    });
 }
 
-pub fn rrgame_solve(input: &Input, m: Retailer) -> Option<rrgame::Parameter> {
-    let mut result: Option<rrgame::Parameter> = None;
+/// Tries every combination of active TVR and Ta constraints for retailer `m`
+/// and keeps the feasible solution with the highest NP.
+pub fn rrgame_solve(input: &Input, m: Retailer) -> RRGameReport {
     let mut profit = computation::NP(input, m);
+    let mut report = RRGameReport {
+        retailer: m,
+        start_NP: profit,
+        attempts: Vec::new(),
+        parameter: None,
+    };
 
-    result = rrgame_try_constraint(result, input, m, &mut profit, false, false);
-    println!("----------------");
-    result = rrgame_try_constraint(result, input, m, &mut profit, true, false);
-    println!("----------------");
-    result = rrgame_try_constraint(result, input, m, &mut profit, false, true);
-    println!("----------------");
-    result = rrgame_try_constraint(result, input, m, &mut profit, true, true);
-    println!("----------------");
+    rrgame_try_constraint(&mut report, input, m, &mut profit, false, false);
+    rrgame_try_constraint(&mut report, input, m, &mut profit, true, false);
+    rrgame_try_constraint(&mut report, input, m, &mut profit, false, true);
+    rrgame_try_constraint(&mut report, input, m, &mut profit, true, true);
 
-    result
+    report
+}
+
+/// How a retailer's problem is solved.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RRGameMethod {
+    /// `rrgame_solve`: one Newton solve per combination of active constraints.
+    ActiveSet,
+    /// `rrgame_interior_solve` with the default options.
+    InteriorPoint,
+}
+
+/// The best response of retailer `m` by `method`.
+pub fn rrgame_respond(input: &Input, m: Retailer, method: RRGameMethod) -> RRGameReport {
+    match method {
+        RRGameMethod::ActiveSet => rrgame_solve(input, m),
+        RRGameMethod::InteriorPoint => rrgame_interior_solve(input, m, &InteriorOptions::default()),
+    }
+}
+
+fn on_off(active: bool) -> &'static str {
+    if active {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+impl RRGameReport {
+    /// Whether some attempt found a feasible local maximum: `parameter` when
+    /// it beats the starting point, otherwise the starting point is the
+    /// retailer's best response.
+    pub fn responded(&self) -> bool {
+        self.parameter.is_some()
+            || self
+                .attempts
+                .iter()
+                .any(|attempt| attempt.outcome == SolveOutcome::NotImproving)
+    }
+
+    /// Formats the report, labelling the retailer with its name in `relation`.
+    pub fn display<'a>(&'a self, relation: &'a Relation) -> RRGameReportDisplay<'a> {
+        RRGameReportDisplay {
+            report: self,
+            relation,
+        }
+    }
+}
+
+impl<'a> fmt::Display for RRGameReportDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let report = self.report;
+        let retailer = self.relation.retailer_label(report.retailer);
+
+        writeln!(
+            f,
+            "RR game, retailer {}: start NP = {}",
+            retailer, report.start_NP
+        )?;
+        for attempt in report.attempts.iter() {
+            let constraints = attempt.constraints;
+            write!(
+                f,
+                "TVR {}, Ta {}: {:?}",
+                on_off(constraints.TVR_active),
+                on_off(constraints.Ta_active),
+                attempt.outcome
+            )?;
+            write!(
+                f,
+                "\titerations: {}\t|f|: {}",
+                attempt.newton.iterations, attempt.newton.f_norm
+            )?;
+            if let Some(solution) = &attempt.solution {
+                write!(
+                    f,
+                    "\tNP: {}\tTVR constraint: {}\tTa constraint: {}",
+                    solution.NP, solution.TVR_constraint, solution.Ta_constraint
+                )?;
+                if constraints.TVR_active {
+                    write!(f, "\tLambda TVR: {}", solution.lambdas.TVR)?;
+                }
+                if constraints.Ta_active {
+                    write!(f, "\tLambda Ta: {}", solution.lambdas.Ta)?;
+                }
+                for bound in solution.active_bounds.iter() {
+                    write!(f, "\t{}: {}", bound.label(self.relation), bound.multiplier)?;
+                }
+                write!(f, "\t{}", solution.second_order)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn test_rrgame_report() {
+        let scenario = Scenario::parse(include_str!("../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        for m in relation.initial_retailers() {
+            let report = rrgame_solve(&input, m);
+            assert_eq!(report.attempts.len(), 4);
+
+            let mut best = report.start_NP;
+            let mut accepted = None;
+            for attempt in report.attempts.iter() {
+                if attempt.solution.is_none() {
+                    assert_ne!(attempt.outcome, SolveOutcome::Accepted);
+                    continue;
+                }
+
+                let solution = attempt.solution.as_ref().unwrap();
+                if attempt.outcome == SolveOutcome::Accepted {
+                    assert!(solution.NP > best);
+                    assert_eq!(solution.second_order.curvature, Curvature::Maximum);
+                    assert!(solution.TVR_constraint <= 0.000001);
+                    assert!(solution.Ta_constraint <= 0.000001);
+                    best = solution.NP;
+                    accepted = Some(solution.parameter.clone());
+                }
+            }
+
+            assert_eq!(accepted.is_some(), report.parameter.is_some());
+            if let (Some(accepted), Some(parameter)) = (accepted, report.parameter) {
+                assert_eq!(accepted.max_change(&relation, &parameter), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rrgame_bounds() {
+        let scenario = Scenario::parse(include_str!("../scenarios/example.toml")).unwrap();
+        let (relation, mut constant, mrgame, rrgame) = scenario.build().unwrap();
+        let m = Retailer { id: 1 };
+        let g = relation.products(m, &mrgame.decision).next().unwrap();
+
+        let free = {
+            let input = Input {
+                relation: &relation,
+                constant: &constant,
+                mrgame: &mrgame,
+                rrgame: &rrgame,
+            };
+            rrgame_solve(&input, m).parameter.unwrap()
+        };
+
+        let cap = free.p_mg[m][g] * 0.9;
+        constant.bounds.p_mg[m][g].upper = cap;
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let report = rrgame_solve(&input, m);
+        let parameter = report.parameter.clone().unwrap();
+        assert!((parameter.p_mg[m][g] - cap).abs() < 0.000001);
+
+        let accepted = report
+            .attempts
+            .iter()
+            .rfind(|attempt| attempt.outcome == SolveOutcome::Accepted)
+            .unwrap();
+        let active_bounds = &accepted.solution.as_ref().unwrap().active_bounds;
+        assert_eq!(active_bounds.len(), 1);
+        assert_eq!(active_bounds[0].variable, Variable::p_mg(m, g));
+        assert_eq!(active_bounds[0].side, BoundSide::Upper);
+        assert!(active_bounds[0].multiplier >= 0.0);
+    }
+
+    #[test]
+    fn test_rrgame_jacobian() {
+        let scenario = Scenario::parse(include_str!("../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, mut rrgame) = scenario.build().unwrap();
+
+        // DP has a kink where a price or an advertising budget is zero.
+        for m in relation.initial_retailers() {
+            for g in relation.products(m, &mrgame.decision) {
+                rrgame.parameter.p_mg[m][g] = rrgame.parameter.p_mg[m][g].max(50.0);
+                rrgame.parameter.a_mg[m][g] = rrgame.parameter.a_mg[m][g].max(500.0);
+            }
+        }
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let lambdas = RRGameLambdas { TVR: 0.5, Ta: 2.0 };
+        for m in relation.initial_retailers() {
+            for index in 0..4 {
+                let constraints = RRGameConstraints {
+                    TVR_active: index & 1 != 0,
+                    Ta_active: index & 2 != 0,
+                };
+                let x = rrgame_input_to_array(&input, m, constraints, lambdas);
+                let f = |a: &Array1<f64>| rrgame_f(&input, m, a, constraints);
+                let dx0 = Array1::from(vec![0.0001; x.len()]);
+
+                let exact = rrgame_jacobian(&input, m, &x, constraints);
+                let approx = newton::jacobi(&f, &x, &dx0);
+                for ((row, col), e) in exact.indexed_iter() {
+                    let a = approx[(row, col)];
+                    assert!(
+                        (e - a).abs() <= 0.001 * e.abs().max(1.0),
+                        "{:?}",
+                        (row, col)
+                    );
+                }
+            }
+        }
+    }
 }
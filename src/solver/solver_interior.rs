@@ -0,0 +1,544 @@
+use super::{rrgame_array_to_parameter, rrgame_f, rrgame_input_to_array, rrgame_jacobian};
+use super::{rrgame_variables, RRGameAttempt, RRGameConstraints, RRGameLambdas};
+use super::{RRGameReport, RRGameSolution, SolveOutcome};
+use crate::bounds::{ActiveBound, Bound, BoundSide};
+use crate::computation;
+use crate::computation::Input;
+use crate::newton::NewtonStats;
+use crate::relation::Retailer;
+use crate::rrgame::RRGame;
+use crate::second_order;
+use crate::second_order::Curvature;
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{EigValsh, Solve, UPLO};
+
+// A primal-dual interior-point method for the retailer problem
+//
+//     max NP(x)  s.t.  g(x) + s = 0,  s >= 0,  l <= x <= u
+//
+// with g = (TVR_constraint, Ta_constraint). Instead of one Newton solve per
+// combination of active constraints, every iteration takes a Newton step on
+// the KKT conditions with the complementarity relaxed to λ s = μ, z (x - l) = μ
+// and z (u - x) = μ, and μ is driven towards zero. The Newton matrix is
+// `rrgame_jacobian` with both constraints active plus the barrier terms,
+// with its Hessian block shifted where needed so that every step heads for a
+// maximum rather than a saddle.
+
+const BOTH: RRGameConstraints = RRGameConstraints {
+    TVR_active: true,
+    Ta_active: true,
+};
+
+#[derive(Debug, Clone)]
+pub struct InteriorOptions {
+    /// Converged once the norm of the KKT residual and the average
+    /// complementarity fall below this value.
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    /// Average complementarity of the starting point.
+    pub mu0: f64,
+    /// Share of the average complementarity each step aims for.
+    pub centering: f64,
+    /// Share of the distance to the boundary of the positive orthant a step
+    /// may cover.
+    pub fraction_to_boundary: f64,
+    /// Distance, relative to `max(1, |bound|)`, the starting point keeps from
+    /// the bounds.
+    pub bound_push: f64,
+    /// Diverged once the KKT residual grows beyond this value.
+    pub max_f_norm: f64,
+    /// The curvature the shifted Hessian block keeps at least along every
+    /// direction, see `inertia_shift`.
+    pub min_curvature: f64,
+}
+
+impl Default for InteriorOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.000001,
+            max_iterations: 100,
+            mu0: 1.0,
+            centering: 0.1,
+            fraction_to_boundary: 0.995,
+            bound_push: 0.01,
+            max_f_norm: 1.0e20,
+            min_curvature: 0.00000001,
+        }
+    }
+}
+
+/// The primal and dual variables of an iterate besides `x`, which holds the
+/// variables of retailer `m` followed by the multipliers of TVR and Ta as in
+/// `rrgame_input_to_array`. Multipliers of infinite bounds stay zero.
+struct Duals {
+    s: [f64; 2],
+    z_lower: Vec<f64>,
+    z_upper: Vec<f64>,
+}
+
+/// `x` moved into the interior of `bound`.
+fn push_inside(bound: &Bound, x: f64, push: f64) -> f64 {
+    let mut lower = bound.lower + push * bound.lower.abs().max(1.0);
+    let mut upper = bound.upper - push * bound.upper.abs().max(1.0);
+    if lower > upper {
+        lower = 0.5 * (bound.lower + bound.upper);
+        upper = lower;
+    }
+    x.max(lower).min(upper)
+}
+
+/// The largest step in `(0, 1]` along `delta` that keeps the positive `value`
+/// above `1 - tau` times its current size.
+fn max_step(value: f64, delta: f64, tau: f64) -> f64 {
+    if delta < 0.0 {
+        (-tau * value / delta).min(1.0)
+    } else {
+        1.0
+    }
+}
+
+/// The shift `δ >= 0` for which `W - δ I - Gᵀ D⁻¹ G`, what is left of the
+/// Newton matrix `[W, -Gᵀ; G, -D]` of `interior_iterate` after eliminating
+/// `dλ`, is negative definite with no eigenvalue above `-min_curvature`.
+/// Only then is the step one towards a maximum. `None` when the eigenvalues
+/// cannot be computed.
+fn inertia_shift(matrix: &Array2<f64>, n: usize, min_curvature: f64) -> Option<f64> {
+    let mut condensed = Array2::zeros((n, n));
+    for j in 0..n {
+        for k in 0..n {
+            let mut value = matrix[(j, k)];
+            for i in n..matrix.nrows() {
+                value -= matrix[(j, i)] * matrix[(i, k)] / matrix[(i, i)];
+            }
+            condensed[(j, k)] = value;
+        }
+    }
+
+    let mut symmetric = Array2::zeros((n, n));
+    for j in 0..n {
+        for k in 0..n {
+            symmetric[(j, k)] = 0.5 * (condensed[(j, k)] + condensed[(k, j)]);
+        }
+    }
+    let eigenvalues = symmetric.eigvalsh(UPLO::Lower).ok()?;
+    let largest = eigenvalues
+        .iter()
+        .fold(f64::NEG_INFINITY, |acc, v| acc.max(*v));
+    if largest.is_nan() {
+        return None;
+    }
+    Some((largest + min_curvature).max(0.0))
+}
+
+/// Whether an inequality whose slack is `slack` is active at a solution with
+/// average complementarity `mu`: slack and multiplier multiply to about `mu`,
+/// and the slack is the smaller side of it, below `sqrt(mu)` relative to
+/// `scale`.
+fn is_active(slack: f64, scale: f64, mu: f64) -> bool {
+    slack < mu.sqrt() * scale
+}
+
+/// Sum and number of the complementarity products of an iterate.
+fn complementarity(bounds: &[Bound], x: &Array1<f64>, duals: &Duals) -> (f64, usize) {
+    let n = bounds.len();
+    let mut sum = 0.0;
+    let mut count = 0;
+    for i in 0..2 {
+        sum += x[n + i] * duals.s[i];
+        count += 1;
+    }
+    for (j, bound) in bounds.iter().enumerate() {
+        if bound.lower.is_finite() {
+            sum += duals.z_lower[j] * (x[j] - bound.lower);
+            count += 1;
+        }
+        if bound.upper.is_finite() {
+            sum += duals.z_upper[j] * (bound.upper - x[j]);
+            count += 1;
+        }
+    }
+    (sum, count)
+}
+
+/// Runs the interior-point iterations for retailer `m` from the current
+/// parameter. Returns the outcome, `None` on convergence, with the last
+/// iterate.
+fn interior_iterate(
+    input: &Input,
+    m: Retailer,
+    bounds: &[Bound],
+    options: &InteriorOptions,
+    stats: &mut NewtonStats,
+) -> (Option<SolveOutcome>, Array1<f64>, Duals) {
+    let n = bounds.len();
+    let tau = options.fraction_to_boundary;
+    let mu0 = options.mu0;
+
+    let lambdas = RRGameLambdas { TVR: 0.0, Ta: 0.0 };
+    let mut x = rrgame_input_to_array(input, m, BOTH, lambdas);
+    for (j, bound) in bounds.iter().enumerate() {
+        x[j] = push_inside(bound, x[j], options.bound_push);
+    }
+
+    let fx = rrgame_f(input, m, &x, BOTH);
+    stats.evaluations += 1;
+    let mut duals = Duals {
+        s: [(-fx[n]).max(1.0), (-fx[n + 1]).max(1.0)],
+        z_lower: vec![0.0; n],
+        z_upper: vec![0.0; n],
+    };
+    for i in 0..2 {
+        x[n + i] = mu0 / duals.s[i];
+    }
+    for (j, bound) in bounds.iter().enumerate() {
+        if bound.lower.is_finite() {
+            duals.z_lower[j] = mu0 / (x[j] - bound.lower);
+        }
+        if bound.upper.is_finite() {
+            duals.z_upper[j] = mu0 / (bound.upper - x[j]);
+        }
+    }
+
+    loop {
+        let fx = rrgame_f(input, m, &x, BOTH);
+        stats.evaluations += 1;
+
+        let mut r_d = Array1::zeros(n);
+        for j in 0..n {
+            r_d[j] = fx[j] + duals.z_lower[j] - duals.z_upper[j];
+        }
+        let r_p = [fx[n] + duals.s[0], fx[n + 1] + duals.s[1]];
+
+        let (gap, count) = complementarity(bounds, &x, &duals);
+        let mu_average = gap / count as f64;
+        stats.f_norm = (r_d.dot(&r_d) + r_p[0] * r_p[0] + r_p[1] * r_p[1]).sqrt();
+
+        if !stats.f_norm.is_finite() || stats.f_norm > options.max_f_norm {
+            return (Some(SolveOutcome::Diverged), x, duals);
+        }
+
+        if stats.f_norm <= options.tolerance && mu_average <= options.tolerance {
+            return (None, x, duals);
+        }
+
+        if stats.iterations >= options.max_iterations {
+            return (Some(SolveOutcome::Stalled), x, duals);
+        }
+
+        // Eliminating ds and dz leaves the system in (dx, dλ)
+        //
+        //     [ H - Σ   -Gᵀ ] [dx]   [ -r_d - μ/(x - l) + z_l + μ/(u - x) - z_u ]
+        //     [ G       -D  ] [dλ] = [ -r_p + s - μ/λ                           ]
+        //
+        // with Σ = z_l/(x - l) + z_u/(u - x) and D = s/λ on the diagonals.
+        let mu = options.centering * mu_average;
+        let mut matrix = rrgame_jacobian(input, m, &x, BOTH);
+        stats.jacobians += 1;
+        let mut rhs = Array1::zeros(n + 2);
+        for (j, bound) in bounds.iter().enumerate() {
+            rhs[j] = -r_d[j];
+            if bound.lower.is_finite() {
+                let distance = x[j] - bound.lower;
+                matrix[(j, j)] -= duals.z_lower[j] / distance;
+                rhs[j] -= mu / distance - duals.z_lower[j];
+            }
+            if bound.upper.is_finite() {
+                let distance = bound.upper - x[j];
+                matrix[(j, j)] -= duals.z_upper[j] / distance;
+                rhs[j] += mu / distance - duals.z_upper[j];
+            }
+        }
+        for i in 0..2 {
+            matrix[(n + i, n + i)] = -duals.s[i] / x[n + i];
+            rhs[n + i] = -r_p[i] + duals.s[i] - mu / x[n + i];
+        }
+
+        let shift = match inertia_shift(&matrix, n, options.min_curvature) {
+            Some(shift) => shift,
+            None => return (Some(SolveOutcome::Singular), x, duals),
+        };
+        for j in 0..n {
+            matrix[(j, j)] -= shift;
+        }
+
+        let d = match matrix.solve(&rhs) {
+            Ok(d) if d.iter().all(|v| v.is_finite()) => d,
+            _ => return (Some(SolveOutcome::Singular), x, duals),
+        };
+        stats.iterations += 1;
+
+        let mut ds = [0.0; 2];
+        for i in 0..2 {
+            ds[i] = -r_p[i];
+            for j in 0..n {
+                ds[i] -= matrix[(n + i, j)] * d[j];
+            }
+        }
+
+        let mut primal_step: f64 = 1.0;
+        let mut dual_step: f64 = 1.0;
+        for i in 0..2 {
+            primal_step = primal_step.min(max_step(duals.s[i], ds[i], tau));
+            dual_step = dual_step.min(max_step(x[n + i], d[n + i], tau));
+        }
+
+        let mut dz_lower = vec![0.0; n];
+        let mut dz_upper = vec![0.0; n];
+        for (j, bound) in bounds.iter().enumerate() {
+            if bound.lower.is_finite() {
+                let distance = x[j] - bound.lower;
+                dz_lower[j] = mu / distance - duals.z_lower[j] - duals.z_lower[j] / distance * d[j];
+                primal_step = primal_step.min(max_step(distance, d[j], tau));
+                dual_step = dual_step.min(max_step(duals.z_lower[j], dz_lower[j], tau));
+            }
+            if bound.upper.is_finite() {
+                let distance = bound.upper - x[j];
+                dz_upper[j] = mu / distance - duals.z_upper[j] + duals.z_upper[j] / distance * d[j];
+                primal_step = primal_step.min(max_step(distance, -d[j], tau));
+                dual_step = dual_step.min(max_step(duals.z_upper[j], dz_upper[j], tau));
+            }
+        }
+
+        let mut dx_norm = 0.0;
+        for j in 0..n {
+            x[j] += primal_step * d[j];
+            dx_norm += d[j] * d[j];
+            duals.z_lower[j] += dual_step * dz_lower[j];
+            duals.z_upper[j] += dual_step * dz_upper[j];
+        }
+        for i in 0..2 {
+            duals.s[i] += primal_step * ds[i];
+            x[n + i] += dual_step * d[n + i];
+        }
+        stats.dx_norm = primal_step * dx_norm.sqrt();
+    }
+}
+
+/// Solves the problem of retailer `m` with TVR, Ta and the variable bounds as
+/// inequalities in a single interior-point solve. The report holds a single
+/// attempt whose constraints are the ones found active, see `is_active`, with
+/// the slack of a bound scaled by `max(1, |bound|)`.
+pub fn rrgame_interior_solve(
+    input: &Input,
+    m: Retailer,
+    options: &InteriorOptions,
+) -> RRGameReport {
+    let start_NP = computation::NP(input, m);
+    let mut report = RRGameReport {
+        retailer: m,
+        start_NP,
+        attempts: Vec::new(),
+        parameter: None,
+    };
+
+    let variables = rrgame_variables(input, m);
+    let bounds: Vec<Bound> = variables
+        .iter()
+        .map(|x| input.constant.bounds.bound(*x))
+        .collect();
+    let n = bounds.len();
+
+    let mut newton = NewtonStats::default();
+    let (outcome, x, duals) = interior_iterate(input, m, &bounds, options, &mut newton);
+    let (gap, count) = complementarity(&bounds, &x, &duals);
+    let mu = gap / count as f64;
+    let constraints = RRGameConstraints {
+        TVR_active: is_active(duals.s[0], 1.0, mu),
+        Ta_active: is_active(duals.s[1], 1.0, mu),
+    };
+
+    if let Some(outcome) = outcome {
+        report.attempts.push(RRGameAttempt {
+            constraints,
+            outcome,
+            newton,
+            solution: None,
+        });
+        return report;
+    }
+
+    let (parameter, lambdas) = rrgame_array_to_parameter(input, m, &x, BOTH);
+    let mut active_bounds = Vec::new();
+    let mut held = vec![None; n];
+    for (j, bound) in bounds.iter().enumerate() {
+        if bound.lower.is_finite() && is_active(x[j] - bound.lower, bound.lower.abs().max(1.0), mu)
+        {
+            held[j] = Some(BoundSide::Lower);
+            active_bounds.push(ActiveBound {
+                variable: variables[j],
+                side: BoundSide::Lower,
+                multiplier: duals.z_lower[j],
+            });
+        }
+        if bound.upper.is_finite() && is_active(bound.upper - x[j], bound.upper.abs().max(1.0), mu)
+        {
+            held[j] = Some(BoundSide::Upper);
+            active_bounds.push(ActiveBound {
+                variable: variables[j],
+                side: BoundSide::Upper,
+                multiplier: duals.z_upper[j],
+            });
+        }
+    }
+
+    let rrgame = RRGame { parameter };
+    let (array, TVR_constraint, Ta_constraint, NP) = {
+        let new_input = Input {
+            rrgame: &rrgame,
+            ..(*input)
+        };
+        (
+            rrgame_input_to_array(&new_input, m, constraints, lambdas),
+            computation::TVR_constraint(&new_input, m),
+            computation::Ta_constraint(&new_input, m),
+            computation::NP(&new_input, m),
+        )
+    };
+
+    // Certified on the constraints found active, as in `rrgame_solve`.
+    let jacobian = rrgame_jacobian(input, m, &array, constraints);
+    let second_order = second_order::second_order(&jacobian, &held);
+
+    let epsilon = 0.000001;
+    let outcome = if TVR_constraint > epsilon || Ta_constraint > epsilon {
+        SolveOutcome::Infeasible
+    } else if second_order.curvature != Curvature::Maximum {
+        SolveOutcome::NotMaximum
+    } else if NP <= start_NP {
+        SolveOutcome::NotImproving
+    } else {
+        SolveOutcome::Accepted
+    };
+
+    if outcome == SolveOutcome::Accepted {
+        report.parameter = Some(rrgame.parameter.clone());
+    }
+
+    report.attempts.push(RRGameAttempt {
+        constraints,
+        outcome,
+        newton,
+        solution: Some(RRGameSolution {
+            parameter: rrgame.parameter,
+            lambdas,
+            active_bounds,
+            second_order,
+            TVR_constraint,
+            Ta_constraint,
+            NP,
+        }),
+    });
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+    use crate::solver::rrgame_solve;
+    use assert_approx_eq::assert_approx_eq;
+    use ndarray::arr2;
+
+    #[test]
+    fn test_inertia_shift() {
+        // W = diag(1, -2) is convex along x_0, but the constraint g = x_0
+        // with D = 1 takes away 1 from the condensed matrix along it.
+        let matrix = arr2(&[[1.0, 0.0, -1.0], [0.0, -2.0, 0.0], [1.0, 0.0, -1.0]]);
+        assert_approx_eq!(inertia_shift(&matrix, 2, 0.5).unwrap(), 0.5);
+
+        let convex = arr2(&[[3.0, 0.0, -1.0], [0.0, -2.0, 0.0], [1.0, 0.0, -1.0]]);
+        assert_approx_eq!(inertia_shift(&convex, 2, 0.5).unwrap(), 2.5);
+
+        let concave = arr2(&[[-1.0, 0.0, -1.0], [0.0, -2.0, 0.0], [1.0, 0.0, -1.0]]);
+        assert_eq!(inertia_shift(&concave, 2, 0.5).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_rrgame_interior_solve() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        for m in relation.initial_retailers() {
+            let report = rrgame_interior_solve(&input, m, &InteriorOptions::default());
+            assert_eq!(report.attempts.len(), 1);
+            let attempt = &report.attempts[0];
+
+            let solution = attempt.solution.as_ref().unwrap();
+            assert!(solution.lambdas.TVR >= 0.0 && solution.lambdas.Ta >= 0.0);
+            assert!(solution.TVR_constraint <= 0.000001);
+            assert!(solution.Ta_constraint <= 0.000001);
+
+            assert_eq!(attempt.outcome, SolveOutcome::Accepted);
+            assert_eq!(solution.second_order.curvature, Curvature::Maximum);
+
+            // No active-set combination does better than the single solve, and
+            // one that does as well found the same point. The web store's
+            // active-set solve stops at a lower maximum with a_mg of the viewer
+            // at its bound.
+            let active_set = rrgame_solve(&input, m);
+            for attempt in active_set.attempts.iter() {
+                if attempt.outcome == SolveOutcome::Accepted {
+                    let other = attempt.solution.as_ref().unwrap();
+                    assert!(other.NP <= solution.NP + 0.0001);
+                    if other.NP >= solution.NP - 0.0001 {
+                        let change = other.parameter.max_change(&relation, &solution.parameter);
+                        assert!(change < 0.0001);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rrgame_interior_bounds() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, mut constant, mrgame, rrgame) = scenario.build().unwrap();
+        let m = Retailer { id: 1 };
+        let g = relation.products(m, &mrgame.decision).next().unwrap();
+
+        let free = {
+            let input = Input {
+                relation: &relation,
+                constant: &constant,
+                mrgame: &mrgame,
+                rrgame: &rrgame,
+            };
+            rrgame_solve(&input, m).parameter.unwrap()
+        };
+
+        let cap = free.p_mg[m][g] * 0.9;
+        constant.bounds.p_mg[m][g].upper = cap;
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let report = rrgame_interior_solve(&input, m, &InteriorOptions::default());
+        let parameter = report.parameter.unwrap();
+        assert!((parameter.p_mg[m][g] - cap).abs() < 0.0001);
+        assert!(parameter.p_mg[m][g] <= cap);
+
+        let interior = &report.attempts[0].solution.as_ref().unwrap().active_bounds;
+        let active_set = rrgame_solve(&input, m);
+        let accepted = active_set
+            .attempts
+            .iter()
+            .rfind(|attempt| attempt.outcome == SolveOutcome::Accepted)
+            .unwrap();
+        let expected = &accepted.solution.as_ref().unwrap().active_bounds;
+
+        assert_eq!(interior.len(), 1);
+        assert_eq!(interior[0].variable, expected[0].variable);
+        assert_eq!(interior[0].side, BoundSide::Upper);
+        assert!((interior[0].multiplier - expected[0].multiplier).abs() < 0.001);
+    }
+}
@@ -1,60 +1,270 @@
+use super::{newton_outcome, RhoResponse, SolveOutcome};
+use crate::bounds::{ActiveBound, Bound, BoundSide};
 use crate::computation;
-use crate::computation::Input;
+use crate::computation::comp_generic;
+use crate::computation::comp_generic::{DualInput, Variable};
+use crate::computation::{Evaluation, Input};
+use crate::dual::{Dual, HyperDual, Scalar};
 use crate::mrgame;
 use crate::newton;
-use crate::relation::{MaterialMap, Relation};
-use ndarray::{arr1, Array1};
+use crate::newton::{NewtonOptions, NewtonResult, NewtonStats};
+use crate::relation::{MaterialMap, Product, Relation};
+use crate::second_order;
+use crate::second_order::{Curvature, SecondOrder};
+use computation::{dA_NP0_TVP_constraint, dA_NP0_bom_constraint};
+use computation::{dAdA_NP0_TVP_constraint, dAdA_NP0_bom_constraint, dcdA_NP0_TVP_constraint};
+use computation::{dc_NP0_TVP_constraint, dcrm_NP0_TVP_constraint};
+use computation::{dcrmddrm_NP0_TVP_constraint, ddrm_NP0_TVP_constraint, ddrm_NP0_bom_constraint};
+use ndarray::{Array1, Array2};
+use std::cmp::Ordering;
+use std::fmt;
+
+// The BOM constraints read NP0_bom_constraint >= 0 (the purchased materials
+// cover what the alternatives consume), the TVP constraint reads
+// NP0_TVP_constraint <= 0. Both multipliers must be non-negative.
+//
+// NP0 is linear in drm_sl, so the orders of a material tend to end up with a
+// single supplier. Every drm_sl is a variable of its own; the lower bound of
+// `Bounds::drm_sl` keeps the orders of the other suppliers at zero.
+#[derive(Clone)]
+pub struct MRGameConstraints {
+    pub bom_active: MaterialMap<bool>,
+    pub TVP_active: bool,
+}
 
-#[derive(Copy, Clone)]
-pub struct MRGameConstraints {}
+// A_g is only a decision for the products in the portfolio, rho_g only for
+// those that some retailer sells as well; for the others the variables have no
+// effect.
+fn decided_products(input: &Input) -> Vec<Product> {
+    let decision = &input.mrgame.decision;
+    input
+        .relation
+        .all_products()
+        .filter(|g| decision.product[*g])
+        .collect()
+}
+
+fn priced_products(input: &Input) -> Vec<Product> {
+    let relation = input.relation;
+    decided_products(input)
+        .into_iter()
+        .filter(|g| !relation.retailers(*g).is_empty())
+        .collect()
+}
 
 #[derive(Clone)]
 pub struct MRGameLambdas {
-    // bom_l: MaterialMap<f64>,
-// TVP: f64,
+    pub bom_l: MaterialMap<f64>,
+    pub TVP: f64,
+}
+
+#[derive(Clone)]
+pub struct MRGameSolution {
+    pub parameter: mrgame::Parameter,
+    pub lambdas: MRGameLambdas,
+    pub active_bounds: Vec<ActiveBound>,
+    pub second_order: SecondOrder,
+    pub bom_constraint_l: MaterialMap<f64>,
+    pub TVP_constraint: f64,
+    pub NP0: f64,
+}
+
+#[derive(Clone)]
+pub struct MRGameAttempt {
+    pub constraints: MRGameConstraints,
+    pub outcome: SolveOutcome,
+    /// `None` when the retailers' response was singular.
+    pub newton: Option<NewtonStats>,
+    /// `None` when Newton's method did not converge.
+    pub solution: Option<MRGameSolution>,
+}
+
+#[derive(Clone)]
+pub struct MRGameReport {
+    /// `None` when the starting point is infeasible.
+    pub start_NP0: Option<f64>,
+    pub attempts: Vec<MRGameAttempt>,
+    /// The best feasible solution, `None` when none beats the starting point.
+    pub parameter: Option<mrgame::Parameter>,
+}
+
+pub struct MRGameReportDisplay<'a> {
+    report: &'a MRGameReport,
+    relation: &'a Relation,
 }
 
 impl MRGameConstraints {
-    fn print(&self, relation: &Relation, lambdas: &MRGameLambdas) {
-        // println!("BOM_l");
-        // for l in relation.all_materials() {
-        //     print!("{}\t", lambdas.bom_l[l]);
-        // }
-        // println!("");
+    pub fn new(relation: &Relation) -> Self {
+        Self {
+            bom_active: MaterialMap::new(relation, false),
+            TVP_active: false,
+        }
+    }
+
+    /// The `index`-th combination of active constraints, bit l for the BOM
+    /// constraint of material l and the last bit for TVP.
+    pub fn combination(relation: &Relation, index: usize) -> Self {
+        let mut result = Self::new(relation);
+        let mut bit = 0;
+        for l in relation.all_materials() {
+            result.bom_active[l] = index & (1 << bit) != 0;
+            bit += 1;
+        }
+        result.TVP_active = index & (1 << bit) != 0;
+        result
+    }
+
+    pub fn combination_count(relation: &Relation) -> usize {
+        1 << (relation.all_materials().count() + 1)
+    }
+
+    fn variable_count(&self, input: &Input) -> usize {
+        mrgame_variables(input).len()
+    }
+
+    fn array_len(&self, input: &Input) -> usize {
+        let relation = input.relation;
+        let mut result = self.variable_count(input);
+
+        for l in relation.all_materials() {
+            if self.bom_active[l] {
+                result += 1;
+            }
+        }
+
+        if self.TVP_active {
+            result += 1;
+        }
+
+        result
+    }
+
+    fn append_lambdas(
+        &self,
+        relation: &Relation,
+        array: &mut Array1<f64>,
+        index: usize,
+        lambdas: &MRGameLambdas,
+    ) {
+        let mut index = index;
+        for l in relation.all_materials() {
+            if self.bom_active[l] {
+                array[index] = lambdas.bom_l[l];
+                index += 1;
+            }
+        }
+
+        if self.TVP_active {
+            array[index] = lambdas.TVP;
+        }
+    }
+
+    fn get_lambdas(&self, relation: &Relation, array: &Array1<f64>, index: usize) -> MRGameLambdas {
+        let mut index = index;
+        let mut lambdas = MRGameLambdas {
+            bom_l: MaterialMap::new(relation, 0.0),
+            TVP: 0.0,
+        };
+
+        for l in relation.all_materials() {
+            if self.bom_active[l] {
+                lambdas.bom_l[l] = array[index];
+                index += 1;
+            }
+        }
+
+        if self.TVP_active {
+            lambdas.TVP = array[index];
+        }
+
+        lambdas
+    }
+
+    fn append_constraints(&self, evaluation: &Evaluation, array: &mut Array1<f64>, index: usize) {
+        let relation = evaluation.input().relation;
+
+        let mut index = index;
+        for l in relation.all_materials() {
+            if self.bom_active[l] {
+                array[index] = evaluation.NP0_bom_constraint(l);
+                index += 1;
+            }
+        }
+
+        if self.TVP_active {
+            array[index] = evaluation.NP0_TVP_constraint();
+        }
+    }
+
+    fn negative_multiplier(&self, relation: &Relation, lambdas: &MRGameLambdas) -> bool {
+        for l in relation.all_materials() {
+            if self.bom_active[l] && lambdas.bom_l[l] < 0.0 {
+                return true;
+            }
+        }
+
+        self.TVP_active && lambdas.TVP < 0.0
     }
 }
 
 impl MRGameLambdas {
     pub fn new(relation: &Relation) -> Self {
         Self {
-            // bom_l: MaterialMap::new(relation, 1.0),
-            // TVP: 2.0,
+            bom_l: MaterialMap::new(relation, 1.0),
+            TVP: 2.0,
         }
     }
 }
 
+/// Every variable the manufacturer decides, `drm_sl` of every pair of
+/// `Relation::supplier_materials`.
+pub fn mrgame_variables(input: &Input) -> Vec<Variable> {
+    let relation = input.relation;
+
+    let mut result = Vec::new();
+    for g in decided_products(input) {
+        result.push(Variable::A_g(g));
+    }
+    for g in priced_products(input) {
+        result.push(Variable::rho_g(g));
+    }
+    for m in relation.initial_retailers() {
+        result.push(Variable::c_m(m));
+    }
+    for s in relation.all_suppliers() {
+        result.push(Variable::crm_s(s));
+    }
+    for (s, l) in relation.supplier_materials().iter() {
+        result.push(Variable::drm_sl(*s, *l));
+    }
+    result
+}
+
 pub fn mrgame_to_array(
     input: &Input,
-    constraints: MRGameConstraints,
+    constraints: &MRGameConstraints,
     lambdas: &MRGameLambdas,
 ) -> Array1<f64> {
     let relation = &input.relation;
     let A_g = &input.mrgame.parameter.A_g;
+    let rho_g = &input.mrgame.parameter.rho_g;
     let c_m = &input.mrgame.parameter.c_m;
     let crm_s = &input.mrgame.parameter.crm_s;
+    let drm_sl = &input.mrgame.parameter.drm_sl;
 
-    let len = relation.all_products().count()
-        + relation.initial_retailers().count()
-        + relation.all_suppliers().count();
-
-    let mut result = Array1::zeros(len);
+    let mut result = Array1::zeros(constraints.array_len(input));
 
     let mut index = 0;
-    for g in relation.all_products() {
+    for g in decided_products(input) {
         result[index] = A_g[g];
         index += 1;
     }
 
+    for g in priced_products(input) {
+        result[index] = rho_g[g];
+        index += 1;
+    }
+
     for m in relation.initial_retailers() {
         result[index] = c_m[m];
         index += 1;
@@ -65,25 +275,36 @@ pub fn mrgame_to_array(
         index += 1;
     }
 
+    for (s, l) in relation.supplier_materials().iter() {
+        result[index] = drm_sl[*s][*l];
+        index += 1;
+    }
+
+    constraints.append_lambdas(relation, &mut result, index, lambdas);
+
     result
 }
 
 pub fn mrgame_array_to_parameter(
     input: &Input,
     array: &Array1<f64>,
-    constraints: MRGameConstraints,
+    constraints: &MRGameConstraints,
 ) -> (mrgame::Parameter, MRGameLambdas) {
     let relation = &input.relation;
 
-    let mut lambdas = MRGameLambdas::new(relation);
     let mut parameter = input.mrgame.parameter.clone();
 
     let mut index = 0;
-    for g in relation.all_products() {
+    for g in decided_products(input) {
         parameter.A_g[g] = array[index];
         index += 1;
     }
 
+    for g in priced_products(input) {
+        parameter.rho_g[g] = array[index];
+        index += 1;
+    }
+
     for m in relation.initial_retailers() {
         parameter.c_m[m] = array[index];
         index += 1;
@@ -94,27 +315,73 @@ pub fn mrgame_array_to_parameter(
         index += 1;
     }
 
-    (parameter, lambdas)
+    for (s, l) in relation.supplier_materials().iter() {
+        parameter.drm_sl[*s][*l] = array[index];
+        index += 1;
+    }
+
+    (parameter, constraints.get_lambdas(relation, array, index))
+}
+
+/// NP0 with the active constraints weighted by their multipliers, whose
+/// derivatives are the stationarity conditions.
+fn mrgame_lagrangian<T: Scalar>(
+    input: &DualInput,
+    constraints: &MRGameConstraints,
+    lambdas: &MRGameLambdas,
+) -> T {
+    let relation = input.relation;
+
+    let mut result: T = comp_generic::NP0(input);
+    for l in relation.all_materials() {
+        if constraints.bom_active[l] {
+            result += comp_generic::NP0_bom_constraint::<T>(input, l) * lambdas.bom_l[l];
+        }
+    }
+    if constraints.TVP_active {
+        result -= comp_generic::NP0_TVP_constraint::<T>(input) * lambdas.TVP;
+    }
+    result
+}
+
+// The retailers' prices and advertising follow rho_j along the response, so
+// the constraints depend on rho_j through them.
+fn mrgame_rho_f(
+    evaluation: &Evaluation,
+    lambdas: &MRGameLambdas,
+    constraints: &MRGameConstraints,
+    response: &RhoResponse,
+) -> Vec<f64> {
+    let lagrangian = |x: &DualInput| mrgame_lagrangian::<Dual>(x, constraints, lambdas);
+
+    let mut result = Vec::new();
+    for j in priced_products(evaluation.input()) {
+        result.push(response.derivative(evaluation, j, lagrangian));
+    }
+
+    result
 }
 
 pub fn mrgame_f(
     old_input: &Input,
     array: &Array1<f64>,
-    constraints: MRGameConstraints,
+    constraints: &MRGameConstraints,
+    response: &RhoResponse,
 ) -> Array1<f64> {
     let (parameter, lambdas) = mrgame_array_to_parameter(old_input, array, constraints);
 
     let decision = old_input.mrgame.decision.clone();
 
     let mrgame = mrgame::MRGame {
-        parameter: parameter,
-        decision: decision,
+        parameter,
+        decision,
     };
 
     let input = Input {
         mrgame: &mrgame,
         ..(*old_input)
     };
+    let evaluation = Evaluation::new(&input);
 
     let relation = input.relation;
 
@@ -122,40 +389,597 @@ pub fn mrgame_f(
     let mut result = Array1::zeros(len);
 
     let mut index = 0;
-    for j in relation.all_products() {
-        result[index] = computation::dA_NP0(&input, j);
+    for j in decided_products(&input) {
+        result[index] = computation::dA_NP0(&evaluation, j);
+        for l in relation.all_materials() {
+            if constraints.bom_active[l] {
+                result[index] += lambdas.bom_l[l] * dA_NP0_bom_constraint(&input, l, j);
+            }
+        }
+        if constraints.TVP_active {
+            result[index] -= lambdas.TVP * dA_NP0_TVP_constraint(&input, j);
+        }
+        index += 1;
+    }
+
+    for value in mrgame_rho_f(&evaluation, &lambdas, constraints, response) {
+        result[index] = value;
         index += 1;
     }
 
     for m in relation.initial_retailers() {
-        result[index] = computation::dc_NP0(&input, m);
+        result[index] = computation::dc_NP0(&evaluation, m);
+        if constraints.TVP_active {
+            result[index] -= lambdas.TVP * dc_NP0_TVP_constraint(&evaluation, m);
+        }
         index += 1;
     }
 
     for s in relation.all_suppliers() {
         result[index] = computation::dcrm_NP0(&input, s);
+        if constraints.TVP_active {
+            result[index] -= lambdas.TVP * dcrm_NP0_TVP_constraint(&input, s);
+        }
+        index += 1;
+    }
+
+    for &(s, l) in relation.supplier_materials().iter() {
+        result[index] = computation::ddrm_NP0(&input, s, l);
+        if constraints.bom_active[l] {
+            result[index] += lambdas.bom_l[l] * ddrm_NP0_bom_constraint(&input, s, l);
+        }
+        if constraints.TVP_active {
+            result[index] -= lambdas.TVP * ddrm_NP0_TVP_constraint(&input, s, l);
+        }
         index += 1;
     }
 
+    constraints.append_constraints(&evaluation, &mut result, index);
+
+    result
+}
+
+/// The Jacobian of `mrgame_f` with respect to `array`. The rho rows are
+/// second derivatives along the retailers' response, see `RhoResponse`.
+pub fn mrgame_jacobian(
+    old_input: &Input,
+    array: &Array1<f64>,
+    constraints: &MRGameConstraints,
+    response: &RhoResponse,
+) -> Array2<f64> {
+    let (parameter, lambdas) = mrgame_array_to_parameter(old_input, array, constraints);
+
+    let mrgame = mrgame::MRGame {
+        parameter,
+        decision: old_input.mrgame.decision.clone(),
+    };
+
+    let input = Input {
+        mrgame: &mrgame,
+        ..(*old_input)
+    };
+    let evaluation = Evaluation::new(&input);
+
+    let relation = input.relation;
+
+    let len = array.len();
+    let mut result = Array2::zeros((len, len));
+
+    let decided = decided_products(&input);
+    let priced = priced_products(&input);
+    let ordered = relation.supplier_materials();
+
+    let rho_index = decided.len();
+    let c_index = rho_index + priced.len();
+    let crm_index = c_index + relation.initial_retailers().count();
+    let drm_index = crm_index + relation.all_suppliers().count();
+
+    let mut bom_index = MaterialMap::new(relation, None);
+    let mut index = constraints.variable_count(&input);
+    for l in relation.all_materials() {
+        if constraints.bom_active[l] {
+            bom_index[l] = Some(index);
+            index += 1;
+        }
+    }
+    let TVP_index = index;
+
+    for (row, j) in decided.iter().enumerate() {
+        for (col, k) in decided.iter().enumerate() {
+            result[(row, col)] = computation::dAdA_NP0(&evaluation, *j, *k);
+            for l in relation.all_materials() {
+                if constraints.bom_active[l] {
+                    result[(row, col)] +=
+                        lambdas.bom_l[l] * dAdA_NP0_bom_constraint(&input, l, *j, *k);
+                }
+            }
+            if constraints.TVP_active {
+                result[(row, col)] -= lambdas.TVP * dAdA_NP0_TVP_constraint(&input, *j, *k);
+            }
+        }
+
+        for (col, k) in priced.iter().enumerate() {
+            result[(row, rho_index + col)] = computation::drhodA_NP0(&evaluation, *j, *k);
+        }
+
+        for (col, m) in relation.initial_retailers().enumerate() {
+            result[(row, c_index + col)] = computation::dcdA_NP0(&input, m, *j);
+            if constraints.TVP_active {
+                result[(row, c_index + col)] -=
+                    lambdas.TVP * dcdA_NP0_TVP_constraint(&input, m, *j);
+            }
+        }
+
+        for l in relation.all_materials() {
+            if let Some(index) = bom_index[l] {
+                result[(row, index)] = dA_NP0_bom_constraint(&input, l, *j);
+            }
+        }
+        if constraints.TVP_active {
+            result[(row, TVP_index)] = -dA_NP0_TVP_constraint(&input, *j);
+        }
+    }
+
+    let variables = mrgame_variables(&input);
+    let lagrangian = |x: &DualInput| mrgame_lagrangian::<HyperDual>(x, constraints, &lambdas);
+    for (row, j) in priced.iter().enumerate() {
+        let row = rho_index + row;
+        for (col, y) in variables.iter().enumerate() {
+            result[(row, col)] = response.second_derivative(&evaluation, *j, *y, lagrangian);
+        }
+        for l in relation.all_materials() {
+            if let Some(index) = bom_index[l] {
+                result[(row, index)] = response
+                    .derivative(&evaluation, *j, |x| comp_generic::NP0_bom_constraint(x, l));
+            }
+        }
+        if constraints.TVP_active {
+            result[(row, TVP_index)] =
+                -response.derivative(&evaluation, *j, comp_generic::NP0_TVP_constraint);
+        }
+    }
+
+    for (row, m) in relation.initial_retailers().enumerate() {
+        let row = c_index + row;
+        for (col, k) in decided.iter().enumerate() {
+            result[(row, col)] = computation::dcdA_NP0(&input, m, *k);
+            if constraints.TVP_active {
+                result[(row, col)] -= lambdas.TVP * dcdA_NP0_TVP_constraint(&input, m, *k);
+            }
+        }
+        result[(row, row)] = computation::dcdc_NP0(&input, m);
+        if constraints.TVP_active {
+            result[(row, TVP_index)] = -dc_NP0_TVP_constraint(&evaluation, m);
+        }
+    }
+
+    for (row, s) in relation.all_suppliers().enumerate() {
+        let row = crm_index + row;
+        result[(row, row)] = computation::dcrmdcrm_NP0(&input, s);
+        for (col, (source, l)) in ordered.iter().enumerate() {
+            if source.id == s.id {
+                result[(row, drm_index + col)] = computation::dcrmddrm_NP0(&input, s, *l);
+                if constraints.TVP_active {
+                    result[(row, drm_index + col)] -=
+                        lambdas.TVP * dcrmddrm_NP0_TVP_constraint(&input, s, *l);
+                }
+            }
+        }
+        if constraints.TVP_active {
+            result[(row, TVP_index)] = -dcrm_NP0_TVP_constraint(&input, s);
+        }
+    }
+
+    for (row, (s, l)) in ordered.iter().enumerate() {
+        let row = drm_index + row;
+        let col = crm_index + s.id;
+        result[(row, col)] = computation::dcrmddrm_NP0(&input, *s, *l);
+        if constraints.TVP_active {
+            result[(row, col)] -= lambdas.TVP * dcrmddrm_NP0_TVP_constraint(&input, *s, *l);
+        }
+        if let Some(index) = bom_index[*l] {
+            result[(row, index)] = ddrm_NP0_bom_constraint(&input, *s, *l);
+        }
+        if constraints.TVP_active {
+            result[(row, TVP_index)] = -ddrm_NP0_TVP_constraint(&input, *s, *l);
+        }
+    }
+
+    for l in relation.all_materials() {
+        if let Some(row) = bom_index[l] {
+            for (col, k) in decided.iter().enumerate() {
+                result[(row, col)] = dA_NP0_bom_constraint(&input, l, *k);
+            }
+            for (col, (s, m)) in ordered.iter().enumerate() {
+                if m.id == l.id {
+                    result[(row, drm_index + col)] = ddrm_NP0_bom_constraint(&input, *s, l);
+                }
+            }
+        }
+    }
+
+    if constraints.TVP_active {
+        let row = TVP_index;
+        for (col, k) in decided.iter().enumerate() {
+            result[(row, col)] = dA_NP0_TVP_constraint(&input, *k);
+        }
+        for (col, m) in relation.initial_retailers().enumerate() {
+            result[(row, c_index + col)] = dc_NP0_TVP_constraint(&evaluation, m);
+        }
+        for (col, s) in relation.all_suppliers().enumerate() {
+            result[(row, crm_index + col)] = dcrm_NP0_TVP_constraint(&input, s);
+        }
+        for (col, (s, l)) in ordered.iter().enumerate() {
+            result[(row, drm_index + col)] = ddrm_NP0_TVP_constraint(&input, *s, *l);
+        }
+    }
+
     result
 }
 
 pub fn mrgame_solve_constraints(
     input: &Input,
-    constraints: MRGameConstraints,
-) -> Option<mrgame::Parameter> {
-    let f = |a: &Array1<f64>| mrgame_f(input, a, constraints);
-    let lambdas = MRGameLambdas::new(input.relation);
-    let x0 = mrgame_to_array(input, constraints, &lambdas);
-    let len = x0.len();
+    constraints: &MRGameConstraints,
+    response: &RhoResponse,
+) -> (NewtonResult, Vec<Option<BoundSide>>) {
+    let relation = input.relation;
+    let f = |a: &Array1<f64>| mrgame_f(input, a, constraints, response);
+    let jacobian = |a: &Array1<f64>| mrgame_jacobian(input, a, constraints, response);
+    let lambdas = MRGameLambdas::new(relation);
+    let variables = mrgame_variables(input);
+    let bounds: Vec<Bound> = variables
+        .iter()
+        .map(|x| input.constant.bounds.bound(*x))
+        .collect();
+
+    // NP0 is linear in drm_sl: with orders of a material at several suppliers
+    // the Jacobian is singular unless the TVP constraint tells them apart.
+    // Each material's orders start at the supplier with the highest
+    // ddrm_NP0, the others at their lower bound, where the bounded solver
+    // holds them until their multiplier turns negative.
+    let mut x0 = mrgame_to_array(input, constraints, &lambdas);
+    let drm_sl = &input.mrgame.parameter.drm_sl;
+    for l in relation.all_materials() {
+        let suppliers = relation.suppliers_for_material(l);
+        let cheapest = suppliers.iter().copied().max_by(|a, b| {
+            let a = computation::ddrm_NP0(input, *a, l);
+            let b = computation::ddrm_NP0(input, *b, l);
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        });
+        let total: f64 = suppliers.iter().map(|s| drm_sl[*s][l]).sum();
+        for (i, x) in variables.iter().enumerate() {
+            if let Variable::drm_sl(s, m) = *x {
+                if m == l {
+                    x0[i] = if Some(s) == cheapest {
+                        bounds[i].clamp(total)
+                    } else {
+                        bounds[i].lower
+                    };
+                }
+            }
+        }
+    }
+
+    newton::bounded_newton_solve(&f, &jacobian, &x0, &bounds, &NewtonOptions::default())
+}
+
+pub fn mrgame_feasible(input: &Input) -> bool {
+    let epsilon = 0.000001;
+    let evaluation = Evaluation::new(input);
+    let bom_ok = input
+        .relation
+        .all_materials()
+        .all(|l| evaluation.NP0_bom_constraint(l) >= -epsilon);
+    let drm_ok = input
+        .relation
+        .supplier_materials()
+        .iter()
+        .all(|(s, l)| input.mrgame.parameter.drm_sl[*s][*l] >= -epsilon);
+    bom_ok && drm_ok && evaluation.NP0_TVP_constraint() <= epsilon
+}
+
+fn mrgame_try_constraint(
+    report: &mut MRGameReport,
+    input: &Input,
+    profit: &mut f64,
+    constraints: &MRGameConstraints,
+    response: &RhoResponse,
+) {
+    let relation = input.relation;
+
+    let (result, held) = mrgame_solve_constraints(input, constraints, response);
+    let newton = *result.stats();
+    if let Some(outcome) = newton_outcome(&result) {
+        report.attempts.push(MRGameAttempt {
+            constraints: constraints.clone(),
+            outcome,
+            newton: Some(newton),
+            solution: None,
+        });
+        return;
+    }
+
+    let (parameter, lambdas) = mrgame_array_to_parameter(input, result.x(), constraints);
+    let active_bounds = ActiveBound::collect(
+        &mrgame_variables(input),
+        &held,
+        mrgame_f(input, result.x(), constraints, response)
+            .as_slice()
+            .unwrap(),
+    );
+    let jacobian = mrgame_jacobian(input, result.x(), constraints, response);
+    let second_order = second_order::second_order(&jacobian, &held);
+
+    let mrgame = mrgame::MRGame {
+        parameter,
+        decision: input.mrgame.decision.clone(),
+    };
+
+    let (feasible, bom_constraint_l, TVP_constraint, NP0) = {
+        let new_input = Input {
+            mrgame: &mrgame,
+            ..(*input)
+        };
+
+        let evaluation = Evaluation::new(&new_input);
+
+        let mut bom_constraint_l = MaterialMap::new(relation, 0.0);
+        for l in relation.all_materials() {
+            bom_constraint_l[l] = evaluation.NP0_bom_constraint(l);
+        }
+
+        (
+            mrgame_feasible(&new_input),
+            bom_constraint_l,
+            evaluation.NP0_TVP_constraint(),
+            evaluation.NP0(),
+        )
+    };
+
+    let outcome = if constraints.negative_multiplier(relation, &lambdas) {
+        SolveOutcome::NegativeMultiplier
+    } else if !feasible {
+        SolveOutcome::Infeasible
+    } else if second_order.curvature != Curvature::Maximum {
+        SolveOutcome::NotMaximum
+    } else if NP0 <= *profit {
+        SolveOutcome::NotImproving
+    } else {
+        SolveOutcome::Accepted
+    };
+
+    if outcome == SolveOutcome::Accepted {
+        *profit = NP0;
+        report.parameter = Some(mrgame.parameter.clone());
+    }
+
+    report.attempts.push(MRGameAttempt {
+        constraints: constraints.clone(),
+        outcome,
+        newton: Some(newton),
+        solution: Some(MRGameSolution {
+            parameter: mrgame.parameter,
+            lambdas,
+            active_bounds,
+            second_order,
+            bom_constraint_l,
+            TVP_constraint,
+            NP0,
+        }),
+    });
+}
+
+/// Tries every combination of active BOM and TVP constraints and keeps the
+/// feasible solution with the highest NP0. The starting point only competes
+/// when it is itself feasible.
+pub fn mrgame_solve(input: &Input) -> MRGameReport {
+    let relation = input.relation;
+
+    let start_NP0 = if mrgame_feasible(input) {
+        Some(computation::NP0(input))
+    } else {
+        None
+    };
+    let mut profit = start_NP0.unwrap_or(f64::NEG_INFINITY);
+    let mut report = MRGameReport {
+        start_NP0,
+        attempts: Vec::new(),
+        parameter: None,
+    };
+
+    let response = RhoResponse::new(input);
+
+    for index in 0..MRGameConstraints::combination_count(relation) {
+        let constraints = MRGameConstraints::combination(relation, index);
+
+        match &response {
+            Some(response) => {
+                mrgame_try_constraint(&mut report, input, &mut profit, &constraints, response)
+            }
+            None => report.attempts.push(MRGameAttempt {
+                constraints,
+                outcome: SolveOutcome::SingularResponse,
+                newton: None,
+                solution: None,
+            }),
+        }
+    }
+
+    report
+}
+
+impl MRGameReport {
+    /// Whether some attempt found a feasible local maximum: `parameter` when
+    /// it beats the starting point, otherwise the starting point is the
+    /// manufacturer's best response.
+    pub fn responded(&self) -> bool {
+        self.parameter.is_some()
+            || self
+                .attempts
+                .iter()
+                .any(|attempt| attempt.outcome == SolveOutcome::NotImproving)
+    }
+
+    /// Formats the report; the material-indexed fields need `relation`.
+    pub fn display<'a>(&'a self, relation: &'a Relation) -> MRGameReportDisplay<'a> {
+        MRGameReportDisplay {
+            report: self,
+            relation,
+        }
+    }
+}
+
+impl<'a> fmt::Display for MRGameReportDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let relation = self.relation;
+
+        match self.report.start_NP0 {
+            Some(NP0) => writeln!(f, "MR game: start NP0 = {}", NP0)?,
+            None => writeln!(f, "MR game: infeasible start")?,
+        }
 
-    let arr: Vec<f64> = (0..len).map(|_| 0.000001).collect();
-    let dx0 = arr1(&arr);
-    println!("{}", x0);
+        for attempt in self.report.attempts.iter() {
+            let constraints = &attempt.constraints;
+
+            write!(f, "BOM")?;
+            for l in relation.all_materials() {
+                let state = if constraints.bom_active[l] {
+                    "on"
+                } else {
+                    "off"
+                };
+                write!(f, " {}", state)?;
+            }
+            write!(
+                f,
+                ", TVP {}: {:?}",
+                if constraints.TVP_active { "on" } else { "off" },
+                attempt.outcome
+            )?;
+
+            if let Some(newton) = &attempt.newton {
+                write!(
+                    f,
+                    "\titerations: {}\t|f|: {}",
+                    newton.iterations, newton.f_norm
+                )?;
+            }
+            if let Some(solution) = &attempt.solution {
+                write!(f, "\tNP0: {}", solution.NP0)?;
+                for l in relation.all_materials() {
+                    let material = relation.material_label(l);
+                    write!(f, "\tBOM {}: {}", material, solution.bom_constraint_l[l])?;
+                    if constraints.bom_active[l] {
+                        write!(f, " (lambda {})", solution.lambdas.bom_l[l])?;
+                    }
+                }
+                write!(f, "\tTVP: {}", solution.TVP_constraint)?;
+                if constraints.TVP_active {
+                    write!(f, " (lambda {})", solution.lambdas.TVP)?;
+                }
+                for bound in solution.active_bounds.iter() {
+                    write!(f, "\t{}: {}", bound.label(relation), bound.multiplier)?;
+                }
+                write!(f, "\t{}", solution.second_order)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
 
-    let x = newton::newton_method(&f, &x0, &dx0, 0.2, 20)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relation::Material;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn test_lambdas_round_trip() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        assert_eq!(MRGameConstraints::combination_count(&relation), 16);
+        let constraints = MRGameConstraints::combination(&relation, 0b1010);
+        assert!(!constraints.bom_active[Material { id: 0 }]);
+        assert!(constraints.bom_active[Material { id: 1 }]);
+        assert!(!constraints.bom_active[Material { id: 2 }]);
+        assert!(constraints.TVP_active);
+
+        let mut lambdas = MRGameLambdas::new(&relation);
+        lambdas.bom_l[Material { id: 1 }] = 3.0;
+        lambdas.TVP = 4.0;
+        let array = mrgame_to_array(&input, &constraints, &lambdas);
+        assert_eq!(array.len(), 4 + 4 + 2 + 3 + 7 + 2);
+
+        let (parameter, new_lambdas) = mrgame_array_to_parameter(&input, &array, &constraints);
+        for (s, l) in relation.supplier_materials().iter() {
+            assert_eq!(parameter.drm_sl[*s][*l], mrgame.parameter.drm_sl[*s][*l]);
+        }
+        assert_eq!(new_lambdas.bom_l[Material { id: 0 }], 0.0);
+        assert_eq!(new_lambdas.bom_l[Material { id: 1 }], 3.0);
+        assert_eq!(new_lambdas.TVP, 4.0);
+    }
 
-    let (parameter, lambdas) = mrgame_array_to_parameter(input, &x, constraints);
+    #[test]
+    fn test_drm_derivatives() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        for (s, l) in relation.supplier_materials().iter() {
+            let exact = ddrm_NP0_bom_constraint(&input, *s, *l);
+            let approx = computation::ddrm_NP0_bom_constraint_approx(&input, *s, *l);
+            assert!((exact - approx).abs() < 1e-6);
+
+            let exact = ddrm_NP0_TVP_constraint(&input, *s, *l);
+            let approx = computation::ddrm_NP0_TVP_constraint_approx(&input, *s, *l);
+            assert!((exact - approx).abs() < 1e-4 * exact.abs().max(1.0));
+        }
+    }
 
-    Some(parameter)
+    #[test]
+    fn test_mrgame_jacobian() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let response = RhoResponse::new(&input).unwrap();
+
+        let mut lambdas = MRGameLambdas::new(&relation);
+        lambdas.bom_l[Material { id: 1 }] = 3.0;
+        for index in [0, 0b0101, 0b1010, 0b1111].iter() {
+            let constraints = MRGameConstraints::combination(&relation, *index);
+            let x = mrgame_to_array(&input, &constraints, &lambdas);
+            let f = |a: &Array1<f64>| mrgame_f(&input, a, &constraints, &response);
+            let dx0 = Array1::from(vec![0.000001; x.len()]);
+
+            let exact = mrgame_jacobian(&input, &x, &constraints, &response);
+            let approx = newton::jacobi(&f, &x, &dx0);
+            for ((row, col), e) in exact.indexed_iter() {
+                let a = approx[(row, col)];
+                assert!(
+                    (e - a).abs() <= 0.001 * e.abs().max(1.0),
+                    "{:?}",
+                    (row, col)
+                );
+            }
+        }
+    }
 }
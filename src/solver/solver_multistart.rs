@@ -0,0 +1,412 @@
+use super::{mrgame_solve, mrgame_variables, rrgame_respond, rrgame_variables};
+use super::{RRGameMethod, SolveOutcome};
+use crate::bounds::Bound;
+use crate::computation::comp_generic::Variable;
+use crate::computation::Input;
+use crate::mrgame;
+use crate::parallel::par_map;
+use crate::relation::{Relation, Retailer};
+use crate::rrgame;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Multi-start search: the local solvers only find the optimum whose basin
+// holds the current parameter, so the search runs them again from points
+// drawn within the variable bounds. Start 0 is the current parameter itself.
+// The optima found are clustered, as many starts end at the same one.
+
+#[derive(Debug, Clone)]
+pub struct MultiStartOptions {
+    /// Number of starts, including the current parameter.
+    pub starts: usize,
+    pub seed: u64,
+    /// A variable with an infinite bound is drawn within `spread` times
+    /// `max(|x|, 1)` of its current value `x` on that side.
+    pub spread: f64,
+    /// Two optima are the same when no variable differs by more than this
+    /// share of `max(|x|, |y|, 1)`.
+    pub tolerance: f64,
+    pub method: RRGameMethod,
+}
+
+#[derive(Clone)]
+pub struct Optimum<P> {
+    pub parameter: P,
+    /// The values of `MultiStart::variables`.
+    pub values: Vec<f64>,
+    /// NP of the retailer, or NP0 of the manufacturer.
+    pub objective: f64,
+    /// The starts whose local solve ended here.
+    pub starts: Vec<usize>,
+}
+
+#[derive(Clone)]
+pub struct MultiStart<P> {
+    pub variables: Vec<Variable>,
+    /// The distinct optima, best first.
+    pub optima: Vec<Optimum<P>>,
+    /// The starts whose local solve found no solution.
+    pub failed: Vec<usize>,
+}
+
+impl Default for MultiStartOptions {
+    fn default() -> Self {
+        Self {
+            starts: 8,
+            seed: 0,
+            spread: 1.0,
+            tolerance: 0.0001,
+            method: RRGameMethod::ActiveSet,
+        }
+    }
+}
+
+/// A value drawn uniformly from `bound`, with an infinite side replaced by
+/// `current` moved by `spread` times `max(|current|, 1)`.
+fn sample(rng: &mut StdRng, bound: &Bound, current: f64, spread: f64) -> f64 {
+    let reach = spread * current.abs().max(1.0);
+    let lower = if bound.lower.is_finite() {
+        bound.lower
+    } else {
+        current - reach
+    };
+    let upper = if bound.upper.is_finite() {
+        bound.upper
+    } else {
+        current + reach
+    };
+
+    if lower < upper {
+        rng.gen_range(lower, upper)
+    } else {
+        bound.clamp(current)
+    }
+}
+
+fn rrgame_value(parameter: &mut rrgame::Parameter, variable: Variable) -> &mut f64 {
+    match variable {
+        Variable::p_mg(m, g) => &mut parameter.p_mg[m][g],
+        Variable::a_mg(m, g) => &mut parameter.a_mg[m][g],
+        _ => unreachable!("{:?} is not a retailer variable", variable),
+    }
+}
+
+fn mrgame_value(parameter: &mut mrgame::Parameter, variable: Variable) -> &mut f64 {
+    match variable {
+        Variable::A_g(g) => &mut parameter.A_g[g],
+        Variable::rho_g(g) => &mut parameter.rho_g[g],
+        Variable::c_m(m) => &mut parameter.c_m[m],
+        Variable::crm_s(s) => &mut parameter.crm_s[s],
+        Variable::drm_sl(s, l) => &mut parameter.drm_sl[s][l],
+        _ => unreachable!("{:?} is not a manufacturer variable", variable),
+    }
+}
+
+/// `options.starts` starting points: `current` itself, then draws of
+/// `variables` within their bounds.
+fn starting_points<P: Clone>(
+    input: &Input,
+    current: &P,
+    variables: &[Variable],
+    options: &MultiStartOptions,
+    value: impl Fn(&mut P, Variable) -> &mut f64,
+) -> Vec<P> {
+    let bounds = &input.constant.bounds;
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    let mut result = Vec::new();
+    for start in 0..options.starts {
+        let mut parameter = current.clone();
+        if start > 0 {
+            for &variable in variables.iter() {
+                let x = value(&mut parameter, variable);
+                *x = sample(&mut rng, &bounds.bound(variable), *x, options.spread);
+            }
+        }
+        result.push(parameter);
+    }
+    result
+}
+
+fn same_optimum(x: &[f64], y: &[f64], tolerance: f64) -> bool {
+    x.iter().zip(y.iter()).all(|(a, b)| {
+        let scale = a.abs().max(b.abs()).max(1.0);
+        (a - b).abs() <= tolerance * scale
+    })
+}
+
+/// Groups the local solutions, `None` for a failed start, into optima.
+fn cluster<P>(
+    variables: Vec<Variable>,
+    solutions: Vec<Option<(P, Vec<f64>, f64)>>,
+    tolerance: f64,
+) -> MultiStart<P> {
+    let mut optima: Vec<Optimum<P>> = Vec::new();
+    let mut failed = Vec::new();
+
+    for (start, solution) in solutions.into_iter().enumerate() {
+        let (parameter, values, objective) = match solution {
+            Some(solution) => solution,
+            None => {
+                failed.push(start);
+                continue;
+            }
+        };
+
+        let found = optima
+            .iter_mut()
+            .find(|optimum| same_optimum(&optimum.values, &values, tolerance));
+        match found {
+            Some(optimum) => {
+                optimum.starts.push(start);
+                if objective > optimum.objective {
+                    optimum.parameter = parameter;
+                    optimum.values = values;
+                    optimum.objective = objective;
+                }
+            }
+            None => optima.push(Optimum {
+                parameter,
+                values,
+                objective,
+                starts: vec![start],
+            }),
+        }
+    }
+
+    optima.sort_by(|a, b| b.objective.partial_cmp(&a.objective).unwrap());
+    MultiStart {
+        variables,
+        optima,
+        failed,
+    }
+}
+
+/// Runs the local solver of retailer `m` by `options.method` from
+/// `options.starts` points and clusters the solutions it accepts.
+pub fn rrgame_multistart_solve(
+    input: &Input,
+    m: Retailer,
+    options: &MultiStartOptions,
+) -> MultiStart<rrgame::Parameter> {
+    let variables = rrgame_variables(input, m);
+    let starts = starting_points(
+        input,
+        &input.rrgame.parameter,
+        &variables,
+        options,
+        rrgame_value,
+    );
+
+    let solutions = par_map(starts.len(), |start| {
+        let rrgame = rrgame::RRGame {
+            parameter: starts[start].clone(),
+        };
+        let start_input = Input {
+            rrgame: &rrgame,
+            ..(*input)
+        };
+        let report = rrgame_respond(&start_input, m, options.method);
+
+        let accepted = report
+            .attempts
+            .iter()
+            .rfind(|attempt| attempt.outcome == SolveOutcome::Accepted)?;
+        let solution = accepted.solution.as_ref().unwrap();
+        let mut parameter = solution.parameter.clone();
+        let values = variables
+            .iter()
+            .map(|x| *rrgame_value(&mut parameter, *x))
+            .collect();
+        Some((parameter, values, solution.NP))
+    });
+
+    cluster(variables, solutions, options.tolerance)
+}
+
+/// Runs `mrgame_solve` from `options.starts` points and clusters the
+/// solutions it accepts.
+pub fn mrgame_multistart_solve(
+    input: &Input,
+    options: &MultiStartOptions,
+) -> MultiStart<mrgame::Parameter> {
+    let variables = mrgame_variables(input);
+    let starts = starting_points(
+        input,
+        &input.mrgame.parameter,
+        &variables,
+        options,
+        mrgame_value,
+    );
+
+    let solutions = par_map(starts.len(), |start| {
+        let mrgame = mrgame::MRGame {
+            parameter: starts[start].clone(),
+            decision: input.mrgame.decision.clone(),
+        };
+        let start_input = Input {
+            mrgame: &mrgame,
+            ..(*input)
+        };
+        let report = mrgame_solve(&start_input);
+
+        let accepted = report
+            .attempts
+            .iter()
+            .rfind(|attempt| attempt.outcome == SolveOutcome::Accepted)?;
+        let solution = accepted.solution.as_ref().unwrap();
+        let mut parameter = solution.parameter.clone();
+        let values = variables
+            .iter()
+            .map(|x| *mrgame_value(&mut parameter, *x))
+            .collect();
+        Some((parameter, values, solution.NP0))
+    });
+
+    cluster(variables, solutions, options.tolerance)
+}
+
+impl<P> MultiStart<P> {
+    pub fn best(&self) -> Option<&Optimum<P>> {
+        self.optima.first()
+    }
+
+    /// Prints the best optimum with its variables, then a line for each of
+    /// the others.
+    pub fn show(&self, relation: &Relation) {
+        let starts: usize =
+            self.optima.iter().map(|x| x.starts.len()).sum::<usize>() + self.failed.len();
+        println!(
+            "Multi-start: {} starts, {} optima, {} failed",
+            starts,
+            self.optima.len(),
+            self.failed.len()
+        );
+
+        let best = match self.best() {
+            Some(best) => best,
+            None => return,
+        };
+        println!("best: {}\tstarts: {:?}", best.objective, best.starts);
+        for (variable, value) in self.variables.iter().zip(best.values.iter()) {
+            println!("\t{}: {}", variable.label(relation), value);
+        }
+
+        for (index, optimum) in self.optima.iter().enumerate().skip(1) {
+            let change = optimum
+                .values
+                .iter()
+                .zip(best.values.iter())
+                .fold(0.0, |acc: f64, (a, b)| acc.max((a - b).abs()));
+            println!(
+                "{}\t{}\tstarts: {:?}\tlargest difference to best: {}",
+                index, optimum.objective, optimum.starts, change
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+    use crate::second_order::Curvature;
+    use crate::solver::rrgame_solve;
+
+    #[test]
+    fn test_rrgame_multistart_solve() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let options = MultiStartOptions::default();
+
+        for m in relation.initial_retailers() {
+            let result = rrgame_multistart_solve(&input, m, &options);
+            let found: usize = result.optima.iter().map(|x| x.starts.len()).sum();
+            assert_eq!(found + result.failed.len(), options.starts);
+            for pair in result.optima.windows(2) {
+                assert!(pair[0].objective >= pair[1].objective);
+            }
+
+            // At least as good as the local solve from the current parameter.
+            let best = result.best().unwrap();
+            let local = rrgame_solve(&input, m);
+            for attempt in local.attempts.iter() {
+                if attempt.outcome == SolveOutcome::Accepted {
+                    assert!(best.objective >= attempt.solution.as_ref().unwrap().NP);
+                }
+            }
+            if local.parameter.is_some() {
+                assert!(result.optima.iter().any(|x| x.starts.contains(&0)));
+            }
+
+            // The web store's local maximum near the current parameter is not
+            // its best one, some other start finds a better one.
+            if m.id == 0 {
+                assert!(!best.starts.contains(&0));
+                let rrgame = rrgame::RRGame {
+                    parameter: best.parameter.clone(),
+                };
+                let best_input = Input {
+                    rrgame: &rrgame,
+                    ..input
+                };
+                let report = rrgame_solve(&best_input, m);
+                let certified = report
+                    .attempts
+                    .iter()
+                    .any(|attempt| match &attempt.solution {
+                        Some(solution) => {
+                            solution.parameter.max_change(&relation, &best.parameter) < 0.0001
+                                && solution.second_order.curvature == Curvature::Maximum
+                        }
+                        None => false,
+                    });
+                assert!(certified);
+            }
+        }
+    }
+
+    #[test]
+    fn test_starting_points() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, mut constant, mrgame, rrgame) = scenario.build().unwrap();
+        let m = Retailer { id: 1 };
+        constant.bounds.c_m[m].lower = 0.1;
+        constant.bounds.c_m[m].upper = 0.2;
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let variables = mrgame_variables(&input);
+        let options = MultiStartOptions::default();
+        let current = &mrgame.parameter;
+        let starts = starting_points(&input, current, &variables, &options, mrgame_value);
+        assert_eq!(starts.len(), options.starts);
+        assert_eq!(starts[0].max_change(&relation, current), 0.0);
+
+        for start in starts.iter().skip(1) {
+            assert!(start.max_change(&relation, current) > 0.0);
+            assert!(start.c_m[m] >= 0.1 && start.c_m[m] < 0.2);
+            for &x in variables.iter() {
+                let mut start = start.clone();
+                let value = *mrgame_value(&mut start, x);
+                let bound = constant.bounds.bound(x);
+                assert!(value >= bound.lower && value <= bound.upper);
+            }
+        }
+
+        let again = starting_points(&input, current, &variables, &options, mrgame_value);
+        for (a, b) in starts.iter().zip(again.iter()) {
+            assert_eq!(a.max_change(&relation, b), 0.0);
+        }
+    }
+}
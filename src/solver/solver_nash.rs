@@ -0,0 +1,329 @@
+use super::{rrgame_respond, RRGameMethod, RRGameReport};
+use crate::computation;
+use crate::computation::Input;
+use crate::parallel::par_map;
+use crate::relation::{Retailer, RetailerMap};
+use crate::rrgame;
+
+// Best-response iteration among the retailers for a fixed manufacturer. Each
+// sweep asks every retailer for its rrgame_respond answer; with Jacobi all
+// retailers answer the parameter of the previous sweep, with Gauss-Seidel each
+// retailer already sees the answers given earlier in the same sweep.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NashOrder {
+    Jacobi,
+    GaussSeidel,
+}
+
+#[derive(Clone)]
+pub struct NashOptions {
+    pub order: NashOrder,
+    /// Share of the old value kept when a retailer moves to its best
+    /// response: 0.0 jumps straight to the response.
+    pub damping: f64,
+    /// Stop once `rrgame::Parameter::max_change` of a sweep falls below this
+    /// value.
+    pub tolerance: f64,
+    pub max_sweeps: usize,
+    /// Report divergence when the change grew in this many consecutive
+    /// sweeps.
+    pub divergence_sweeps: usize,
+    /// How each retailer finds its best response.
+    pub method: RRGameMethod,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NashStatus {
+    Converged,
+    SweepLimit,
+    /// The parameter came back within `tolerance` of the one `period` sweeps
+    /// earlier without settling.
+    Cycling {
+        period: usize,
+    },
+    /// A parameter became non-finite or the change kept growing.
+    Diverged,
+    /// The retailer's problem had no solution in the last sweep, see
+    /// `RRGameReport::responded`, so its parameter was left where it was.
+    NoResponse(Retailer),
+}
+
+#[derive(Clone)]
+pub struct NashSweep {
+    pub parameter: rrgame::Parameter,
+    pub change: f64,
+    pub NP_m: RetailerMap<f64>,
+    /// The `rrgame_respond` report of every retailer, in the order they moved.
+    pub reports: Vec<RRGameReport>,
+}
+
+#[derive(Clone)]
+pub struct NashResult {
+    pub status: NashStatus,
+    pub parameter: rrgame::Parameter,
+    pub trace: Vec<NashSweep>,
+}
+
+impl Default for NashOptions {
+    fn default() -> Self {
+        Self {
+            order: NashOrder::GaussSeidel,
+            damping: 0.0,
+            tolerance: 0.0001,
+            max_sweeps: 10,
+            divergence_sweeps: 5,
+            method: RRGameMethod::ActiveSet,
+        }
+    }
+}
+
+fn finite(input: &Input, parameter: &rrgame::Parameter) -> bool {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    relation.initial_retailers().all(|m| {
+        relation
+            .products(m, decision)
+            .all(|g| parameter.p_mg[m][g].is_finite() && parameter.a_mg[m][g].is_finite())
+    })
+}
+
+fn sweep(
+    input: &Input,
+    parameter: &mut rrgame::Parameter,
+    options: &NashOptions,
+) -> Vec<RRGameReport> {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let old_parameter = parameter.clone();
+    let keep = options.damping;
+    let mut reports = Vec::new();
+
+    // In a Jacobi sweep every retailer answers the same parameter, so the
+    // answers are independent and are computed up front.
+    let retailers: Vec<Retailer> = relation.initial_retailers().collect();
+    let mut jacobi_reports = match options.order {
+        NashOrder::Jacobi => {
+            let rrgame = rrgame::RRGame {
+                parameter: old_parameter.clone(),
+            };
+            let input = Input {
+                rrgame: &rrgame,
+                ..(*input)
+            };
+            par_map(retailers.len(), |i| {
+                rrgame_respond(&input, retailers[i], options.method)
+            })
+            .into_iter()
+        }
+        NashOrder::GaussSeidel => Vec::new().into_iter(),
+    };
+
+    for m in retailers.iter().copied() {
+        let report = match jacobi_reports.next() {
+            Some(report) => report,
+            None => {
+                let rrgame = rrgame::RRGame {
+                    parameter: parameter.clone(),
+                };
+                let input = Input {
+                    rrgame: &rrgame,
+                    ..(*input)
+                };
+                rrgame_respond(&input, m, options.method)
+            }
+        };
+
+        if let Some(response) = &report.parameter {
+            for g in relation.products(m, decision) {
+                parameter.p_mg[m][g] =
+                    keep * old_parameter.p_mg[m][g] + (1.0 - keep) * response.p_mg[m][g];
+                parameter.a_mg[m][g] =
+                    keep * old_parameter.a_mg[m][g] + (1.0 - keep) * response.a_mg[m][g];
+            }
+        }
+        reports.push(report);
+    }
+
+    reports
+}
+
+/// Iterates the retailers' best responses to `input.mrgame`, starting from
+/// `input.rrgame`, until no retailer moves. Stops at the first sweep in which
+/// a retailer's problem has no solution.
+pub fn retailer_nash_solve(input: &Input, options: &NashOptions) -> NashResult {
+    let relation = input.relation;
+
+    let mut parameter = input.rrgame.parameter.clone();
+    let mut trace: Vec<NashSweep> = Vec::new();
+    let mut status = NashStatus::SweepLimit;
+    let mut growing = 0;
+
+    for _ in 0..options.max_sweeps {
+        let old_parameter = parameter.clone();
+        let reports = sweep(input, &mut parameter, options);
+
+        if !finite(input, &parameter) {
+            status = NashStatus::Diverged;
+            break;
+        }
+
+        let failed = reports
+            .iter()
+            .find(|report| !report.responded())
+            .map(|report| report.retailer);
+        let change = parameter.max_change(relation, &old_parameter);
+        let NP_m = {
+            let rrgame = rrgame::RRGame {
+                parameter: parameter.clone(),
+            };
+            let input = Input {
+                rrgame: &rrgame,
+                ..(*input)
+            };
+
+            let mut NP_m = RetailerMap::new(relation, 0.0);
+            for m in relation.initial_retailers() {
+                NP_m[m] = computation::NP(&input, m);
+            }
+            NP_m
+        };
+
+        match trace.last() {
+            Some(last) if change > last.change => growing += 1,
+            _ => growing = 0,
+        }
+
+        // The last entry of the trace is the parameter before this sweep, so
+        // a match with it means the change is already below tolerance.
+        let period = trace
+            .iter()
+            .rev()
+            .skip(1)
+            .position(|old| parameter.max_change(relation, &old.parameter) <= options.tolerance)
+            .map(|index| index + 2);
+
+        trace.push(NashSweep {
+            parameter: parameter.clone(),
+            change,
+            NP_m,
+            reports,
+        });
+
+        // A retailer left in place by a failed solve did not answer, so the
+        // sweep says nothing about a fixed point.
+        if let Some(m) = failed {
+            status = NashStatus::NoResponse(m);
+            break;
+        }
+
+        if change <= options.tolerance {
+            status = NashStatus::Converged;
+            break;
+        }
+
+        if let Some(period) = period {
+            status = NashStatus::Cycling { period };
+            break;
+        }
+
+        if growing >= options.divergence_sweeps {
+            status = NashStatus::Diverged;
+            break;
+        }
+    }
+
+    NashResult {
+        status,
+        parameter,
+        trace,
+    }
+}
+
+impl NashResult {
+    pub fn show(&self, input: &Input) {
+        let relation = input.relation;
+
+        println!("Nash: {:?} after {} sweeps", self.status, self.trace.len());
+        for (index, sweep) in self.trace.iter().enumerate() {
+            print!("{}\tchange: {}", index, sweep.change);
+            for m in relation.initial_retailers() {
+                print!("\tNP {}: {}", relation.retailer_label(m), sweep.NP_m[m]);
+            }
+            println!();
+        }
+
+        self.parameter.show_p_mg(relation);
+        self.parameter.show_a_mg(relation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn test_orders() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        for order in [NashOrder::Jacobi, NashOrder::GaussSeidel].iter() {
+            let options = NashOptions {
+                order: *order,
+                max_sweeps: 30,
+                ..NashOptions::default()
+            };
+            let result = retailer_nash_solve(&input, &options);
+            assert_eq!(result.status, NashStatus::Converged);
+
+            // The same with or without the `parallel` feature, and on every run.
+            let again = retailer_nash_solve(&input, &options);
+            assert_eq!(
+                again.parameter.max_change(&relation, &result.parameter),
+                0.0
+            );
+
+            let last = result.trace.last().unwrap();
+            assert!(last.change <= options.tolerance);
+            assert_eq!(last.parameter.max_change(&relation, &result.parameter), 0.0);
+            for sweep in result.trace.iter() {
+                assert!(sweep.NP_m[Retailer { id: 0 }].is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn test_interior_method() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let options = NashOptions {
+            max_sweeps: 30,
+            method: RRGameMethod::InteriorPoint,
+            ..NashOptions::default()
+        };
+        let result = retailer_nash_solve(&input, &options);
+        assert_eq!(result.status, NashStatus::Converged);
+        for sweep in result.trace.iter() {
+            assert_eq!(sweep.reports.len(), relation.initial_retailers().count());
+            for report in sweep.reports.iter() {
+                assert_eq!(report.attempts.len(), 1);
+            }
+        }
+    }
+}
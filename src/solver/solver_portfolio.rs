@@ -0,0 +1,337 @@
+use super::{stackelberg_solve, StackelbergOptions, StackelbergResult, StackelbergStatus};
+use crate::computation::Input;
+use crate::error::{Error, Result};
+use crate::mrgame;
+use crate::relation::{Alternative, AlternativeMap, Product, ProductMap, Relation};
+use std::convert::TryFrom;
+
+// A decision is feasible when each chosen product has a chosen alternative in
+// every module it draws an alternative from. Only alternatives that serve a
+// chosen product are considered: any other one only adds its fixed cost.
+//
+// Every feasible decision is priced by a full stackelberg_solve. There is no
+// pruning: adding a product can raise or lower the equilibrium profit of the
+// others, so no cheap solve bounds the portfolios that extend a product line.
+// The search is exponential in the products and alternatives, so it refuses to
+// start when the subsets to enumerate or the feasible decisions to solve
+// exceed `max_candidates`. Only converged solves compete for the best
+// portfolio: the profit of an unfinished iteration is not an equilibrium.
+
+#[derive(Clone)]
+pub struct PortfolioOptions {
+    pub stackelberg: StackelbergOptions,
+    pub max_candidates: usize,
+}
+
+impl Default for PortfolioOptions {
+    fn default() -> Self {
+        Self {
+            stackelberg: StackelbergOptions::default(),
+            max_candidates: 1024,
+        }
+    }
+}
+
+pub struct PortfolioCandidate {
+    pub decision: mrgame::Decision,
+    pub status: StackelbergStatus,
+    /// `None` when the Stackelberg solve stopped because the retailers or the
+    /// manufacturer had no answer.
+    pub NP0: Option<f64>,
+}
+
+pub struct PortfolioResult {
+    pub best: Option<(mrgame::Decision, StackelbergResult)>,
+    pub candidates: Vec<PortfolioCandidate>,
+}
+
+pub fn feasible_decision(relation: &Relation, decision: &mrgame::Decision) -> bool {
+    if !relation.all_products().any(|g| decision.product[g]) {
+        return false;
+    }
+
+    for g in relation.all_products() {
+        if !decision.product[g] {
+            continue;
+        }
+
+        let alternatives = relation.alternatives_of_product(g);
+        for k in alternatives.iter() {
+            let j = match relation.module(*k) {
+                Ok(j) => j,
+                Err(_) => return false,
+            };
+            let covered = alternatives
+                .iter()
+                .any(|x| relation.module(*x).ok() == Some(j) && decision.alternative[*x]);
+            if !covered {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// `2^len`, or `Error::TooManyCandidates` when it is above `limit`.
+fn subset_count(len: usize, limit: usize) -> Result<usize> {
+    u32::try_from(len)
+        .ok()
+        .and_then(|len| 1usize.checked_shl(len))
+        .filter(|count| *count <= limit)
+        .ok_or(Error::TooManyCandidates { limit })
+}
+
+/// Feasible alternative choices for a fixed product line, or an error when
+/// there are more than `limit` choices to check.
+pub fn alternative_sets(
+    relation: &Relation,
+    product: &ProductMap<bool>,
+    limit: usize,
+) -> Result<Vec<AlternativeMap<bool>>> {
+    let mut usable: Vec<Alternative> = Vec::new();
+    for g in relation.all_products().filter(|g| product[*g]) {
+        for k in relation.alternatives_of_product(g) {
+            if !usable.contains(k) {
+                usable.push(*k);
+            }
+        }
+    }
+
+    let mut result = Vec::new();
+    for mask in 0..subset_count(usable.len(), limit)? {
+        let mut decision = mrgame::Decision {
+            product: product.clone(),
+            alternative: AlternativeMap::new(relation, false),
+        };
+        for (bit, k) in usable.iter().enumerate() {
+            decision.alternative[*k] = mask & (1 << bit) != 0;
+        }
+
+        if feasible_decision(relation, &decision) {
+            result.push(decision.alternative);
+        }
+    }
+
+    Ok(result)
+}
+
+fn product_sets(relation: &Relation, limit: usize) -> Result<Vec<ProductMap<bool>>> {
+    let products: Vec<Product> = relation.all_products().collect();
+
+    let mut result = Vec::new();
+    for mask in 1..subset_count(products.len(), limit)? {
+        let mut product = ProductMap::new(relation, false);
+        for (bit, g) in products.iter().enumerate() {
+            product[*g] = mask & (1 << bit) != 0;
+        }
+        result.push(product);
+    }
+
+    Ok(result)
+}
+
+fn evaluate(
+    input: &Input,
+    decision: &mrgame::Decision,
+    options: &PortfolioOptions,
+) -> StackelbergResult {
+    let mrgame = mrgame::MRGame {
+        decision: decision.clone(),
+        parameter: input.mrgame.parameter.clone(),
+    };
+    let input = Input {
+        mrgame: &mrgame,
+        ..(*input)
+    };
+
+    stackelberg_solve(&input, &options.stackelberg)
+}
+
+struct Search {
+    best: Option<(mrgame::Decision, StackelbergResult)>,
+    candidates: Vec<PortfolioCandidate>,
+}
+
+impl Search {
+    fn best_NP0(&self) -> f64 {
+        match &self.best {
+            Some((_, result)) => result.NP0,
+            None => f64::NEG_INFINITY,
+        }
+    }
+
+    fn try_decision(
+        &mut self,
+        input: &Input,
+        decision: mrgame::Decision,
+        options: &PortfolioOptions,
+    ) {
+        let result = evaluate(input, &decision, options);
+        let NP0 = match result.status {
+            StackelbergStatus::NoEquilibrium(_) | StackelbergStatus::NoResponse => None,
+            _ => Some(result.NP0),
+        };
+
+        self.candidates.push(PortfolioCandidate {
+            decision: decision.clone(),
+            status: result.status,
+            NP0,
+        });
+
+        if result.status == StackelbergStatus::Converged && result.NP0 > self.best_NP0() {
+            self.best = Some((decision, result));
+        }
+    }
+}
+
+/// Looks for the product line and alternatives with the highest equilibrium
+/// `NP0`, starting every candidate from the parameters in `input`. Fails
+/// before solving anything when there are more than `max_candidates`.
+pub fn portfolio_solve(input: &Input, options: &PortfolioOptions) -> Result<PortfolioResult> {
+    let relation = input.relation;
+    let limit = options.max_candidates;
+
+    let mut decisions = Vec::new();
+    for product in product_sets(relation, limit)? {
+        for alternative in alternative_sets(relation, &product, limit)? {
+            if decisions.len() == limit {
+                return Err(Error::TooManyCandidates { limit });
+            }
+            decisions.push(mrgame::Decision {
+                product: product.clone(),
+                alternative,
+            });
+        }
+    }
+
+    let mut search = Search {
+        best: None,
+        candidates: Vec::new(),
+    };
+
+    for decision in decisions {
+        search.try_decision(input, decision, options);
+    }
+
+    Ok(PortfolioResult {
+        best: search.best,
+        candidates: search.candidates,
+    })
+}
+
+impl PortfolioResult {
+    pub fn show(&self, relation: &Relation) {
+        println!("Portfolio: {} candidates", self.candidates.len());
+        for candidate in self.candidates.iter() {
+            let products: Vec<String> = relation
+                .all_products()
+                .filter(|g| candidate.decision.product[*g])
+                .map(|g| relation.product_label(g))
+                .collect();
+            let alternatives: Vec<String> = relation
+                .all_alternatives()
+                .filter(|k| candidate.decision.alternative[*k])
+                .map(|k| relation.alternative_label(k))
+                .collect();
+            print!("{}\t{}", products.join(", "), alternatives.join(", "));
+            match (candidate.NP0, candidate.status) {
+                (Some(NP0), StackelbergStatus::Converged) => println!("\tNP0: {}", NP0),
+                (Some(NP0), status) => println!("\tNP0: {} ({:?}, not ranked)", NP0, status),
+                (None, status) => println!("\tno equilibrium ({:?})", status),
+            }
+        }
+
+        match &self.best {
+            Some((decision, result)) => {
+                decision.show(relation);
+
+                println!("Best modules");
+                for j in relation.all_modules() {
+                    let alternatives: Vec<String> = relation
+                        .alternatives_of_module(j)
+                        .iter()
+                        .filter(|k| decision.alternative[**k])
+                        .map(|k| relation.alternative_label(*k))
+                        .collect();
+                    println!("{}: {}", relation.module_label(j), alternatives.join(", "));
+                }
+
+                result.show(relation);
+            }
+            None => println!("No converged portfolio"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn test_portfolio() {
+        let text = include_str!("../../scenarios/single_retailer.toml");
+        let (relation, constant, mrgame, rrgame) = Scenario::parse(text).unwrap().build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        assert!(feasible_decision(&relation, &mrgame.decision));
+        assert_eq!(
+            alternative_sets(&relation, &mrgame.decision.product, 8)
+                .unwrap()
+                .len(),
+            3
+        );
+        // Three usable alternatives give eight subsets to check.
+        match alternative_sets(&relation, &mrgame.decision.product, 7) {
+            Err(Error::TooManyCandidates { limit: 7 }) => {}
+            _ => panic!("expected too many candidates"),
+        }
+        assert!(subset_count(64, usize::MAX).is_err());
+
+        let options = PortfolioOptions {
+            max_candidates: 2,
+            ..PortfolioOptions::default()
+        };
+        match portfolio_solve(&input, &options) {
+            Err(Error::TooManyCandidates { limit: 2 }) => {}
+            _ => panic!("expected too many candidates"),
+        }
+
+        let options = PortfolioOptions::default();
+        let result = portfolio_solve(&input, &options).unwrap();
+        assert_eq!(result.candidates.len(), 3);
+        for candidate in result.candidates.iter() {
+            assert!(feasible_decision(&relation, &candidate.decision));
+        }
+
+        let (decision, best) = result.best.unwrap();
+        assert_eq!(best.status, StackelbergStatus::Converged);
+        for candidate in result.candidates.iter() {
+            if candidate.status == StackelbergStatus::Converged {
+                assert!(candidate.NP0.unwrap() <= best.NP0);
+            }
+        }
+
+        // The second alternative of module 1 only adds costs.
+        let k = |id| Alternative { id };
+        assert!(decision.alternative[k(0)] && decision.alternative[k(1)]);
+        assert!(!decision.alternative[k(2)]);
+
+        let mrgame = mrgame::MRGame {
+            decision,
+            parameter: mrgame.parameter.clone(),
+        };
+        let input = Input {
+            mrgame: &mrgame,
+            ..input
+        };
+        let again = stackelberg_solve(&input, &options.stackelberg);
+        assert_eq!(again.NP0, best.NP0);
+    }
+}
@@ -0,0 +1,334 @@
+use super::{rrgame_input_to_array, rrgame_jacobian, rrgame_variables};
+use super::{RRGameConstraints, RRGameLambdas};
+use crate::computation;
+use crate::computation::comp_generic::{Seed, Variable};
+use crate::computation::{Evaluation, Input};
+use crate::dual::{Dual, HyperDual};
+use crate::relation::{Product, ProductMap};
+use crate::rrgame;
+use computation::comp_generic;
+use computation::comp_generic::DualInput;
+use computation::{drhoda_NP, drhodp_NP};
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{FactorizeInto, Solve};
+
+// The retailers answer a change of rho_g by moving their prices and
+// advertising. Around the current rrgame parameter, which is taken to be the
+// retailers' Nash response, the first-order conditions dp_NP = da_NP = 0 of
+// every retailer hold. Differentiating them gives the direction in which the
+// retailers move (implicit function theorem): J_x dx = -J_rho, with J_x the
+// Jacobian of the stacked first-order conditions and J_rho their derivative
+// with respect to rho_j. The diagonal blocks of J_x are `rrgame_jacobian`,
+// the blocks coupling two retailers come from the cross terms of DP.
+// A variable the retailer holds at a bound it is pushed against stays there,
+// its row replaced by dx_i = 0. The TVR and Ta constraints of the retailer
+// game are not taken into account.
+//
+// J_x is factorised once and dx_g holds the direction for every rho_g, so the
+// response is linearised around the point it was built at.
+
+/// How far from a bound a retailer's variable may be and still count as held
+/// there.
+const BOUND_EPSILON: f64 = 0.000001;
+
+pub struct RhoResponse {
+    /// The retailers' prices and advertising in the order of
+    /// `retailer_to_array`.
+    pub variables: Vec<Variable>,
+    pub dx_g: ProductMap<Array1<f64>>,
+}
+
+fn retailer_len(input: &Input) -> usize {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut len = 0;
+    for m in relation.initial_retailers() {
+        len += relation.products(m, decision).count() * 2;
+    }
+    len
+}
+
+pub fn retailer_to_array(input: &Input) -> Array1<f64> {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+    let p_mg = &input.rrgame.parameter.p_mg;
+    let a_mg = &input.rrgame.parameter.a_mg;
+
+    let mut result = Array1::zeros(retailer_len(input));
+
+    let mut index = 0;
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            result[index] = p_mg[m][g];
+            index += 1;
+        }
+
+        for g in relation.products(m, decision) {
+            result[index] = a_mg[m][g];
+            index += 1;
+        }
+    }
+
+    result
+}
+
+pub fn retailer_array_to_parameter(input: &Input, array: &Array1<f64>) -> rrgame::Parameter {
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut parameter = input.rrgame.parameter.clone();
+
+    let mut index = 0;
+    for m in relation.initial_retailers() {
+        for g in relation.products(m, decision) {
+            parameter.p_mg[m][g] = array[index];
+            index += 1;
+        }
+
+        for g in relation.products(m, decision) {
+            parameter.a_mg[m][g] = array[index];
+            index += 1;
+        }
+    }
+
+    parameter
+}
+
+fn retailer_drho_f(evaluation: &Evaluation, k: Product) -> Array1<f64> {
+    let input = evaluation.input();
+    let relation = input.relation;
+    let decision = &input.mrgame.decision;
+
+    let mut result = Array1::zeros(retailer_len(input));
+
+    let mut index = 0;
+    for m in relation.initial_retailers() {
+        for j in relation.products(m, decision) {
+            result[index] = drhodp_NP(evaluation, m, j, k);
+            index += 1;
+        }
+
+        for j in relation.products(m, decision) {
+            result[index] = drhoda_NP(evaluation, m, j, k);
+            index += 1;
+        }
+    }
+
+    result
+}
+
+impl RhoResponse {
+    /// `None` when the Jacobian of the retailers' first-order conditions is
+    /// singular.
+    pub fn new(input: &Input) -> Option<Self> {
+        let relation = input.relation;
+        let evaluation = Evaluation::new(input);
+        let constraints = RRGameConstraints {
+            TVR_active: false,
+            Ta_active: false,
+        };
+        let lambdas = RRGameLambdas { TVR: 0.0, Ta: 0.0 };
+
+        let mut variables = Vec::new();
+        for m in relation.initial_retailers() {
+            variables.extend(rrgame_variables(input, m));
+        }
+        let len = variables.len();
+
+        let mut jx = Array2::zeros((len, len));
+        let mut held = Vec::new();
+        let mut start = 0;
+        for m in relation.initial_retailers() {
+            let own = rrgame_variables(input, m);
+            let array = rrgame_input_to_array(input, m, constraints, lambdas);
+            let block = rrgame_jacobian(input, m, &array, constraints);
+            let own_columns = start..(start + own.len());
+            let NP_m = |x: &DualInput| comp_generic::NP::<HyperDual>(x, m);
+
+            for (row, x) in own.iter().enumerate() {
+                let gradient = evaluation.derivative(*x, |x| comp_generic::NP(x, m));
+                let blocking =
+                    input
+                        .constant
+                        .bounds
+                        .bound(*x)
+                        .blocking(array[row], gradient, BOUND_EPSILON);
+                if blocking.is_some() {
+                    held.push(start + row);
+                    jx[(start + row, start + row)] = 1.0;
+                    continue;
+                }
+
+                for (col, y) in variables.iter().enumerate() {
+                    jx[(start + row, col)] = if own_columns.contains(&col) {
+                        block[(row, col - start)]
+                    } else {
+                        evaluation.second_derivative(*x, *y, NP_m)
+                    };
+                }
+            }
+            start += own.len();
+        }
+
+        let lu = jx.factorize_into().ok()?;
+
+        let mut dx_g = ProductMap::new(relation, Array1::zeros(len));
+        for k in relation.all_products() {
+            let mut minus_jrho = -retailer_drho_f(&evaluation, k);
+            for i in held.iter() {
+                minus_jrho[*i] = 0.0;
+            }
+            dx_g[k] = lu.solve_into(minus_jrho).ok()?;
+        }
+
+        Some(Self { variables, dx_g })
+    }
+
+    /// The direction everything moves in when `rho_j` moves by one unit:
+    /// `rho_j` itself and the retailers along `dx_g[j]`.
+    pub fn direction(&self, j: Product) -> Vec<(Variable, f64)> {
+        let mut result = vec![(Variable::rho_g(j), 1.0)];
+        for (x, dx) in self.variables.iter().zip(self.dx_g[j].iter()) {
+            result.push((*x, *dx));
+        }
+        result
+    }
+
+    /// Total derivative of `f` with respect to `rho_j`, the retailers
+    /// following along `dx_g[j]`.
+    pub fn derivative(
+        &self,
+        evaluation: &Evaluation,
+        j: Product,
+        f: impl Fn(&DualInput) -> Dual,
+    ) -> f64 {
+        let direction = self.direction(j);
+        evaluation.derivative_along(Seed::Direction(&direction), f)
+    }
+
+    /// Derivative of `derivative(evaluation, j, f)` with respect to `y`, with
+    /// the response held fixed.
+    pub fn second_derivative(
+        &self,
+        evaluation: &Evaluation,
+        j: Product,
+        y: Variable,
+        f: impl Fn(&DualInput) -> HyperDual,
+    ) -> f64 {
+        let direction = self.direction(j);
+        evaluation.second_derivative_along(Seed::Direction(&direction), Seed::Variable(y), f)
+    }
+}
+
+/// Total derivative of NP0 with respect to `rho_j`, including the retailers'
+/// response, `None` when `RhoResponse::new` is.
+pub fn drho_NP0_response(input: &Input, j: Product) -> Option<f64> {
+    let response = RhoResponse::new(input)?;
+    Some(response.derivative(&Evaluation::new(input), j, comp_generic::NP0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn test_response() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, mut rrgame) = scenario.build().unwrap();
+
+        // Away from the kinks of DP where a price or advertising is zero.
+        for m in relation.initial_retailers() {
+            for g in relation.products(m, &mrgame.decision) {
+                rrgame.parameter.p_mg[m][g] = rrgame.parameter.p_mg[m][g].max(50.0);
+                rrgame.parameter.a_mg[m][g] = rrgame.parameter.a_mg[m][g].max(500.0);
+            }
+        }
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let response = RhoResponse::new(&input).unwrap();
+
+        let first_order = |input: &Input| -> Vec<f64> {
+            let evaluation = Evaluation::new(input);
+            let mut result = Vec::new();
+            for x in response.variables.iter() {
+                let m = match x {
+                    Variable::p_mg(m, _) | Variable::a_mg(m, _) => *m,
+                    _ => unreachable!(),
+                };
+                result.push(evaluation.derivative(*x, |x| comp_generic::NP(x, m)));
+            }
+            result
+        };
+
+        // Moving rho_k and the retailers along dx_g[k] leaves the first-order
+        // conditions unchanged to first order, unlike moving rho_k alone.
+        let base = first_order(&input);
+        let delta = 0.0001;
+        for k in relation.all_products() {
+            let x = retailer_to_array(&input) + delta * &response.dx_g[k];
+            let rrgame = rrgame::RRGame {
+                parameter: retailer_array_to_parameter(&input, &x),
+            };
+            let mut mrgame = mrgame.clone();
+            mrgame.parameter.rho_g[k] += delta;
+            let moved = first_order(&Input {
+                mrgame: &mrgame,
+                rrgame: &rrgame,
+                ..input
+            });
+            let fixed = first_order(&Input {
+                mrgame: &mrgame,
+                ..input
+            });
+            for ((a, b), c) in base.iter().zip(moved.iter()).zip(fixed.iter()) {
+                assert!((a - b).abs() <= 0.05 * (a - c).abs() + delta);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rho_derivatives() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mut mrgame, rrgame) = scenario.build().unwrap();
+        for g in relation.all_products() {
+            mrgame.parameter.rho_g[g] = 0.0001 * (g.id + 1) as f64;
+        }
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let evaluation = computation::Evaluation::new(&input);
+
+        let close = |exact: f64, approx: f64| (exact - approx).abs() < 1e-3 * exact.abs().max(1.0);
+        for k in relation.all_products() {
+            assert!(close(
+                computation::drho_NP0(&evaluation, k),
+                computation::drho_NP0_approx(&input, k)
+            ));
+            for m in relation.initial_retailers() {
+                assert!(close(
+                    computation::drho_NP(&evaluation, m, k),
+                    computation::drho_NP_approx(&input, m, k)
+                ));
+                for j in relation.products(m, &mrgame.decision) {
+                    assert!(close(
+                        drhodp_NP(&evaluation, m, j, k),
+                        computation::drhodp_NP_approx(&input, m, j, k)
+                    ));
+                    assert!(close(
+                        drhoda_NP(&evaluation, m, j, k),
+                        computation::drhoda_NP_approx(&input, m, j, k)
+                    ));
+                }
+            }
+        }
+    }
+}
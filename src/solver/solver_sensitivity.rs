@@ -0,0 +1,515 @@
+use super::{stackelberg_solve, StackelbergOptions, StackelbergStatus};
+use crate::computation::{Evaluation, Input};
+use crate::mrgame::MRGame;
+use crate::parallel::par_map;
+use crate::relation::show_header;
+use crate::relation::{Constant, CrossMap, Entity, EntityMap, Product, Relation, Retailer};
+use crate::rrgame::RRGame;
+
+// Sensitivity of the equilibrium to the constants by central differences:
+// every entry of one `Constant` field is scaled by `1 + step` and `1 - step`,
+// the Stackelberg game is solved again from the equilibrium, and the outcomes
+// are compared. Dividing by the outcome at the equilibrium gives the
+// elasticity d ln(outcome) / d ln(field).
+//
+// The results are elasticities to a whole field, not derivatives with respect
+// to its entries: scaling every `PCA_k` together says nothing about one
+// alternative's `PCA_k[k]` on its own.
+
+/// The `f64` fields of `Constant`. A field is perturbed as a whole, every
+/// entry by the same factor.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Field {
+    v_mgxy,
+    ea_mgxy,
+    beta_mgxy,
+    ep_mgxy,
+    K_mg,
+    zeta_mg,
+    HR_mg,
+    TP_mg,
+    eA_mgy,
+    u_mgy,
+    pw_g0,
+    PCP_g,
+    ORM_s,
+    HRM_l,
+    FCA_k,
+    PCA_k,
+    PCR_sl,
+    V_g,
+    w_0,
+    w_m,
+    TVR_m,
+    Ta_m,
+    OP_m,
+    FCM_j,
+    HP_g,
+    VRM_l,
+    TVP,
+}
+
+/// An outcome of the equilibrium.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Outcome {
+    NP(Retailer),
+    NP0,
+    DP(Retailer, Product),
+    p_mg(Retailer, Product),
+    A_g(Product),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensitivityMethod {
+    /// Solves the Stackelberg game again, from the equilibrium, for every
+    /// perturbed field.
+    Resolve,
+    /// Keeps the parameters at the equilibrium: the direct effect of the
+    /// constants only, so `p_mg` and `A_g` do not change.
+    Fixed,
+}
+
+#[derive(Clone)]
+pub struct SensitivityOptions {
+    /// Relative change of the fields on either side.
+    pub step: f64,
+    pub method: SensitivityMethod,
+    pub stackelberg: StackelbergOptions,
+}
+
+#[derive(Clone)]
+pub struct FieldElasticity {
+    pub field: Field,
+    /// Semi-elasticities d(outcome) / d ln(field) of each of
+    /// `Sensitivity::outcomes`: the change of the outcome per relative change
+    /// of every entry of the field at once. All NaN with
+    /// `SensitivityMethod::Resolve` unless the equilibrium and both
+    /// perturbed solves converged.
+    pub semi_elasticities: Vec<f64>,
+    /// d ln(outcome) / d ln(field), NaN for an outcome that is 0 at the
+    /// equilibrium.
+    pub elasticities: Vec<f64>,
+    /// Statuses of the solves at `1 + step` and `1 - step` with
+    /// `SensitivityMethod::Resolve`.
+    pub status: Option<(StackelbergStatus, StackelbergStatus)>,
+}
+
+#[derive(Clone)]
+pub struct Sensitivity {
+    /// Status of the solve that found the equilibrium.
+    pub status: StackelbergStatus,
+    pub mrgame: MRGame,
+    pub rrgame: RRGame,
+    pub outcomes: Vec<Outcome>,
+    /// The outcomes at the equilibrium.
+    pub values: Vec<f64>,
+    /// One row per field, ranked by `FieldElasticity::largest`.
+    pub fields: Vec<FieldElasticity>,
+}
+
+impl Default for SensitivityOptions {
+    fn default() -> Self {
+        Self {
+            step: 0.01,
+            method: SensitivityMethod::Resolve,
+            stackelberg: StackelbergOptions::default(),
+        }
+    }
+}
+
+trait Scale {
+    fn scale(&mut self, factor: f64);
+}
+
+impl Scale for f64 {
+    fn scale(&mut self, factor: f64) {
+        *self *= factor;
+    }
+}
+
+impl<E: Entity, T: Scale> Scale for EntityMap<E, T> {
+    fn scale(&mut self, factor: f64) {
+        for (_, x) in self.iter_mut() {
+            x.scale(factor);
+        }
+    }
+}
+
+impl Scale for CrossMap {
+    fn scale(&mut self, factor: f64) {
+        self.array_mut().mapv_inplace(|x| x * factor);
+    }
+}
+
+impl Field {
+    pub const ALL: [Field; 27] = [
+        Field::v_mgxy,
+        Field::ea_mgxy,
+        Field::beta_mgxy,
+        Field::ep_mgxy,
+        Field::K_mg,
+        Field::zeta_mg,
+        Field::HR_mg,
+        Field::TP_mg,
+        Field::eA_mgy,
+        Field::u_mgy,
+        Field::pw_g0,
+        Field::PCP_g,
+        Field::ORM_s,
+        Field::HRM_l,
+        Field::FCA_k,
+        Field::PCA_k,
+        Field::PCR_sl,
+        Field::V_g,
+        Field::w_0,
+        Field::w_m,
+        Field::TVR_m,
+        Field::Ta_m,
+        Field::OP_m,
+        Field::FCM_j,
+        Field::HP_g,
+        Field::VRM_l,
+        Field::TVP,
+    ];
+
+    fn value(self, constant: &mut Constant) -> &mut dyn Scale {
+        match self {
+            Field::v_mgxy => &mut constant.v_mgxy,
+            Field::ea_mgxy => &mut constant.ea_mgxy,
+            Field::beta_mgxy => &mut constant.beta_mgxy,
+            Field::ep_mgxy => &mut constant.ep_mgxy,
+            Field::K_mg => &mut constant.K_mg,
+            Field::zeta_mg => &mut constant.zeta_mg,
+            Field::HR_mg => &mut constant.HR_mg,
+            Field::TP_mg => &mut constant.TP_mg,
+            Field::eA_mgy => &mut constant.eA_mgy,
+            Field::u_mgy => &mut constant.u_mgy,
+            Field::pw_g0 => &mut constant.pw_g0,
+            Field::PCP_g => &mut constant.PCP_g,
+            Field::ORM_s => &mut constant.ORM_s,
+            Field::HRM_l => &mut constant.HRM_l,
+            Field::FCA_k => &mut constant.FCA_k,
+            Field::PCA_k => &mut constant.PCA_k,
+            Field::PCR_sl => &mut constant.PCR_sl,
+            Field::V_g => &mut constant.V_g,
+            Field::w_0 => &mut constant.w_0,
+            Field::w_m => &mut constant.w_m,
+            Field::TVR_m => &mut constant.TVR_m,
+            Field::Ta_m => &mut constant.Ta_m,
+            Field::OP_m => &mut constant.OP_m,
+            Field::FCM_j => &mut constant.FCM_j,
+            Field::HP_g => &mut constant.HP_g,
+            Field::VRM_l => &mut constant.VRM_l,
+            Field::TVP => &mut constant.TVP,
+        }
+    }
+
+    /// Multiplies every entry of the field in `constant` by `factor`.
+    pub fn scale(self, constant: &mut Constant, factor: f64) {
+        self.value(constant).scale(factor);
+    }
+}
+
+impl Outcome {
+    /// NP of every retailer, NP0, then DP and p_mg of the products each
+    /// retailer sells and A_g of the products offered under `mrgame`.
+    pub fn all(relation: &Relation, mrgame: &MRGame) -> Vec<Outcome> {
+        let mut result = Vec::new();
+        for m in relation.initial_retailers() {
+            result.push(Outcome::NP(m));
+        }
+        result.push(Outcome::NP0);
+        for m in relation.initial_retailers() {
+            for g in relation.products(m, &mrgame.decision) {
+                result.push(Outcome::DP(m, g));
+            }
+        }
+        for m in relation.initial_retailers() {
+            for g in relation.products(m, &mrgame.decision) {
+                result.push(Outcome::p_mg(m, g));
+            }
+        }
+        for g in relation.all_products() {
+            if mrgame.decision.product[g] {
+                result.push(Outcome::A_g(g));
+            }
+        }
+        result
+    }
+
+    pub fn value(self, evaluation: &Evaluation, input: &Input) -> f64 {
+        match self {
+            Outcome::NP(m) => evaluation.NP(m),
+            Outcome::NP0 => evaluation.NP0(),
+            Outcome::DP(m, g) => evaluation.DP(m, g),
+            Outcome::p_mg(m, g) => input.rrgame.parameter.p_mg[m][g],
+            Outcome::A_g(g) => input.mrgame.parameter.A_g[g],
+        }
+    }
+
+    pub fn label(self, relation: &Relation) -> String {
+        match self {
+            Outcome::NP(m) => format!("NP[{}]", relation.retailer_label(m)),
+            Outcome::NP0 => "NP0".to_string(),
+            Outcome::DP(m, g) => format!(
+                "DP[{}, {}]",
+                relation.retailer_label(m),
+                relation.product_label(g)
+            ),
+            Outcome::p_mg(m, g) => format!(
+                "p_mg[{}, {}]",
+                relation.retailer_label(m),
+                relation.product_label(g)
+            ),
+            Outcome::A_g(g) => format!("A_g[{}]", relation.product_label(g)),
+        }
+    }
+}
+
+impl FieldElasticity {
+    /// The largest absolute elasticity, ignoring NaN.
+    pub fn largest(&self) -> f64 {
+        self.elasticities
+            .iter()
+            .filter(|x| !x.is_nan())
+            .fold(0.0, |acc: f64, x| acc.max(x.abs()))
+    }
+}
+
+fn values(input: &Input, outcomes: &[Outcome]) -> Vec<f64> {
+    let evaluation = Evaluation::new(input);
+    outcomes
+        .iter()
+        .map(|x| x.value(&evaluation, input))
+        .collect()
+}
+
+/// The outcomes with `field` scaled by `factor`, and the status of the solve
+/// with `SensitivityMethod::Resolve`.
+fn perturbed(
+    input: &Input,
+    outcomes: &[Outcome],
+    field: Field,
+    factor: f64,
+    options: &SensitivityOptions,
+) -> (Vec<f64>, Option<StackelbergStatus>) {
+    let mut constant = input.constant.clone();
+    field.scale(&mut constant, factor);
+    let input = Input {
+        constant: &constant,
+        ..(*input)
+    };
+
+    match options.method {
+        SensitivityMethod::Resolve => {
+            let result = stackelberg_solve(&input, &options.stackelberg);
+            let input = Input {
+                mrgame: &result.mrgame,
+                rrgame: &result.rrgame,
+                ..input
+            };
+            (values(&input, outcomes), Some(result.status))
+        }
+        SensitivityMethod::Fixed => (values(&input, outcomes), None),
+    }
+}
+
+/// Solves the Stackelberg game from `input` and the elasticities of its
+/// outcomes to every field of `Constant`, ranked by the largest.
+pub fn sensitivity_solve(input: &Input, options: &SensitivityOptions) -> Sensitivity {
+    let relation = input.relation;
+    let equilibrium = stackelberg_solve(input, &options.stackelberg);
+    let input = Input {
+        mrgame: &equilibrium.mrgame,
+        rrgame: &equilibrium.rrgame,
+        ..(*input)
+    };
+    let outcomes = Outcome::all(relation, &equilibrium.mrgame);
+    let base = values(&input, &outcomes);
+
+    let step = options.step;
+    let mut fields = par_map(Field::ALL.len(), |index| {
+        let field = Field::ALL[index];
+        let (up, up_status) = perturbed(&input, &outcomes, field, 1.0 + step, options);
+        let (down, down_status) = perturbed(&input, &outcomes, field, 1.0 - step, options);
+
+        // Outcomes of solves that stopped early are not on the equilibrium
+        // path, so their difference is no elasticity.
+        let converged = match options.method {
+            SensitivityMethod::Resolve => [Some(equilibrium.status), up_status, down_status]
+                .iter()
+                .all(|status| *status == Some(StackelbergStatus::Converged)),
+            SensitivityMethod::Fixed => true,
+        };
+
+        let mut semi_elasticities = Vec::new();
+        let mut elasticities = Vec::new();
+        for i in 0..outcomes.len() {
+            let semi_elasticity = if converged {
+                (up[i] - down[i]) / (2.0 * step)
+            } else {
+                f64::NAN
+            };
+            semi_elasticities.push(semi_elasticity);
+            elasticities.push(if base[i] != 0.0 {
+                semi_elasticity / base[i]
+            } else {
+                f64::NAN
+            });
+        }
+
+        FieldElasticity {
+            field,
+            semi_elasticities,
+            elasticities,
+            status: up_status.zip(down_status),
+        }
+    });
+    fields.sort_by(|a, b| b.largest().partial_cmp(&a.largest()).unwrap());
+
+    Sensitivity {
+        status: equilibrium.status,
+        mrgame: equilibrium.mrgame.clone(),
+        rrgame: equilibrium.rrgame.clone(),
+        outcomes,
+        values: base,
+        fields,
+    }
+}
+
+impl Sensitivity {
+    /// Prints the outcomes at the equilibrium, then the elasticities of a
+    /// field per row, the most influential first. A field whose solves did
+    /// not both converge is marked with their statuses.
+    pub fn show(&self, relation: &Relation) {
+        println!("Sensitivity: equilibrium {:?}", self.status);
+        show_header(self.outcomes.iter().map(|x| x.label(relation)));
+        print!("value\t");
+        for value in self.values.iter() {
+            print!("{}\t", value);
+        }
+        println!();
+
+        for row in self.fields.iter() {
+            print!("{:?}\t", row.field);
+            for elasticity in row.elasticities.iter() {
+                print!("{}\t", elasticity);
+            }
+            if let Some((up, down)) = row.status {
+                if up != StackelbergStatus::Converged || down != StackelbergStatus::Converged {
+                    print!("status: {:?} / {:?}", up, down);
+                }
+            }
+            println!();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    #[test]
+    fn test_field_scale() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, _, _) = scenario.build().unwrap();
+
+        let mut scaled = constant.clone();
+        for &field in Field::ALL.iter() {
+            field.scale(&mut scaled, 2.0);
+        }
+        assert_eq!(scaled.TVP, 2.0 * constant.TVP);
+        assert_eq!(scaled.delta_gk, constant.delta_gk);
+        for m in relation.initial_retailers() {
+            assert_eq!(scaled.TVR_m[m], 2.0 * constant.TVR_m[m]);
+            for g in relation.all_products() {
+                assert_eq!(scaled.K_mg[m][g], 2.0 * constant.K_mg[m][g]);
+                for y in relation.all_products() {
+                    assert_eq!(scaled.eA_mgy[m][g][y], 2.0 * constant.eA_mgy[m][g][y]);
+                }
+            }
+        }
+        assert_eq!(scaled.v_mgxy.array(), &(constant.v_mgxy.array() * 2.0));
+    }
+
+    #[test]
+    fn test_sensitivity_fixed() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+        let options = SensitivityOptions {
+            method: SensitivityMethod::Fixed,
+            stackelberg: StackelbergOptions {
+                max_iterations: 0,
+                ..StackelbergOptions::default()
+            },
+            ..SensitivityOptions::default()
+        };
+
+        let result = sensitivity_solve(&input, &options);
+        assert_eq!(result.fields.len(), Field::ALL.len());
+        for pair in result.fields.windows(2) {
+            assert!(pair[0].largest() >= pair[1].largest());
+        }
+        assert!(result.fields[0].largest() > 0.0);
+
+        for row in result.fields.iter() {
+            assert!(row.status.is_none());
+            let semi_elasticities = row.semi_elasticities.iter();
+            for (outcome, semi_elasticity) in result.outcomes.iter().zip(semi_elasticities) {
+                match outcome {
+                    Outcome::p_mg(..) | Outcome::A_g(..) => assert_eq!(*semi_elasticity, 0.0),
+                    _ => {}
+                }
+            }
+            // TVP only bounds the manufacturer's game, it enters no outcome.
+            if row.field == Field::TVP {
+                assert_eq!(row.largest(), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sensitivity_resolve() {
+        let text = include_str!("../../scenarios/single_retailer.toml");
+        let (relation, constant, mrgame, rrgame) = Scenario::parse(text).unwrap().build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let mut options = SensitivityOptions::default();
+        let result = sensitivity_solve(&input, &options);
+        assert_eq!(result.status, StackelbergStatus::Converged);
+        let converged = (StackelbergStatus::Converged, StackelbergStatus::Converged);
+        for row in result.fields.iter() {
+            assert_eq!(row.status, Some(converged));
+            assert!(row.semi_elasticities.iter().all(|x| x.is_finite()));
+        }
+        let K_mg = result
+            .fields
+            .iter()
+            .find(|row| row.field == Field::K_mg)
+            .unwrap();
+        assert!(K_mg.largest() > 0.0);
+
+        // One iteration does not settle the game, so no row is an elasticity.
+        options.stackelberg.max_iterations = 1;
+        let result = sensitivity_solve(&input, &options);
+        assert_eq!(result.status, StackelbergStatus::IterationLimit);
+        for row in result.fields.iter() {
+            assert!(row.semi_elasticities.iter().all(|x| x.is_nan()));
+            assert!(row.elasticities.iter().all(|x| x.is_nan()));
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use super::{mrgame_solve, retailer_nash_solve, MRGameReport};
+use super::{NashOptions, NashResult, NashStatus};
+use crate::computation::{Evaluation, Input};
+use crate::mrgame;
+use crate::relation::{Relation, RetailerMap};
+use crate::rrgame;
+
+// The manufacturer leads: for its current parameter the retailers settle on a
+// Nash equilibrium, then the manufacturer re-optimises against that response.
+// The loop stops when the manufacturer's parameter no longer moves, or when
+// either side has no answer.
+
+#[derive(Clone)]
+pub struct StackelbergOptions {
+    /// Stop once `mrgame::Parameter::max_change` of an iteration falls below
+    /// this value.
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    pub nash: NashOptions,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StackelbergStatus {
+    Converged,
+    IterationLimit,
+    /// The retailers' Nash iteration ended with this status instead of
+    /// converging.
+    NoEquilibrium(NashStatus),
+    /// The manufacturer's problem had no solution against the retailers'
+    /// response, see `MRGameReport::responded`.
+    NoResponse,
+}
+
+#[derive(Clone)]
+pub struct StackelbergIteration {
+    /// The manufacturer's answer to `nash`, its previous parameter when it
+    /// had none.
+    pub mrgame: mrgame::Parameter,
+    /// The retailers' response to the manufacturer's previous parameter.
+    pub nash: NashResult,
+    /// `None` when the retailers did not settle and the manufacturer was not
+    /// asked.
+    pub mrgame_report: Option<MRGameReport>,
+    /// `NaN` when the manufacturer had no answer.
+    pub change: f64,
+    pub NP0: f64,
+    pub NP_m: RetailerMap<f64>,
+}
+
+/// `mrgame` and `rrgame` are always a parameter of the manufacturer and the
+/// retailers' response to it; the manufacturer's last answer is in `history`.
+pub struct StackelbergResult {
+    pub status: StackelbergStatus,
+    pub mrgame: mrgame::MRGame,
+    pub rrgame: rrgame::RRGame,
+    pub history: Vec<StackelbergIteration>,
+    pub NP0: f64,
+    pub NP_m: RetailerMap<f64>,
+}
+
+impl Default for StackelbergOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.0001,
+            max_iterations: 20,
+            nash: NashOptions::default(),
+        }
+    }
+}
+
+fn profits(input: &Input) -> (f64, RetailerMap<f64>) {
+    let relation = input.relation;
+    let evaluation = Evaluation::new(input);
+
+    let mut NP_m = RetailerMap::new(relation, 0.0);
+    for m in relation.initial_retailers() {
+        NP_m[m] = evaluation.NP(m);
+    }
+
+    (evaluation.NP0(), NP_m)
+}
+
+/// Alternates the retailers' Nash response and the manufacturer's
+/// `mrgame_solve` until the manufacturer's parameter settles, starting from
+/// the parameters in `input`.
+pub fn stackelberg_solve(input: &Input, options: &StackelbergOptions) -> StackelbergResult {
+    let relation = input.relation;
+
+    let mut mrgame = input.mrgame.clone();
+    let mut rrgame = input.rrgame.clone();
+    let mut history = Vec::new();
+    let mut status = StackelbergStatus::IterationLimit;
+    let mut answer = None;
+
+    for _ in 0..options.max_iterations {
+        if let Some(parameter) = answer.take() {
+            mrgame.parameter = parameter;
+        }
+
+        let nash = {
+            let input = Input {
+                mrgame: &mrgame,
+                rrgame: &rrgame,
+                ..(*input)
+            };
+            retailer_nash_solve(&input, &options.nash)
+        };
+        rrgame.parameter = nash.parameter.clone();
+
+        let mrgame_report = if nash.status == NashStatus::Converged {
+            let input = Input {
+                mrgame: &mrgame,
+                rrgame: &rrgame,
+                ..(*input)
+            };
+            Some(mrgame_solve(&input))
+        } else {
+            status = StackelbergStatus::NoEquilibrium(nash.status);
+            None
+        };
+
+        // mrgame_solve only returns improvements, so a feasible starting point
+        // that no local maximum beats is the manufacturer's best response.
+        let new_parameter = match &mrgame_report {
+            Some(report) if report.responded() => match &report.parameter {
+                Some(parameter) => Some(parameter.clone()),
+                None => Some(mrgame.parameter.clone()),
+            },
+            Some(_) => {
+                status = StackelbergStatus::NoResponse;
+                None
+            }
+            None => None,
+        };
+
+        let (change, iteration_mrgame) = match &new_parameter {
+            Some(parameter) => (
+                mrgame.parameter.max_change(relation, parameter),
+                mrgame::MRGame {
+                    parameter: parameter.clone(),
+                    decision: mrgame.decision.clone(),
+                },
+            ),
+            None => (f64::NAN, mrgame.clone()),
+        };
+        let (NP0, NP_m) = profits(&Input {
+            mrgame: &iteration_mrgame,
+            rrgame: &rrgame,
+            ..(*input)
+        });
+        history.push(StackelbergIteration {
+            mrgame: iteration_mrgame.parameter,
+            nash,
+            mrgame_report,
+            change,
+            NP0,
+            NP_m,
+        });
+
+        if new_parameter.is_none() {
+            break;
+        }
+
+        if change <= options.tolerance {
+            status = StackelbergStatus::Converged;
+            break;
+        }
+
+        answer = new_parameter;
+    }
+
+    let (NP0, NP_m) = profits(&Input {
+        mrgame: &mrgame,
+        rrgame: &rrgame,
+        ..(*input)
+    });
+
+    StackelbergResult {
+        status,
+        mrgame,
+        rrgame,
+        history,
+        NP0,
+        NP_m,
+    }
+}
+
+impl StackelbergResult {
+    /// Prints the solver reports of every iteration.
+    pub fn show_reports(&self, relation: &Relation) {
+        for (index, iteration) in self.history.iter().enumerate() {
+            println!("Iteration {}", index);
+            for sweep in iteration.nash.trace.iter() {
+                for report in sweep.reports.iter() {
+                    print!("{}", report.display(relation));
+                }
+            }
+            if let Some(report) = &iteration.mrgame_report {
+                print!("{}", report.display(relation));
+            }
+        }
+    }
+
+    pub fn show(&self, relation: &Relation) {
+        println!(
+            "Stackelberg: {:?} after {} iterations",
+            self.status,
+            self.history.len()
+        );
+        for (index, iteration) in self.history.iter().enumerate() {
+            print!(
+                "{}\tchange: {}\tNash: {:?} after {} sweeps\tNP0: {}",
+                index,
+                iteration.change,
+                iteration.nash.status,
+                iteration.nash.trace.len(),
+                iteration.NP0
+            );
+            for m in relation.initial_retailers() {
+                print!("\tNP {}: {}", relation.retailer_label(m), iteration.NP_m[m]);
+            }
+            println!();
+        }
+
+        self.mrgame.parameter.show(relation);
+        self.rrgame.parameter.show_p_mg(relation);
+        self.rrgame.parameter.show_a_mg(relation);
+
+        println!("NP0 = {}", self.NP0);
+        for m in relation.initial_retailers() {
+            println!("NP {} = {}", relation.retailer_label(m), self.NP_m[m]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounds::Bound;
+    use crate::computation;
+    use crate::scenario::Scenario;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_no_iterations() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let options = StackelbergOptions {
+            max_iterations: 0,
+            ..StackelbergOptions::default()
+        };
+        let result = stackelberg_solve(&input, &options);
+        assert_eq!(result.status, StackelbergStatus::IterationLimit);
+        assert!(result.history.is_empty());
+        let NP0 = computation::NP0(&input);
+        assert_approx_eq!(result.NP0, NP0, 1e-9 * NP0.abs());
+        for m in relation.initial_retailers() {
+            let NP = computation::NP(&input, m);
+            assert_approx_eq!(result.NP_m[m], NP, 1e-9 * NP.abs());
+        }
+        assert_eq!(
+            result
+                .mrgame
+                .parameter
+                .max_change(&relation, &mrgame.parameter),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_example() {
+        let scenario = Scenario::parse(include_str!("../../scenarios/example.toml")).unwrap();
+        let (relation, constant, mrgame, rrgame) = scenario.build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        // The manufacturer's answers swing between two regimes until one
+        // retailer response leaves it without a feasible answer, well within
+        // the iteration cap.
+        let options = StackelbergOptions::default();
+        let result = stackelberg_solve(&input, &options);
+        assert_eq!(result.status, StackelbergStatus::NoResponse);
+        assert!(result.history.len() < options.max_iterations);
+        let last = result.history.last().unwrap();
+        assert!(!last.mrgame_report.as_ref().unwrap().responded());
+        for iteration in result.history.iter() {
+            assert_eq!(iteration.nash.status, NashStatus::Converged);
+        }
+    }
+
+    #[test]
+    fn test_converged() {
+        let text = include_str!("../../scenarios/single_retailer.toml");
+        let (relation, constant, mrgame, rrgame) = Scenario::parse(text).unwrap().build().unwrap();
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let options = StackelbergOptions::default();
+        let result = stackelberg_solve(&input, &options);
+        assert_eq!(result.status, StackelbergStatus::Converged);
+        // The scenario starts away from the equilibrium.
+        assert!(result.history[0].change > 0.5);
+        for iteration in result.history.iter() {
+            assert_eq!(iteration.nash.status, NashStatus::Converged);
+            assert!(iteration.mrgame_report.as_ref().unwrap().responded());
+        }
+
+        // The result pairs the manufacturer's last parameter with the
+        // retailers' response to it, one tolerance away from its last answer.
+        let last = result.history.last().unwrap();
+        assert!(last.change <= options.tolerance);
+        assert_eq!(
+            result.mrgame.parameter.max_change(&relation, &last.mrgame),
+            last.change
+        );
+        assert_eq!(
+            result
+                .rrgame
+                .parameter
+                .max_change(&relation, &last.nash.parameter),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_no_response() {
+        let text = include_str!("../../scenarios/single_retailer.toml");
+        let (relation, mut constant, mrgame, rrgame) =
+            Scenario::parse(text).unwrap().build().unwrap();
+
+        // Held at half the start, the orders fall short of both materials'
+        // BOM demand, so the manufacturer has no feasible answer.
+        for (s, l) in relation.supplier_materials() {
+            let drm = 0.5 * mrgame.parameter.drm_sl[*s][*l];
+            constant.bounds.drm_sl[*s][*l] = Bound::new(drm, drm);
+        }
+        let input = Input {
+            relation: &relation,
+            constant: &constant,
+            mrgame: &mrgame,
+            rrgame: &rrgame,
+        };
+
+        let result = stackelberg_solve(&input, &StackelbergOptions::default());
+        assert_eq!(result.status, StackelbergStatus::NoResponse);
+        assert_eq!(result.history.len(), 1);
+        let iteration = &result.history[0];
+        assert!(iteration.change.is_nan());
+        assert!(!iteration.mrgame_report.as_ref().unwrap().responded());
+        assert_eq!(
+            result
+                .mrgame
+                .parameter
+                .max_change(&relation, &mrgame.parameter),
+            0.0
+        );
+        assert_eq!(
+            result
+                .rrgame
+                .parameter
+                .max_change(&relation, &iteration.nash.parameter),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use crate::computation::comp_generic::Variable;
+use crate::relation::{Constant, Relation};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// A pair list refers to an id outside the range set up by the
+    /// `supplier_material_pairs`, `retailer_product_pairs` and
+    /// `alternative_module_pairs` lists.
+    UnknownEntity {
+        pairs: &'static str,
+        entity: &'static str,
+        id: usize,
+    },
+    ModuleCount {
+        alternative: usize,
+        count: usize,
+    },
+    UnsoldProduct {
+        alternative: usize,
+        product: usize,
+    },
+    UndeclaredDelta {
+        product: usize,
+        alternative: usize,
+        value: usize,
+    },
+    UndeclaredSigma {
+        alternative: usize,
+        material: usize,
+        value: usize,
+    },
+    NonFinitePCR {
+        supplier: usize,
+        material: usize,
+        value: f64,
+    },
+    UndeclaredPCR {
+        supplier: usize,
+        material: usize,
+        value: f64,
+    },
+    /// A variable whose lower bound exceeds its upper bound, or one of them
+    /// is NaN.
+    EmptyBound {
+        variable: Variable,
+        lower: f64,
+        upper: f64,
+    },
+}
+
+fn variable_name(variable: Variable) -> String {
+    match variable {
+        Variable::p_mg(m, g) => format!("p_mg[{}][{}]", m.id, g.id),
+        Variable::a_mg(m, g) => format!("a_mg[{}][{}]", m.id, g.id),
+        Variable::A_g(g) => format!("A_g[{}]", g.id),
+        Variable::rho_g(g) => format!("rho_g[{}]", g.id),
+        Variable::c_m(m) => format!("c_m[{}]", m.id),
+        Variable::crm_s(s) => format!("crm_s[{}]", s.id),
+        Variable::drm_sl(s, l) => format!("drm_sl[{}][{}]", s.id, l.id),
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::UnknownEntity { pairs, entity, id } => {
+                write!(f, "{}: unknown {} {}", pairs, entity, id)
+            }
+            ValidationError::ModuleCount { alternative, count } => write!(
+                f,
+                "alternative_modules: alternative {} belongs to {} modules, expected exactly 1",
+                alternative, count
+            ),
+            ValidationError::UnsoldProduct {
+                alternative,
+                product,
+            } => write!(
+                f,
+                "alternative_products: product {} of alternative {} is not in retailer_products",
+                product, alternative
+            ),
+            ValidationError::UndeclaredDelta {
+                product,
+                alternative,
+                value,
+            } => write!(
+                f,
+                "delta_gk[{}][{}] = {} but alternative {} is not declared for product {}",
+                product, alternative, value, alternative, product
+            ),
+            ValidationError::UndeclaredSigma {
+                alternative,
+                material,
+                value,
+            } => write!(
+                f,
+                "sigma_kl[{}][{}] = {} but material {} is not declared for alternative {}",
+                alternative, material, value, material, alternative
+            ),
+            ValidationError::NonFinitePCR {
+                supplier,
+                material,
+                value,
+            } => write!(
+                f,
+                "PCR_sl[{}][{}] = {} but supplier {} sells material {}",
+                supplier, material, value, supplier, material
+            ),
+            ValidationError::UndeclaredPCR {
+                supplier,
+                material,
+                value,
+            } => write!(
+                f,
+                "PCR_sl[{}][{}] = {} but supplier {} does not sell material {}",
+                supplier, material, value, supplier, material
+            ),
+            ValidationError::EmptyBound {
+                variable,
+                lower,
+                upper,
+            } => write!(
+                f,
+                "bounds of {}: lower bound {} is not at most upper bound {}",
+                variable_name(*variable),
+                lower,
+                upper
+            ),
+        }
+    }
+}
+
+fn check_id(
+    errors: &mut Vec<ValidationError>,
+    pairs: &'static str,
+    entity: &'static str,
+    id: usize,
+    count: usize,
+) -> bool {
+    if id < count {
+        return true;
+    }
+    errors.push(ValidationError::UnknownEntity { pairs, entity, id });
+    false
+}
+
+/// Checks that the constants agree with the pair lists of `relation`.
+/// Returns every problem found instead of stopping at the first one.
+pub fn validate(relation: &Relation, constant: &Constant) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+
+    let material_count = relation.all_materials().count();
+    let product_count = relation.all_products().count();
+    let alternative_count = relation.all_alternatives().count();
+
+    let mut material_alternatives = Vec::new();
+    for (l, k) in relation.material_alternatives().iter() {
+        let known_l = check_id(
+            &mut errors,
+            "material_alternatives",
+            "material",
+            l.id,
+            material_count,
+        );
+        let known_k = check_id(
+            &mut errors,
+            "material_alternatives",
+            "alternative",
+            k.id,
+            alternative_count,
+        );
+        if known_l && known_k {
+            material_alternatives.push((l.id, k.id));
+        }
+    }
+
+    let mut alternative_products = Vec::new();
+    for (k, g) in relation.alternative_products().iter() {
+        let known_k = check_id(
+            &mut errors,
+            "alternative_products",
+            "alternative",
+            k.id,
+            alternative_count,
+        );
+        let known_g = check_id(
+            &mut errors,
+            "alternative_products",
+            "product",
+            g.id,
+            product_count,
+        );
+        if known_k && known_g {
+            alternative_products.push((k.id, g.id));
+        }
+    }
+
+    for k in relation.all_alternatives() {
+        let count = relation
+            .alternative_modules()
+            .iter()
+            .filter(|p| p.0.id == k.id)
+            .count();
+        if count != 1 {
+            errors.push(ValidationError::ModuleCount {
+                alternative: k.id,
+                count,
+            });
+        }
+    }
+
+    for (k, g) in alternative_products.iter() {
+        if !relation.retailer_products().iter().any(|p| p.1.id == *g) {
+            errors.push(ValidationError::UnsoldProduct {
+                alternative: *k,
+                product: *g,
+            });
+        }
+    }
+
+    for g in relation.all_products() {
+        for k in relation.all_alternatives() {
+            let value = constant.delta_gk[g][k];
+            if value != 0 && !alternative_products.contains(&(k.id, g.id)) {
+                errors.push(ValidationError::UndeclaredDelta {
+                    product: g.id,
+                    alternative: k.id,
+                    value,
+                });
+            }
+        }
+    }
+
+    for k in relation.all_alternatives() {
+        for l in relation.all_materials() {
+            let value = constant.sigma_kl[k][l];
+            if value != 0 && !material_alternatives.contains(&(l.id, k.id)) {
+                errors.push(ValidationError::UndeclaredSigma {
+                    alternative: k.id,
+                    material: l.id,
+                    value,
+                });
+            }
+        }
+    }
+
+    for s in relation.all_suppliers() {
+        for l in relation.all_materials() {
+            let value = constant.PCR_sl[s][l];
+            let sells = relation
+                .supplier_materials()
+                .iter()
+                .any(|p| p.0.id == s.id && p.1.id == l.id);
+            if sells && !value.is_finite() {
+                errors.push(ValidationError::NonFinitePCR {
+                    supplier: s.id,
+                    material: l.id,
+                    value,
+                });
+            } else if !sells && value.is_finite() {
+                errors.push(ValidationError::UndeclaredPCR {
+                    supplier: s.id,
+                    material: l.id,
+                    value,
+                });
+            }
+        }
+    }
+
+    for (variable, bound) in constant.bounds.all(relation) {
+        if bound.is_empty() {
+            errors.push(ValidationError::EmptyBound {
+                variable,
+                lower: bound.lower,
+                upper: bound.upper,
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relation::{Alternative, Material, Product, Retailer};
+    use crate::scenario::Scenario;
+
+    const EXAMPLE: &str = include_str!("../scenarios/example.toml");
+
+    fn example(text: &str) -> (Relation, Constant) {
+        let (relation, constant, _, _) = Scenario::parse(text).unwrap().build().unwrap();
+        (relation, constant)
+    }
+
+    #[test]
+    fn test_example_is_valid() {
+        let (relation, constant) = example(EXAMPLE);
+        assert_eq!(validate(&relation, &constant), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_every_error() {
+        let text = EXAMPLE
+            .replace(
+                "alternative_modules = [[0, 0], [1, 0], [2, 0], [3, 1], [4, 1], [5, 1]]",
+                "alternative_modules = [[0, 0], [0, 1], [2, 0], [3, 1], [4, 1], [5, 1]]",
+            )
+            .replace(
+                "alternative_products = [[0, 0], [3, 0],",
+                "alternative_products = [[0, 0], [3, 0], [0, 7],",
+            )
+            .replace("[1.0, inf, 1.2]", "[1.0, 3.0, inf]")
+            + "\n[bounds.c_m]\nlower = [0.5, 0.01]\nupper = [0.2, inf]\n";
+        let (mut relation, mut constant) = example(&text);
+        relation
+            .retailer_product_pairs(&[(0, 0), (0, 1), (0, 3), (1, 0), (1, 3)])
+            .unwrap();
+        constant.sigma_kl[Alternative { id: 5 }][Material { id: 0 }] = 4;
+
+        let errors = validate(&relation, &constant).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::UnknownEntity {
+                    pairs: "alternative_products",
+                    entity: "product",
+                    id: 7,
+                },
+                ValidationError::ModuleCount {
+                    alternative: 0,
+                    count: 2,
+                },
+                ValidationError::ModuleCount {
+                    alternative: 1,
+                    count: 0,
+                },
+                ValidationError::UnsoldProduct {
+                    alternative: 2,
+                    product: 2,
+                },
+                ValidationError::UnsoldProduct {
+                    alternative: 3,
+                    product: 2,
+                },
+                ValidationError::UndeclaredSigma {
+                    alternative: 5,
+                    material: 0,
+                    value: 4,
+                },
+                ValidationError::UndeclaredPCR {
+                    supplier: 1,
+                    material: 1,
+                    value: 3.0,
+                },
+                ValidationError::NonFinitePCR {
+                    supplier: 1,
+                    material: 2,
+                    value: f64::INFINITY,
+                },
+                ValidationError::EmptyBound {
+                    variable: Variable::c_m(Retailer { id: 0 }),
+                    lower: 0.5,
+                    upper: 0.2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_undeclared_delta() {
+        let (relation, mut constant) = example(EXAMPLE);
+        constant.delta_gk[Product { id: 1 }][Alternative { id: 0 }] = 2;
+
+        let errors = validate(&relation, &constant).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![ValidationError::UndeclaredDelta {
+                product: 1,
+                alternative: 0,
+                value: 2,
+            }]
+        );
+    }
+}